@@ -0,0 +1,326 @@
+use crate::expr::{self, Expr};
+use crate::json::Json;
+use crate::stmt::{self, CatchClause, LoxFunctionNode, Stmt};
+use crate::token::{Literal, Token};
+use std::rc::Rc;
+
+/// Conversion of tokens and syntax trees to JSON for external tooling. Nodes
+/// are objects whose `"type"` names the variant, with a field per child.
+pub(crate) trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+impl ToJson for Literal {
+    fn to_json(&self) -> Json {
+        match self {
+            Literal::String(s) => Json::String(s.clone()),
+            Literal::Number(n) => Json::Number(*n),
+            Literal::Bool(b) => Json::Bool(*b),
+            Literal::Nil => Json::Null,
+        }
+    }
+}
+
+impl ToJson for Token {
+    fn to_json(&self) -> Json {
+        let mut members = vec![
+            field("type", Json::String(format!("{:?}", self.token_type))),
+            field("lexeme", Json::String(self.lexeme.clone())),
+        ];
+        if let Some(literal) = &self.literal {
+            members.push(field("literal", literal.to_json()));
+        }
+        members.push(field("line", Json::Number(self.line as f64)));
+        members.push(field("column", Json::Number(self.column as f64)));
+        Json::Object(members)
+    }
+}
+
+impl ToJson for Expr {
+    fn to_json(&self) -> Json {
+        self.accept(&mut AstJson)
+    }
+}
+
+impl ToJson for Stmt {
+    fn to_json(&self) -> Json {
+        self.accept(&mut AstJson)
+    }
+}
+
+impl ToJson for LoxFunctionNode {
+    fn to_json(&self) -> Json {
+        node(
+            "Function",
+            vec![
+                field("name", self.name.to_json()),
+                field("params", self.params.to_json()),
+                field("body", self.body.to_json()),
+            ],
+        )
+    }
+}
+
+impl ToJson for CatchClause {
+    fn to_json(&self) -> Json {
+        Json::Object(vec![
+            field("name", self.name.to_json()),
+            field("body", self.body.to_json()),
+        ])
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> Json {
+        Json::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+fn field(name: &str, value: Json) -> (String, Json) {
+    (name.to_string(), value)
+}
+
+fn node(kind: &str, mut fields: Vec<(String, Json)>) -> Json {
+    fields.insert(0, field("type", Json::String(kind.to_string())));
+    Json::Object(fields)
+}
+
+fn optional<T: ToJson + ?Sized>(item: Option<&T>) -> Json {
+    item.map_or(Json::Null, ToJson::to_json)
+}
+
+struct AstJson;
+
+impl expr::Visitor<Json> for AstJson {
+    fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Json {
+        node(
+            "Binary",
+            vec![
+                field("left", left.to_json()),
+                field("operator", operator.to_json()),
+                field("right", right.to_json()),
+            ],
+        )
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> Json {
+        node("Grouping", vec![field("expression", expression.to_json())])
+    }
+
+    fn visit_literal_expr(&mut self, value: &Literal) -> Json {
+        node("Literal", vec![field("value", value.to_json())])
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Json {
+        node(
+            "Unary",
+            vec![
+                field("operator", operator.to_json()),
+                field("right", right.to_json()),
+            ],
+        )
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> Json {
+        node(
+            "Call",
+            vec![
+                field("callee", callee.to_json()),
+                field("paren", paren.to_json()),
+                field("arguments", arguments.to_json()),
+            ],
+        )
+    }
+
+    fn visit_variable_expr(&mut self, name: &Token) -> Json {
+        node("Variable", vec![field("name", name.to_json())])
+    }
+
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Json {
+        node(
+            "Assign",
+            vec![
+                field("name", name.to_json()),
+                field("value", value.to_json()),
+            ],
+        )
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Json {
+        node(
+            "Logical",
+            vec![
+                field("left", left.to_json()),
+                field("operator", operator.to_json()),
+                field("right", right.to_json()),
+            ],
+        )
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Json {
+        node(
+            "Get",
+            vec![
+                field("object", object.to_json()),
+                field("name", name.to_json()),
+            ],
+        )
+    }
+
+    fn visit_list_expr(&mut self, elements: &[Expr]) -> Json {
+        node("List", vec![field("elements", elements.to_json())])
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> Json {
+        node(
+            "Index",
+            vec![
+                field("object", object.to_json()),
+                field("bracket", bracket.to_json()),
+                field("index", index.to_json()),
+            ],
+        )
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> Json {
+        node(
+            "SetIndex",
+            vec![
+                field("object", object.to_json()),
+                field("bracket", bracket.to_json()),
+                field("index", index.to_json()),
+                field("value", value.to_json()),
+            ],
+        )
+    }
+}
+
+impl stmt::Visitor<Json> for AstJson {
+    fn visit_expression_stmt(&mut self, expr: &Expr) -> Json {
+        node("Expression", vec![field("expression", expr.to_json())])
+    }
+
+    fn visit_print_stmt(&mut self, expr: &Expr) -> Json {
+        node("Print", vec![field("expression", expr.to_json())])
+    }
+
+    fn visit_return_stmt(&mut self, keyword: &Token, value: &Expr) -> Json {
+        node(
+            "Return",
+            vec![
+                field("keyword", keyword.to_json()),
+                field("value", value.to_json()),
+            ],
+        )
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> Json {
+        node(
+            "Var",
+            vec![
+                field("name", name.to_json()),
+                field("initializer", optional(initializer)),
+            ],
+        )
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Json {
+        node("Block", vec![field("statements", statements.to_json())])
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        _keyword: &Token,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Json {
+        node(
+            "If",
+            vec![
+                field("condition", condition.to_json()),
+                field("thenBranch", then_branch.to_json()),
+                field("elseBranch", optional(else_branch)),
+            ],
+        )
+    }
+
+    fn visit_while_stmt(&mut self, _keyword: &Token, condition: &Expr, body: &Stmt) -> Json {
+        node(
+            "While",
+            vec![
+                field("condition", condition.to_json()),
+                field("body", body.to_json()),
+            ],
+        )
+    }
+
+    fn visit_function_stmt(&mut self, stmt: Rc<LoxFunctionNode>) -> Json {
+        stmt.to_json()
+    }
+
+    fn visit_throw_stmt(&mut self, keyword: &Token, value: &Expr) -> Json {
+        node(
+            "Throw",
+            vec![
+                field("keyword", keyword.to_json()),
+                field("value", value.to_json()),
+            ],
+        )
+    }
+
+    fn visit_import_stmt(&mut self, keyword: &Token, path: &Token, alias: &Token) -> Json {
+        node(
+            "Import",
+            vec![
+                field("keyword", keyword.to_json()),
+                field("path", path.to_json()),
+                field("alias", alias.to_json()),
+            ],
+        )
+    }
+
+    fn visit_from_import_stmt(&mut self, keyword: &Token, path: &Token, names: &[Token]) -> Json {
+        node(
+            "FromImport",
+            vec![
+                field("keyword", keyword.to_json()),
+                field("path", path.to_json()),
+                field("names", names.to_json()),
+            ],
+        )
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        body: &[Stmt],
+        catch_clause: Option<&CatchClause>,
+        finally_body: Option<&[Stmt]>,
+    ) -> Json {
+        node(
+            "Try",
+            vec![
+                field("body", body.to_json()),
+                field("catchClause", optional(catch_clause)),
+                field("finallyBody", optional(finally_body)),
+            ],
+        )
+    }
+
+    fn visit_test_stmt(&mut self, keyword: &Token, name: &Token, body: &[Stmt]) -> Json {
+        node(
+            "Test",
+            vec![
+                field("keyword", keyword.to_json()),
+                field("name", name.to_json()),
+                field("body", body.to_json()),
+            ],
+        )
+    }
+}
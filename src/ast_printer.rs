@@ -1,16 +1,25 @@
-use crate::expr::{Expr, Visitor};
-use crate::token::Literal;
+use crate::expr::{self, Expr};
+use crate::stmt::{self, CatchClause, LoxFunctionNode, Stmt};
+use crate::token::{Literal, Token};
+use std::rc::Rc;
 
-pub(crate) struct ExprVisitor;
+/// Prints syntax trees as S-expressions, one top-level statement per line.
+pub(crate) struct AstPrinter;
 
-impl ExprVisitor {
-    #[allow(dead_code)]
-    pub(crate) fn print(&self, expr: &Expr) -> String {
+impl AstPrinter {
+    pub(crate) fn print_program(&mut self, statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|stmt| format!("{}\n", stmt.accept(self)))
+            .collect()
+    }
+
+    pub(crate) fn print_expr(&mut self, expr: &Expr) -> String {
         expr.accept(self)
     }
-    fn parenthesize(&self, name: &str, exprs: Vec<&Expr>) -> String {
-        let mut result = String::from("(");
-        result.push_str(name);
+
+    fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) -> String {
+        let mut result = format!("({}", name);
         for expr in exprs {
             result.push(' ');
             result.push_str(&expr.accept(self));
@@ -18,42 +27,186 @@ impl ExprVisitor {
         result.push(')');
         result
     }
-}
 
-impl Visitor<String> for ExprVisitor {
-    fn visit_binary(&self, left: &Expr, operator: &crate::token::Token, right: &Expr) -> String {
-        self.parenthesize(operator.lexeme.as_str(), vec![left, right])
+    /// `(name part...)` where the parts are already printed.
+    fn list(name: &str, parts: Vec<String>) -> String {
+        let mut result = format!("({}", name);
+        for part in parts {
+            result.push(' ');
+            result.push_str(&part);
+        }
+        result.push(')');
+        result
     }
 
-    fn visit_grouping(&self, expression: &Expr) -> String {
-        self.parenthesize("group", vec![expression])
+    fn block(&mut self, name: &str, statements: &[Stmt]) -> String {
+        let parts = statements.iter().map(|stmt| stmt.accept(self)).collect();
+        Self::list(name, parts)
     }
+}
 
-    fn visit_literal(&self, value: &Literal) -> String {
-        if let Literal::Nil = value {
-            return String::from("nil");
-        }
-        value.to_string()
+/// String literals are quoted so they can be told apart from names.
+fn literal(value: &Literal) -> String {
+    match value {
+        Literal::String(s) => format!("{:?}", s),
+        value => value.to_string(),
     }
+}
 
-    fn visit_unary(&self, operator: &crate::token::Token, right: &Expr) -> String {
-        self.parenthesize(operator.lexeme.as_str(), vec![right])
+fn string_token(token: &Token) -> String {
+    match &token.literal {
+        Some(value) => literal(value),
+        None => token.lexeme.clone(),
     }
 }
 
-#[allow(dead_code)]
-pub(crate) fn print(expr: &Expr) -> String {
-    expr.accept(&ExprVisitor)
+impl expr::Visitor<String> for AstPrinter {
+    fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        self.parenthesize(&operator.lexeme, &[left, right])
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> String {
+        self.parenthesize("group", &[expression])
+    }
+
+    fn visit_literal_expr(&mut self, value: &Literal) -> String {
+        literal(value)
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> String {
+        self.parenthesize(&operator.lexeme, &[right])
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> String {
+        let exprs: Vec<&Expr> = std::iter::once(callee).chain(arguments).collect();
+        self.parenthesize("call", &exprs)
+    }
+
+    fn visit_variable_expr(&mut self, name: &Token) -> String {
+        name.lexeme.clone()
+    }
+
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> String {
+        Self::list("=", vec![name.lexeme.clone(), value.accept(self)])
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        self.parenthesize(&operator.lexeme, &[left, right])
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> String {
+        Self::list(".", vec![object.accept(self), name.lexeme.clone()])
+    }
+
+    fn visit_list_expr(&mut self, elements: &[Expr]) -> String {
+        let exprs: Vec<&Expr> = elements.iter().collect();
+        self.parenthesize("list", &exprs)
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, _bracket: &Token, index: &Expr) -> String {
+        self.parenthesize("index", &[object, index])
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> String {
+        self.parenthesize("index=", &[object, index, value])
+    }
 }
 
-#[allow(dead_code)]
-fn parenthesize(name: &str, exprs: Vec<&Expr>) -> String {
-    let mut result = String::from("(");
-    result.push_str(name);
-    for expr in exprs {
-        result.push(' ');
-        result.push_str(&expr.accept(&ExprVisitor));
+impl stmt::Visitor<String> for AstPrinter {
+    fn visit_expression_stmt(&mut self, expr: &Expr) -> String {
+        self.parenthesize("expr", &[expr])
+    }
+
+    fn visit_print_stmt(&mut self, expr: &Expr) -> String {
+        self.parenthesize("print", &[expr])
+    }
+
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: &Expr) -> String {
+        self.parenthesize("return", &[value])
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> String {
+        let mut parts = vec![name.lexeme.clone()];
+        parts.extend(initializer.map(|expr| expr.accept(self)));
+        Self::list("var", parts)
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> String {
+        self.block("block", statements)
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        _keyword: &Token,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> String {
+        let mut parts = vec![condition.accept(self), then_branch.accept(self)];
+        parts.extend(else_branch.map(|stmt| stmt.accept(self)));
+        Self::list("if", parts)
+    }
+
+    fn visit_while_stmt(&mut self, _keyword: &Token, condition: &Expr, body: &Stmt) -> String {
+        Self::list("while", vec![condition.accept(self), body.accept(self)])
+    }
+
+    fn visit_function_stmt(&mut self, stmt: Rc<LoxFunctionNode>) -> String {
+        let params: Vec<String> = stmt.params.iter().map(|p| p.lexeme.clone()).collect();
+        let mut parts = vec![stmt.name.lexeme.clone(), format!("({})", params.join(" "))];
+        parts.extend(stmt.body.iter().map(|stmt| stmt.accept(self)));
+        Self::list("fun", parts)
+    }
+
+    fn visit_throw_stmt(&mut self, _keyword: &Token, value: &Expr) -> String {
+        self.parenthesize("throw", &[value])
+    }
+
+    fn visit_import_stmt(&mut self, _keyword: &Token, path: &Token, alias: &Token) -> String {
+        Self::list(
+            "import",
+            vec![string_token(path), "as".to_string(), alias.lexeme.clone()],
+        )
+    }
+
+    fn visit_from_import_stmt(
+        &mut self,
+        _keyword: &Token,
+        path: &Token,
+        names: &[Token],
+    ) -> String {
+        let mut parts = vec![string_token(path), "import".to_string()];
+        parts.extend(names.iter().map(|name| name.lexeme.clone()));
+        Self::list("from", parts)
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        body: &[Stmt],
+        catch_clause: Option<&CatchClause>,
+        finally_body: Option<&[Stmt]>,
+    ) -> String {
+        let mut parts = vec![self.block("block", body)];
+        if let Some(clause) = catch_clause {
+            let mut catch = vec![clause.name.lexeme.clone()];
+            catch.extend(clause.body.iter().map(|stmt| stmt.accept(self)));
+            parts.push(Self::list("catch", catch));
+        }
+        if let Some(statements) = finally_body {
+            parts.push(self.block("finally", statements));
+        }
+        Self::list("try", parts)
+    }
+
+    fn visit_test_stmt(&mut self, _keyword: &Token, name: &Token, body: &[Stmt]) -> String {
+        let mut parts = vec![string_token(name)];
+        parts.extend(body.iter().map(|stmt| stmt.accept(self)));
+        Self::list("test", parts)
     }
-    result.push(')');
-    result
 }
@@ -0,0 +1,81 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Where the time natives get the time from. Embedders and tests can install
+/// their own with `Interpreter::set_clock` to make time deterministic.
+pub(crate) trait Clock {
+    /// Wall-clock time since the Unix epoch.
+    fn wall(&self) -> Duration;
+    /// Monotonic time since some fixed point, for measuring intervals.
+    fn monotonic(&self) -> Duration;
+    /// Waits for `duration`, returning false without waiting if the clock
+    /// cannot count that far.
+    fn sleep(&self, duration: Duration) -> bool;
+}
+
+/// The real clock.
+pub(crate) struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub(crate) fn new() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn wall(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> bool {
+        std::thread::sleep(duration);
+        true
+    }
+}
+
+/// A clock that stands still except when slept on, which advances it
+/// instantly.
+pub(crate) struct FixedClock {
+    wall: Cell<Duration>,
+    monotonic: Cell<Duration>,
+}
+
+impl FixedClock {
+    pub(crate) fn new(wall: Duration) -> Self {
+        FixedClock {
+            wall: Cell::new(wall),
+            monotonic: Cell::new(Duration::ZERO),
+        }
+    }
+}
+
+impl Clock for FixedClock {
+    fn wall(&self) -> Duration {
+        self.wall.get()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.monotonic.get()
+    }
+
+    fn sleep(&self, duration: Duration) -> bool {
+        let wall = self.wall.get().checked_add(duration);
+        let monotonic = self.monotonic.get().checked_add(duration);
+        let (Some(wall), Some(monotonic)) = (wall, monotonic) else {
+            return false;
+        };
+        self.wall.set(wall);
+        self.monotonic.set(monotonic);
+        true
+    }
+}
@@ -0,0 +1,335 @@
+use crate::execution_hook::ExecutionHook;
+use crate::expr::{self, Expr};
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::stmt::{self, CatchClause, LoxFunctionNode, Stmt};
+use crate::token::{Literal, Token};
+use crate::Lox;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// A branch point by the line and column of its keyword or operator.
+type BranchKey = (i32, i32);
+
+/// What ran in one file.
+#[derive(Default)]
+struct Hits {
+    /// Statements run, by the line they start on.
+    lines: HashMap<i32, u64>,
+    /// How often each condition came out truthy and falsy.
+    branches: HashMap<BranchKey, [u64; 2]>,
+}
+
+/// The statement lines and branch points of a file, whether they ran or not.
+#[derive(Default)]
+struct Points {
+    lines: BTreeSet<i32>,
+    branches: BTreeSet<BranchKey>,
+}
+
+/// The `--coverage` instrumentation. It counts the statements run on each
+/// line and which way each `if`, `while`, `and` and `or` went, then writes
+/// them as an LCOV file and a summary per file to stderr. Branch 0 of a
+/// condition is it holding, branch 1 it failing.
+pub(crate) struct Coverage {
+    output: PathBuf,
+    files: HashMap<Rc<Path>, Hits>,
+}
+
+impl Coverage {
+    /// Records coverage, writing the LCOV file to `output` when the script
+    /// finishes.
+    pub(crate) fn new(output: PathBuf) -> Self {
+        Coverage {
+            output,
+            files: HashMap::new(),
+        }
+    }
+
+    fn hits(&mut self, interpreter: &Interpreter) -> Option<&mut Hits> {
+        let file = interpreter.shared_current_file()?;
+        Some(self.files.entry(file).or_default())
+    }
+
+    /// The LCOV records and the summary rows, with the files in order.
+    fn report(&self) -> (String, String) {
+        let mut files: Vec<_> = self.files.iter().collect();
+        files.sort_by_key(|&(path, _)| path);
+
+        let mut lcov = String::new();
+        let mut summary = String::from("Coverage:\n       lines         branches  file\n");
+        let mut total = [0; 4];
+        for (path, hits) in files {
+            let mut points = std::fs::read_to_string(path)
+                .map(points)
+                .unwrap_or_default();
+            points.lines.extend(hits.lines.keys());
+            points.branches.extend(hits.branches.keys());
+
+            lcov.push_str(&format!("TN:\nSF:{}\n", path.display()));
+            let mut lines_hit = 0;
+            for line in &points.lines {
+                let count = hits.lines.get(line).copied().unwrap_or(0);
+                lines_hit += (count > 0) as usize;
+                lcov.push_str(&format!("DA:{},{}\n", line, count));
+            }
+            lcov.push_str(&format!("LF:{}\nLH:{}\n", points.lines.len(), lines_hit));
+
+            let mut branches_hit = 0;
+            let mut block = 0;
+            let mut previous_line = 0;
+            for key @ (line, _) in &points.branches {
+                block = if *line == previous_line { block + 1 } else { 0 };
+                previous_line = *line;
+                let counts = hits.branches.get(key);
+                for (branch, count) in counts.copied().unwrap_or_default().iter().enumerate() {
+                    let taken = match counts {
+                        Some(_) => count.to_string(),
+                        None => "-".to_string(),
+                    };
+                    branches_hit += (*count > 0) as usize;
+                    lcov.push_str(&format!("BRDA:{},{},{},{}\n", line, block, branch, taken));
+                }
+            }
+            let branches = points.branches.len() * 2;
+            lcov.push_str(&format!("BRF:{}\nBRH:{}\n", branches, branches_hit));
+            lcov.push_str("end_of_record\n");
+
+            let counts = [lines_hit, points.lines.len(), branches_hit, branches];
+            summary.push_str(&row(counts, &path.display().to_string()));
+            for (total, count) in total.iter_mut().zip(counts) {
+                *total += count;
+            }
+        }
+        if self.files.len() > 1 {
+            summary.push_str(&row(total, "total"));
+        }
+        (lcov, summary)
+    }
+}
+
+impl ExecutionHook for Coverage {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+    ) -> Result<(), Box<dyn Error>> {
+        // Its statements are counted instead.
+        if matches!(stmt, Stmt::Block { .. }) {
+            return Ok(());
+        }
+        if let Some(hits) = self.hits(interpreter) {
+            *hits.lines.entry(stmt.line()).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    fn branch(&mut self, interpreter: &mut Interpreter, token: &Token, truthy: bool) {
+        if let Some(hits) = self.hits(interpreter) {
+            let counts = hits.branches.entry((token.line, token.column)).or_default();
+            counts[if truthy { 0 } else { 1 }] += 1;
+        }
+    }
+
+    fn finish(&mut self, _interpreter: &mut Interpreter) {
+        let (lcov, summary) = self.report();
+        eprint!("{}", summary);
+        match std::fs::write(&self.output, lcov) {
+            Ok(()) => eprintln!("\nLCOV written to {}.", self.output.display()),
+            Err(error) => eprintln!("Could not write '{}': {}.", self.output.display(), error),
+        }
+    }
+}
+
+/// The statement lines and branch points of a source, none if it does not
+/// parse.
+fn points(source: String) -> Points {
+    let (statements, errors) = Lox::collect_errors(|| {
+        let tokens = Scanner::new(source).scan_tokens();
+        Parser::new(tokens).parse()
+    });
+    let mut points = Points::default();
+    if errors.is_empty() {
+        points.statements(&statements);
+    }
+    points
+}
+
+/// "hit/total percent" for the lines, then the branches.
+fn row(counts: [usize; 4], name: &str) -> String {
+    format!(
+        "{:>7} {:>6}  {:>7} {:>6}  {}\n",
+        format!("{}/{}", counts[0], counts[1]),
+        percent(counts[0], counts[1]),
+        format!("{}/{}", counts[2], counts[3]),
+        percent(counts[2], counts[3]),
+        name
+    )
+}
+
+fn percent(hit: usize, total: usize) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", hit as f64 * 100.0 / total as f64)
+}
+
+impl Points {
+    fn statements(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        if !matches!(stmt, Stmt::Block { .. }) {
+            self.lines.insert(stmt.line());
+        }
+        stmt.accept(self);
+    }
+
+    fn branch(&mut self, token: &Token) {
+        self.branches.insert((token.line, token.column));
+    }
+}
+
+impl expr::Visitor<()> for Points {
+    fn visit_binary_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        left.accept(self);
+        right.accept(self);
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) {
+        expression.accept(self);
+    }
+
+    fn visit_literal_expr(&mut self, _value: &Literal) {}
+
+    fn visit_unary_expr(&mut self, _operator: &Token, right: &Expr) {
+        right.accept(self);
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) {
+        callee.accept(self);
+        for argument in arguments {
+            argument.accept(self);
+        }
+    }
+
+    fn visit_variable_expr(&mut self, _name: &Token) {}
+
+    fn visit_assign_expr(&mut self, _name: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) {
+        self.branch(operator);
+        left.accept(self);
+        right.accept(self);
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, _name: &Token) {
+        object.accept(self);
+    }
+
+    fn visit_list_expr(&mut self, elements: &[Expr]) {
+        for element in elements {
+            element.accept(self);
+        }
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, _bracket: &Token, index: &Expr) {
+        object.accept(self);
+        index.accept(self);
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) {
+        object.accept(self);
+        index.accept(self);
+        value.accept(self);
+    }
+}
+
+impl stmt::Visitor<()> for Points {
+    fn visit_expression_stmt(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn visit_print_stmt(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    fn visit_var_stmt(&mut self, _name: &Token, initializer: Option<&Expr>) {
+        if let Some(initializer) = initializer {
+            initializer.accept(self);
+        }
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) {
+        self.statements(statements);
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        keyword: &Token,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) {
+        self.branch(keyword);
+        condition.accept(self);
+        self.statement(then_branch);
+        if let Some(else_branch) = else_branch {
+            self.statement(else_branch);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, keyword: &Token, condition: &Expr, body: &Stmt) {
+        self.branch(keyword);
+        condition.accept(self);
+        self.statement(body);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: Rc<LoxFunctionNode>) {
+        self.statements(&stmt.body);
+    }
+
+    fn visit_throw_stmt(&mut self, _keyword: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    fn visit_import_stmt(&mut self, _keyword: &Token, _path: &Token, _alias: &Token) {}
+
+    fn visit_from_import_stmt(&mut self, _keyword: &Token, _path: &Token, _names: &[Token]) {}
+
+    fn visit_try_stmt(
+        &mut self,
+        body: &[Stmt],
+        catch_clause: Option<&CatchClause>,
+        finally_body: Option<&[Stmt]>,
+    ) {
+        self.statements(body);
+        if let Some(clause) = catch_clause {
+            self.statements(&clause.body);
+        }
+        if let Some(statements) = finally_body {
+            self.statements(statements);
+        }
+    }
+
+    /// Tests only run under `rlox test`, so they are not counted.
+    fn visit_test_stmt(&mut self, _keyword: &Token, _name: &Token, _body: &[Stmt]) {}
+}
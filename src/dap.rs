@@ -0,0 +1,635 @@
+use crate::debugger::{parse_expression, scope_variables, Resume, Stepper, Stop};
+use crate::environment::Environment;
+use crate::execution_hook::ExecutionHook;
+use crate::interpreter::{self, Interpreter};
+use crate::json::Json;
+use crate::lsp::{object, read_message, string, write_message};
+use crate::parser::Parser;
+use crate::runtime_error::Exit;
+use crate::scanner::Scanner;
+use crate::stmt::Stmt;
+use crate::value::Value;
+use crate::{Lox, LOX};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// The only thread a Lox program has.
+const THREAD_ID: f64 = 1.0;
+
+/// A message from the client: a request, the end of the input, or a message
+/// that could not be read.
+type Incoming = Result<Option<Json>, String>;
+
+/// Runs `rlox dap`, a debug adapter speaking the Debug Adapter Protocol over
+/// standard input and output. It debugs one program per session.
+pub(crate) fn run_command(args: Vec<String>) -> i32 {
+    if let Some(option) = args.first() {
+        eprintln!("Unknown option '{}'.", option);
+        return 64;
+    }
+    // Requests are read on their own thread, so that ones like
+    // `setBreakpoints` are seen while the program runs.
+    let (requests, incoming) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut input = std::io::stdin().lock();
+        loop {
+            let message = read_message(&mut input);
+            let done = matches!(message, Ok(None));
+            if requests.send(message).is_err() || done {
+                break;
+            }
+        }
+    });
+    // The program needs the same stack it would get from `rlox script`.
+    let session_thread =
+        interpreter::spawn_with_stack(interpreter::DEFAULT_MAX_CALL_DEPTH, move || serve(incoming))
+            .unwrap();
+    session_thread.join().unwrap()
+}
+
+fn serve(incoming: Receiver<Incoming>) -> i32 {
+    let session = Rc::new(RefCell::new(Session {
+        incoming,
+        sender: Rc::new(RefCell::new(Sender {
+            output: Box::new(std::io::stdout()),
+            seq: 0.0,
+        })),
+        launch: None,
+        configured: false,
+        state: State::Configuring,
+        breakpoints: HashMap::new(),
+        stepper: Stepper::new(Resume::Continue),
+        entry: false,
+        stopped_at: None,
+        references: Vec::new(),
+        disconnected: false,
+    }));
+    while !session.borrow().disconnected {
+        if session.borrow().ready() {
+            run_program(&session);
+        } else {
+            session.borrow_mut().receive(None, true);
+        }
+    }
+    0
+}
+
+/// Runs the launched program under the session's breakpoints, then tells
+/// the client how it exited.
+fn run_program(session: &Rc<RefCell<Session>>) {
+    let (launch, sender) = {
+        let mut session = session.borrow_mut();
+        session.state = State::Running;
+        (session.launch.take().unwrap(), session.sender.clone())
+    };
+    #[allow(static_mut_refs)]
+    let interpreter = unsafe { &mut LOX.interpreter };
+    interpreter.set_current_file(Some(launch.program.into()));
+    interpreter.set_args(launch.args);
+    interpreter.set_output(Box::new(OutputEvents {
+        sender: sender.clone(),
+        buffer: String::new(),
+    }));
+    interpreter.add_hook(Box::new(Hook(session.clone())));
+
+    let (statements, errors) = Lox::collect_errors(|| {
+        let tokens = Scanner::new(launch.source).scan_tokens();
+        Parser::new(tokens).parse()
+    });
+    let exit_code = if errors.is_empty() {
+        interpreter.interpret(statements);
+        #[allow(static_mut_refs)]
+        unsafe {
+            match LOX.exit_code {
+                Some(code) => code,
+                None if LOX.had_runtime_error => 70,
+                None => 0,
+            }
+        }
+    } else {
+        for error in errors {
+            let location = error.token.as_ref().map(Lox::location).unwrap_or_default();
+            let text = format!(
+                "[line {}] Error{}: {}\n",
+                error.line, location, error.message
+            );
+            sender.borrow_mut().output("stderr", &text);
+        }
+        65
+    };
+
+    let mut session = session.borrow_mut();
+    session.state = State::Terminated;
+    if !session.disconnected {
+        session.event(
+            "exited",
+            object(vec![("exitCode", Json::Number(exit_code as f64))]),
+        );
+        session.event("terminated", object(vec![]));
+    }
+}
+
+/// Writes messages to the client, numbering them.
+struct Sender {
+    output: Box<dyn Write>,
+    seq: f64,
+}
+
+impl Sender {
+    fn send(&mut self, kind: &str, mut members: Vec<(&str, Json)>) {
+        self.seq += 1.0;
+        members.splice(
+            0..0,
+            [("seq", Json::Number(self.seq)), ("type", string(kind))],
+        );
+        write_message(&mut self.output, &object(members));
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.send("event", vec![("event", string(event)), ("body", body)]);
+    }
+
+    fn output(&mut self, category: &str, text: &str) {
+        self.event(
+            "output",
+            object(vec![
+                ("category", string(category)),
+                ("output", string(text)),
+            ]),
+        );
+    }
+}
+
+/// Sends what the program prints as `output` events, a line at a time.
+struct OutputEvents {
+    sender: Rc<RefCell<Sender>>,
+    buffer: String,
+}
+
+impl Write for OutputEvents {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.buffer.push_str(&String::from_utf8_lossy(bytes));
+        if let Some(end) = self.buffer.rfind('\n') {
+            let lines: String = self.buffer.drain(..=end).collect();
+            self.sender.borrow_mut().output("stdout", &lines);
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buffer.is_empty() {
+            let text = std::mem::take(&mut self.buffer);
+            self.sender.borrow_mut().output("stdout", &text);
+        }
+        Ok(())
+    }
+}
+
+/// The arguments of a `launch` request.
+struct Launch {
+    program: PathBuf,
+    source: String,
+    args: Vec<String>,
+}
+
+#[derive(PartialEq)]
+enum State {
+    /// Waiting for `launch` and `configurationDone`.
+    Configuring,
+    Running,
+    Terminated,
+}
+
+/// What a `variablesReference` handed to the client stands for. They are
+/// numbered from 1 and forgotten when the program resumes.
+enum Reference {
+    Scope(Rc<RefCell<Environment>>),
+    Value(Value),
+}
+
+struct Session {
+    incoming: Receiver<Incoming>,
+    sender: Rc<RefCell<Sender>>,
+    launch: Option<Launch>,
+    configured: bool,
+    state: State,
+    /// Breakpoint lines by canonical path.
+    breakpoints: HashMap<PathBuf, BTreeSet<i32>>,
+    stepper: Stepper,
+    /// Whether the first stop is the one `stopOnEntry` asked for.
+    entry: bool,
+    /// The line the program is stopped at, if it is.
+    stopped_at: Option<i32>,
+    references: Vec<Reference>,
+    disconnected: bool,
+}
+
+impl Session {
+    /// Whether the program has been launched and configured but not run.
+    fn ready(&self) -> bool {
+        self.state == State::Configuring && self.configured && self.launch.is_some()
+    }
+
+    /// Handles the next request. Without `block` it returns `false` at once
+    /// if there is none waiting.
+    fn receive(&mut self, interpreter: Option<&mut Interpreter>, block: bool) -> bool {
+        let message = if block {
+            self.incoming.recv().unwrap_or(Ok(None))
+        } else {
+            match self.incoming.try_recv() {
+                Ok(message) => message,
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => Ok(None),
+            }
+        };
+        match message {
+            Ok(Some(request)) => self.dispatch(&request, interpreter),
+            // The client went away.
+            Ok(None) => self.disconnected = true,
+            // Without a readable request there is no one to respond to.
+            Err(error) => eprintln!("{}", error),
+        }
+        true
+    }
+
+    fn dispatch(&mut self, request: &Json, interpreter: Option<&mut Interpreter>) {
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let arguments = request.get("arguments").cloned().unwrap_or(Json::Null);
+        let result = self.request(command, &arguments, interpreter);
+        let initialized = command == "initialize" && result.is_ok();
+        let mut members = vec![
+            (
+                "request_seq",
+                request.get("seq").cloned().unwrap_or(Json::Null),
+            ),
+            ("success", Json::Bool(result.is_ok())),
+            ("command", string(command)),
+        ];
+        match result {
+            Ok(body) => members.push(("body", body)),
+            Err(message) => members.push(("message", string(&message))),
+        }
+        self.sender.borrow_mut().send("response", members);
+        if initialized {
+            self.event("initialized", object(vec![]));
+        }
+    }
+
+    fn request(
+        &mut self,
+        command: &str,
+        arguments: &Json,
+        interpreter: Option<&mut Interpreter>,
+    ) -> Result<Json, String> {
+        match command {
+            "initialize" => Ok(object(vec![
+                ("supportsConfigurationDoneRequest", Json::Bool(true)),
+                ("supportsEvaluateForHovers", Json::Bool(true)),
+            ])),
+            "launch" => self.launch(arguments),
+            "configurationDone" => {
+                self.configured = true;
+                Ok(object(vec![]))
+            }
+            "setBreakpoints" => Ok(self.set_breakpoints(arguments)),
+            "threads" => Ok(object(vec![(
+                "threads",
+                Json::Array(vec![object(vec![
+                    ("id", Json::Number(THREAD_ID)),
+                    ("name", string("main")),
+                ])]),
+            )])),
+            "disconnect" => {
+                self.disconnected = true;
+                Ok(object(vec![]))
+            }
+            "continue" => {
+                self.stopped(interpreter)?;
+                self.resume(Resume::Continue);
+                Ok(object(vec![("allThreadsContinued", Json::Bool(true))]))
+            }
+            "next" | "stepIn" | "stepOut" => {
+                let depth = self.stopped(interpreter)?.0.call_depth();
+                self.resume(match command {
+                    "next" => Resume::StepOver(depth),
+                    "stepIn" => Resume::StepIn,
+                    _ => Resume::StepOut(depth),
+                });
+                Ok(object(vec![]))
+            }
+            "stackTrace" => {
+                let (interpreter, line) = self.stopped(interpreter)?;
+                Ok(stack_trace(interpreter, line, arguments))
+            }
+            "scopes" => {
+                let interpreter = self.stopped(interpreter)?.0;
+                let environment = frame_environment(interpreter, arguments)?;
+                Ok(object(vec![(
+                    "scopes",
+                    Json::Array(self.scopes(environment)),
+                )]))
+            }
+            "variables" => {
+                self.stopped(interpreter)?;
+                let reference = arguments
+                    .get("variablesReference")
+                    .and_then(Json::as_f64)
+                    .filter(|n| *n >= 1.0 && *n as usize <= self.references.len())
+                    .ok_or("Unknown variablesReference.")?;
+                let variables = match &self.references[reference as usize - 1] {
+                    Reference::Scope(scope) => scope_variables(scope),
+                    Reference::Value(value) => members(value),
+                };
+                let variables = variables
+                    .into_iter()
+                    .map(|(name, value)| {
+                        object(vec![
+                            ("name", string(&name)),
+                            ("value", string(&value.repr())),
+                            ("variablesReference", self.reference(&value)),
+                        ])
+                    })
+                    .collect();
+                Ok(object(vec![("variables", Json::Array(variables))]))
+            }
+            "evaluate" => {
+                let interpreter = self.stopped(interpreter)?.0;
+                let environment = frame_environment(interpreter, arguments)?;
+                let source = arguments
+                    .get("expression")
+                    .and_then(Json::as_str)
+                    .ok_or("Missing expression.")?;
+                let expr = parse_expression(source).map_err(|errors| {
+                    let messages: Vec<String> =
+                        errors.into_iter().map(|error| error.message).collect();
+                    messages.join(" ")
+                })?;
+                let value = interpreter
+                    .evaluate_in(&expr, environment)
+                    .map_err(|error| error.to_string())?;
+                Ok(object(vec![
+                    ("result", string(&value.repr())),
+                    ("variablesReference", self.reference(&value)),
+                ]))
+            }
+            _ => Err(format!("Unknown request '{}'.", command)),
+        }
+    }
+
+    fn launch(&mut self, arguments: &Json) -> Result<Json, String> {
+        if self.state != State::Configuring || self.launch.is_some() {
+            return Err("The program has already been launched.".to_string());
+        }
+        let program = arguments
+            .get("program")
+            .and_then(Json::as_str)
+            .ok_or("Missing program.")?;
+        let source = std::fs::read_to_string(program)
+            .map_err(|error| format!("Could not read '{}': {}", program, error))?;
+        let args = match arguments.get("args") {
+            Some(Json::Array(args)) => args
+                .iter()
+                .map(|arg| arg.as_str().map(str::to_string))
+                .collect::<Option<_>>()
+                .ok_or("The args must be strings.")?,
+            _ => Vec::new(),
+        };
+        if arguments.get("stopOnEntry") == Some(&Json::Bool(true)) {
+            self.stepper.resume(Resume::StepIn);
+            self.entry = true;
+        }
+        self.launch = Some(Launch {
+            program: canonical(Path::new(program)),
+            source,
+            args,
+        });
+        Ok(object(vec![]))
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Json) -> Json {
+        let path = arguments
+            .get("source")
+            .and_then(|source| source.get("path"))
+            .and_then(Json::as_str)
+            .map(|path| canonical(Path::new(path)));
+        let lines: BTreeSet<i32> = match arguments.get("breakpoints") {
+            Some(Json::Array(breakpoints)) => breakpoints
+                .iter()
+                .filter_map(|breakpoint| breakpoint.get("line").and_then(Json::as_f64))
+                .map(|line| line as i32)
+                .collect(),
+            _ => BTreeSet::new(),
+        };
+        let breakpoints = lines
+            .iter()
+            .map(|line| {
+                object(vec![
+                    ("verified", Json::Bool(path.is_some())),
+                    ("line", Json::Number(*line as f64)),
+                ])
+            })
+            .collect();
+        if let Some(path) = path {
+            self.breakpoints.insert(path, lines);
+        }
+        object(vec![("breakpoints", Json::Array(breakpoints))])
+    }
+
+    /// The interpreter and line of a stopped program.
+    fn stopped<'a>(
+        &self,
+        interpreter: Option<&'a mut Interpreter>,
+    ) -> Result<(&'a mut Interpreter, i32), String> {
+        match (interpreter, self.stopped_at) {
+            (Some(interpreter), Some(line)) => Ok((interpreter, line)),
+            _ => Err("The program is not stopped.".to_string()),
+        }
+    }
+
+    fn resume(&mut self, resume: Resume) {
+        self.stepper.resume(resume);
+        self.stopped_at = None;
+        self.references.clear();
+    }
+
+    /// A `variablesReference` for a value, or 0 if it has no members.
+    fn reference(&mut self, value: &Value) -> Json {
+        if !matches!(value, Value::List(_) | Value::Map(_) | Value::Module(_)) {
+            return Json::Number(0.0);
+        }
+        self.references.push(Reference::Value(value.clone()));
+        Json::Number(self.references.len() as f64)
+    }
+
+    /// The scopes of a frame from its innermost outwards.
+    fn scopes(&mut self, environment: Rc<RefCell<Environment>>) -> Vec<Json> {
+        let mut scopes = Vec::new();
+        let mut environment = Some(environment);
+        while let Some(scope) = environment {
+            let enclosing = scope.borrow().enclosing.clone();
+            let name = match (scopes.len(), &enclosing) {
+                (_, None) => "Globals",
+                (0, _) => "Locals",
+                _ => "Enclosing",
+            };
+            self.references.push(Reference::Scope(scope));
+            scopes.push(object(vec![
+                ("name", string(name)),
+                (
+                    "variablesReference",
+                    Json::Number(self.references.len() as f64),
+                ),
+                ("expensive", Json::Bool(false)),
+            ]));
+            environment = enclosing;
+        }
+        scopes
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.sender.borrow_mut().event(event, body);
+    }
+}
+
+/// Stops the program where the session's breakpoints and stepping say, and
+/// serves requests about it until the client resumes it.
+struct Hook(Rc<RefCell<Session>>);
+
+impl ExecutionHook for Hook {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut session = self.0.borrow_mut();
+        while session.receive(Some(&mut *interpreter), false) {}
+        // A block stops at its first statement instead.
+        if !session.disconnected && !matches!(stmt, Stmt::Block { .. }) {
+            let line = stmt.line();
+            let breakpoint = interpreter
+                .current_file()
+                .and_then(|file| session.breakpoints.get(file))
+                .is_some_and(|lines| lines.contains(&line));
+            let stop = session
+                .stepper
+                .check(line, interpreter.call_depth(), breakpoint);
+            if let Some(stop) = stop {
+                let entry = std::mem::take(&mut session.entry);
+                let reason = match stop {
+                    Stop::Breakpoint => "breakpoint",
+                    Stop::Step if entry => "entry",
+                    Stop::Step => "step",
+                };
+                session.stopped_at = Some(line);
+                session.event(
+                    "stopped",
+                    object(vec![
+                        ("reason", string(reason)),
+                        ("threadId", Json::Number(THREAD_ID)),
+                        ("allThreadsStopped", Json::Bool(true)),
+                    ]),
+                );
+                while session.stopped_at.is_some() && !session.disconnected {
+                    session.receive(Some(&mut *interpreter), true);
+                }
+            }
+        }
+        if session.disconnected {
+            return Err(Box::new(Exit(0)));
+        }
+        Ok(())
+    }
+}
+
+/// The stack of a stopped program, innermost frame first. Frame ids count
+/// from 1 at the innermost frame.
+fn stack_trace(interpreter: &Interpreter, line: i32, arguments: &Json) -> Json {
+    let frames = interpreter.stack_trace(line);
+    let argument = |name| {
+        arguments
+            .get(name)
+            .and_then(Json::as_f64)
+            .filter(|n| *n > 0.0)
+            .map(|n| n as usize)
+    };
+    let start = argument("startFrame").unwrap_or(0);
+    let levels = argument("levels").unwrap_or(frames.len());
+    let stack_frames = frames
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(levels)
+        .map(|(i, frame)| {
+            let mut members = vec![
+                ("id", Json::Number(i as f64 + 1.0)),
+                ("name", string(&frame.function)),
+                ("line", Json::Number(frame.line.unwrap_or(0) as f64)),
+                ("column", Json::Number(1.0)),
+            ];
+            match (interpreter.frame_file(i), frame.line) {
+                (Some(path), Some(_)) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    members.push((
+                        "source",
+                        object(vec![
+                            ("name", string(&name)),
+                            ("path", string(&path.to_string_lossy())),
+                        ]),
+                    ));
+                }
+                _ => members.push(("presentationHint", string("subtle"))),
+            }
+            object(members)
+        })
+        .collect();
+    object(vec![
+        ("stackFrames", Json::Array(stack_frames)),
+        ("totalFrames", Json::Number(frames.len() as f64)),
+    ])
+}
+
+/// The environment of the frame named by a request's `frameId`, the
+/// innermost one if it has none.
+fn frame_environment(
+    interpreter: &Interpreter,
+    arguments: &Json,
+) -> Result<Rc<RefCell<Environment>>, String> {
+    let id = arguments
+        .get("frameId")
+        .and_then(Json::as_f64)
+        .unwrap_or(1.0);
+    let frame = (id >= 1.0).then(|| id as usize - 1);
+    frame
+        .and_then(|frame| interpreter.frame_environment(frame))
+        .ok_or_else(|| format!("Unknown frameId {}.", id))
+}
+
+/// What a list, map or module shows when expanded.
+fn members(value: &Value) -> Vec<(String, Value)> {
+    match value {
+        Value::List(list) => list
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(i, element)| (i.to_string(), element.clone()))
+            .collect(),
+        Value::Map(map) => map
+            .borrow()
+            .iter()
+            .map(|(key, value)| (format!("{:?}", key), value.clone()))
+            .collect(),
+        Value::Module(module) => scope_variables(&module.environment),
+        _ => Vec::new(),
+    }
+}
+
+/// Paths are compared canonically, so that the client's spelling of one
+/// matches the interpreter's.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,346 @@
+use crate::environment::Environment;
+use crate::execution_hook::ExecutionHook;
+use crate::expr::Expr;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::LoxCallable;
+use crate::parser::Parser;
+use crate::runtime_error::Exit;
+use crate::scanner::Scanner;
+use crate::stmt::Stmt;
+use crate::value::Value;
+use crate::{Lox, SyntaxError};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const PROMPT: &str = "(debug) ";
+
+const HELP: &str = "\
+Commands:
+  break <line>         stop whenever the script reaches a line
+  delete <line>        remove the breakpoint on a line
+  breakpoints          list the breakpoints
+  continue, c          run to the next breakpoint
+  step, s              run to the next statement, entering calls
+  next, n              run to the next statement, stepping over calls
+  finish, out          run until the current call returns
+  backtrace, bt        show the call stack
+  frame <n>            look at frame n of the call stack
+  locals               show the variables visible in the frame
+  print, p <expr>      evaluate an expression in the frame
+  set <name> = <expr>  assign to a variable visible in the frame
+  list, l              show the source around the current line
+  quit, q              stop the script
+  help                 show this message";
+
+/// When to stop next, besides at breakpoints.
+#[derive(Clone, Copy)]
+pub(crate) enum Resume {
+    Continue,
+    StepIn,
+    /// At the next statement no deeper than this many calls.
+    StepOver(usize),
+    /// At the next statement shallower than this many calls.
+    StepOut(usize),
+}
+
+/// Why a `Stepper` stopped the script.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Stop {
+    Breakpoint,
+    Step,
+}
+
+/// Decides which statements a debugger stops at, from its breakpoints and
+/// the last resume command.
+pub(crate) struct Stepper {
+    resume: Resume,
+    /// The line and call depth of the last stop. The other statements on a
+    /// line do not stop again, so steps go a line at a time.
+    last_stop: Option<(i32, usize)>,
+}
+
+impl Stepper {
+    pub(crate) fn new(resume: Resume) -> Self {
+        Stepper {
+            resume,
+            last_stop: None,
+        }
+    }
+
+    pub(crate) fn resume(&mut self, resume: Resume) {
+        self.resume = resume;
+    }
+
+    /// Whether to stop at a statement on `line`, `depth` calls deep.
+    /// `breakpoint` says whether the line has a breakpoint.
+    pub(crate) fn check(&mut self, line: i32, depth: usize, breakpoint: bool) -> Option<Stop> {
+        if self.last_stop == Some((line, depth)) {
+            return None;
+        }
+        self.last_stop = None;
+        let stepped = match self.resume {
+            Resume::Continue => false,
+            Resume::StepIn => true,
+            Resume::StepOver(stop_depth) => depth <= stop_depth,
+            Resume::StepOut(stop_depth) => depth < stop_depth,
+        };
+        let stop = if breakpoint {
+            Stop::Breakpoint
+        } else if stepped {
+            Stop::Step
+        } else {
+            return None;
+        };
+        self.last_stop = Some((line, depth));
+        Some(stop)
+    }
+}
+
+/// The `--debug` prompt. It stops before the script's first statement and
+/// reads commands a line at a time from standard input, so a session can be
+/// scripted. At the end of the input the script runs on undisturbed.
+pub(crate) struct Debugger {
+    script: PathBuf,
+    source: Vec<String>,
+    breakpoints: BTreeSet<i32>,
+    stepper: Stepper,
+    /// The frame `locals`, `print` and `set` look at, 0 being the innermost.
+    frame: usize,
+    detached: bool,
+}
+
+impl Debugger {
+    pub(crate) fn new(script: PathBuf, source: &str) -> Self {
+        Debugger {
+            script,
+            source: source.lines().map(str::to_string).collect(),
+            breakpoints: BTreeSet::new(),
+            stepper: Stepper::new(Resume::StepIn),
+            frame: 0,
+            detached: false,
+        }
+    }
+
+    fn source_line(&self, line: i32) -> &str {
+        self.source
+            .get((line - 1).max(0) as usize)
+            .map_or("", |text| text.trim())
+    }
+
+    /// Reads and runs commands until one resumes the script.
+    fn prompt(&mut self, interpreter: &mut Interpreter, line: i32) -> Result<(), Box<dyn Error>> {
+        let interactive = std::io::stdin().is_terminal();
+        loop {
+            if interactive {
+                print!("{}", PROMPT);
+                let _ = std::io::stdout().flush();
+            }
+            let mut input = String::new();
+            if matches!(std::io::stdin().read_line(&mut input), Ok(0) | Err(_)) {
+                self.detached = true;
+                return Ok(());
+            }
+            let input = input.trim();
+            let (command, argument) = match input.split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (input, ""),
+            };
+            let depth = interpreter.call_depth();
+            match (command, argument) {
+                ("", _) => {}
+                ("continue" | "c", _) => return self.resume(Resume::Continue),
+                ("step" | "s", _) => return self.resume(Resume::StepIn),
+                ("next" | "n", _) => return self.resume(Resume::StepOver(depth)),
+                ("finish" | "out", _) => return self.resume(Resume::StepOut(depth)),
+                ("quit" | "q", _) => return Err(Box::new(Exit(0))),
+                ("break" | "b", line) if line.parse::<i32>().is_ok_and(|n| n > 0) => {
+                    let line = line.parse().unwrap();
+                    self.breakpoints.insert(line);
+                    println!("Breakpoint set at line {}.", line);
+                }
+                ("delete" | "d", line) if line.parse::<i32>().is_ok() => {
+                    let line = line.parse().unwrap();
+                    if self.breakpoints.remove(&line) {
+                        println!("Breakpoint removed from line {}.", line);
+                    } else {
+                        println!("No breakpoint at line {}.", line);
+                    }
+                }
+                ("breakpoints", _) if self.breakpoints.is_empty() => println!("No breakpoints."),
+                ("breakpoints", _) => {
+                    let lines: Vec<String> = self.breakpoints.iter().map(i32::to_string).collect();
+                    println!("Breakpoints at lines {}.", lines.join(", "));
+                }
+                ("backtrace" | "bt", _) => {
+                    for (i, frame) in interpreter.stack_trace(line).iter().enumerate() {
+                        let marker = if i == self.frame { "*" } else { " " };
+                        println!("{}#{} {}", marker, i, frame);
+                    }
+                }
+                ("frame", n) if n.parse::<usize>().is_ok() => {
+                    let n = n.parse().unwrap();
+                    match interpreter.stack_trace(line).get(n) {
+                        Some(frame) => {
+                            self.frame = n;
+                            println!("#{} {}", n, frame);
+                        }
+                        None => println!("No frame {}.", n),
+                    }
+                }
+                ("locals", _) => self.print_locals(interpreter),
+                ("print" | "p", source) if !source.is_empty() => match parse_expression(source) {
+                    Ok(expr) => self.evaluate(interpreter, &expr),
+                    Err(errors) => print_errors(&errors),
+                },
+                ("set", source) if !source.is_empty() => match parse_expression(source) {
+                    Ok(expr @ (Expr::Assign { .. } | Expr::SetIndex { .. })) => {
+                        self.evaluate(interpreter, &expr);
+                    }
+                    Ok(_) => println!("Usage: set <name> = <expr>"),
+                    Err(errors) => print_errors(&errors),
+                },
+                ("list" | "l", _) => {
+                    let first = (line - 2).max(1);
+                    let last = (line + 2).min(self.source.len() as i32);
+                    for n in first..=last {
+                        let marker = if n == line { "->" } else { "  " };
+                        println!("{}{:>4}  {}", marker, n, self.source[n as usize - 1]);
+                    }
+                }
+                ("help" | "h", _) => println!("{}", HELP),
+                ("break" | "b" | "delete" | "d", _) => println!("Usage: {} <line>", command),
+                ("frame", _) => println!("Usage: frame <n>"),
+                ("print" | "p", _) => println!("Usage: {} <expr>", command),
+                ("set", _) => println!("Usage: set <name> = <expr>"),
+                _ => println!("Unknown command '{}'. Type help for a list.", command),
+            }
+        }
+    }
+
+    fn resume(&mut self, resume: Resume) -> Result<(), Box<dyn Error>> {
+        self.stepper.resume(resume);
+        Ok(())
+    }
+
+    fn evaluate(&self, interpreter: &mut Interpreter, expr: &Expr) {
+        let Some(environment) = interpreter.frame_environment(self.frame) else {
+            return;
+        };
+        match interpreter.evaluate_in(expr, environment) {
+            Ok(value) => println!("{}", value.repr()),
+            Err(error) => println!("Error: {}", error),
+        }
+    }
+
+    /// The variables of each scope from the frame's outwards, leaving out
+    /// the natives.
+    fn print_locals(&self, interpreter: &Interpreter) {
+        let mut environment = interpreter.frame_environment(self.frame);
+        let mut depth = 0;
+        while let Some(scope) = environment {
+            let enclosing = scope.borrow().enclosing.clone();
+            let label = match (depth, &enclosing) {
+                (_, None) => "globals",
+                (0, _) => "locals",
+                _ => "enclosing",
+            };
+            print_scope(label, &scope);
+            environment = enclosing;
+            depth += 1;
+        }
+    }
+}
+
+impl ExecutionHook for Debugger {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+    ) -> Result<(), Box<dyn Error>> {
+        // A block stops at its first statement instead.
+        if self.detached || matches!(stmt, Stmt::Block { .. }) {
+            return Ok(());
+        }
+        let line = stmt.line();
+        let in_script = interpreter.current_file() == Some(self.script.as_path());
+        let breakpoint = in_script && self.breakpoints.contains(&line);
+        if self
+            .stepper
+            .check(line, interpreter.call_depth(), breakpoint)
+            .is_none()
+        {
+            return Ok(());
+        }
+
+        self.frame = 0;
+        let function = &interpreter.stack_trace(line)[0].function;
+        if in_script {
+            println!(
+                "Stopped at line {} in {}: {}",
+                line,
+                function,
+                self.source_line(line)
+            );
+        } else {
+            let file = interpreter
+                .current_file()
+                .map(|path| path.display().to_string());
+            println!(
+                "Stopped at line {} of {} in {}.",
+                line,
+                file.unwrap_or_default(),
+                function
+            );
+        }
+        self.prompt(interpreter, line)
+    }
+}
+
+/// Parses an expression typed while stopped.
+pub(crate) fn parse_expression(source: &str) -> Result<Expr, Vec<SyntaxError>> {
+    let (expr, errors) = Lox::collect_errors(|| {
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        Parser::new(tokens).parse_expression()
+    });
+    match expr {
+        Some(expr) if errors.is_empty() => Ok(expr),
+        _ => Err(errors),
+    }
+}
+
+fn print_errors(errors: &[SyntaxError]) {
+    for error in errors {
+        println!("Error: {}", error.message);
+    }
+}
+
+fn print_scope(label: &str, scope: &Rc<RefCell<Environment>>) {
+    let variables = scope_variables(scope);
+    if variables.is_empty() {
+        return;
+    }
+    println!("{}:", label);
+    for (name, value) in variables {
+        println!("  {} = {}", name, value.repr());
+    }
+}
+
+/// The variables of one scope by name, leaving out the natives.
+pub(crate) fn scope_variables(scope: &Rc<RefCell<Environment>>) -> Vec<(String, Value)> {
+    let mut variables: Vec<_> = scope
+        .borrow()
+        .values
+        .iter()
+        .filter(|(_, value)| {
+            !matches!(value, Value::Callable(callable)
+                if matches!(**callable, LoxCallable::NativeFunction(_)))
+        })
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    variables.sort_by(|a, b| a.0.cmp(&b.0));
+    variables
+}
@@ -30,7 +30,7 @@ impl Environment {
         self.values.insert(name, value);
     }
 
-    pub(crate) fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+    pub(crate) fn assign(&mut self, name: &Token, value: Value) -> Result<(), Box<RuntimeError>> {
         if self.values.contains_key(&name.lexeme) {
             self.values.insert(name.lexeme.clone(), value);
             return Ok(());
@@ -41,10 +41,11 @@ impl Environment {
             return Ok(());
         }
 
-        Err(RuntimeError {
-            token: name.clone(),
-            message: format!("Undefined variable '{}'.", &name.lexeme),
-        })
+        Err(RuntimeError::new(
+            name.clone(),
+            format!("Undefined variable '{}'.", &name.lexeme),
+        )
+        .into())
     }
 
     pub(crate) fn get(&self, name: &Token) -> Result<Value, Box<RuntimeError>> {
@@ -54,10 +55,10 @@ impl Environment {
         if let Some(enclosing) = &self.enclosing {
             return enclosing.borrow().get(name);
         }
-        Err(RuntimeError {
-            token: name.clone(),
-            message: format!("Undefined variable '{}'.", &name.lexeme),
-        }
+        Err(RuntimeError::new(
+            name.clone(),
+            format!("Undefined variable '{}'.", &name.lexeme),
+        )
         .into())
     }
 }
@@ -0,0 +1,75 @@
+use crate::interpreter::Interpreter;
+use crate::lox_callable::LoxCallable;
+use crate::stmt::Stmt;
+use crate::token::Token;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Watches a script as it runs, e.g. to debug it. An error returned from a
+/// hook stops the script like one raised by the statement would.
+pub(crate) trait ExecutionHook {
+    /// Called before each statement is executed, with the interpreter in the
+    /// state the statement will see.
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+    ) -> Result<(), Box<dyn Error>>;
+
+    /// Called after each statement `before_statement` saw, however it ended.
+    fn after_statement(&mut self, _interpreter: &mut Interpreter, _stmt: &Stmt) {}
+
+    /// Called when a call has entered `callee`, with its frame on the call
+    /// stack. A tail call enters its callee in the caller's frame, so the
+    /// call depth stays the same.
+    fn enter_function(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _callee: &LoxCallable,
+        _arguments: &[Value],
+    ) {
+    }
+
+    /// Called as a call made to `callee` returns a value or fails, with its
+    /// frame still on the call stack. Tail calls it made do not return
+    /// separately.
+    fn exit_function(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _callee: &LoxCallable,
+        _result: Result<&Value, &dyn Error>,
+    ) {
+    }
+
+    /// Called as the condition of an `if` or `while`, or the left operand of
+    /// `and` or `or`, decides which way to go. `token` is the keyword or
+    /// operator, and `truthy` what the condition came out as.
+    fn branch(&mut self, _interpreter: &mut Interpreter, _token: &Token, _truthy: bool) {}
+
+    /// Called once the script has finished, e.g. to write a report.
+    fn finish(&mut self, _interpreter: &mut Interpreter) {}
+}
+
+/// The lines of the files a script runs, read when first asked for, for
+/// hooks that show them.
+#[derive(Default)]
+pub(crate) struct SourceLines {
+    files: HashMap<PathBuf, Vec<String>>,
+}
+
+impl SourceLines {
+    /// A line of `path` without its indentation, or "" if it cannot be read.
+    pub(crate) fn get(&mut self, path: &Path, line: i32) -> &str {
+        if !self.files.contains_key(path) {
+            let lines = std::fs::read_to_string(path)
+                .map(|source| source.lines().map(|line| line.trim().to_string()).collect())
+                .unwrap_or_default();
+            self.files.insert(path.to_path_buf(), lines);
+        }
+        self.files[path]
+            .get((line - 1).max(0) as usize)
+            .map_or("", String::as_str)
+    }
+}
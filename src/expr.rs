@@ -1,5 +1,3 @@
-use std::vec;
-
 use crate::token::Token;
 
 pub(crate) trait Visitor<R> {
@@ -7,10 +5,20 @@ pub(crate) trait Visitor<R> {
     fn visit_grouping_expr(&mut self, expression: &Expr) -> R;
     fn visit_literal_expr(&mut self, value: &crate::token::Literal) -> R;
     fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> R;
-    fn visit_call_expr(&mut self, callee: &Expr, paren: &Token, arguments: &Vec<Box<Expr>>) -> R;
+    fn visit_call_expr(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> R;
     fn visit_variable_expr(&mut self, name: &Token) -> R;
     fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> R;
     fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> R;
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> R;
+    fn visit_list_expr(&mut self, elements: &[Expr]) -> R;
+    fn visit_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> R;
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> R;
 }
 
 #[derive(Debug, Clone)]
@@ -33,7 +41,7 @@ pub(crate) enum Expr {
     Call {
         callee: Box<Expr>,
         paren: Token,
-        arguments: Vec<Box<Expr>>,
+        arguments: Vec<Expr>,
     },
     Variable {
         name: Token,
@@ -47,6 +55,24 @@ pub(crate) enum Expr {
         operator: Token,
         right: Box<Expr>,
     },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    List {
+        elements: Vec<Expr>,
+    },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
 }
 
 impl Expr {
@@ -72,6 +98,19 @@ impl Expr {
                 operator,
                 right,
             } => visitor.visit_logical_expr(left, operator, right),
+            Expr::Get { object, name } => visitor.visit_get_expr(object, name),
+            Expr::List { elements } => visitor.visit_list_expr(elements),
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => visitor.visit_index_expr(object, bracket, index),
+            Expr::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+            } => visitor.visit_set_index_expr(object, bracket, index, value),
         }
     }
 }
@@ -0,0 +1,682 @@
+use crate::expr::Expr;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::stmt::{LoxFunctionNode, Stmt};
+use crate::token::{Comment, Token};
+use crate::token_type::TokenType::{self, *};
+use crate::Lox;
+use std::path::{Path, PathBuf};
+
+const INDENT: &str = "  ";
+const MAX_WIDTH: usize = 80;
+
+/// Formats source, or returns `None` after reporting its syntax errors.
+pub(crate) fn format_source(source: String) -> Option<String> {
+    let (tokens, statements) = Lox::isolated(|| {
+        let tokens = Scanner::with_comments(source).scan_tokens();
+        let statements = Parser::new(tokens.clone()).parse();
+        (tokens, statements)
+    })?;
+    Some(Formatter::new(&tokens).program(&statements))
+}
+
+/// Prints a parsed program back as canonically formatted source.
+///
+/// The tree says what to print. The tokens it was parsed from are walked in
+/// step with it to recover what the tree leaves out: comments, blank lines,
+/// `for` loops (parsed into `while`) and bare `return;` (parsed as
+/// `return nil;`).
+struct Formatter<'a> {
+    tokens: &'a [Token],
+    current: usize,
+    /// Tokens before this one have had their comments written or queued.
+    comments_done: usize,
+    /// Comments met inside a statement, written after it.
+    pending: Vec<Comment>,
+    /// The last source line written, to place comments and blank lines.
+    last_line: i32,
+    indent: usize,
+    /// Whether the next call or list literal puts each item on its own line.
+    wrap: bool,
+}
+
+/// Where the formatter is in the tokens, to retry a statement with wrapping.
+struct Checkpoint {
+    current: usize,
+    comments_done: usize,
+    pending: usize,
+    last_line: i32,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        Formatter {
+            tokens,
+            current: 0,
+            comments_done: 0,
+            pending: Vec::new(),
+            last_line: 1,
+            indent: 0,
+            wrap: false,
+        }
+    }
+
+    fn program(&mut self, statements: &[Stmt]) -> String {
+        let mut out = String::new();
+        self.statements(&mut out, statements);
+        self.comments(&mut out);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Appends statements one per line, keeping single blank lines between
+    /// them.
+    fn statements(&mut self, out: &mut String, statements: &[Stmt]) {
+        for stmt in statements {
+            self.comments(out);
+            self.newline(out, self.peek().line);
+            out.push_str(&self.indentation());
+            let text = self.wrapped_statement(stmt);
+            out.push_str(&text);
+            self.flush_pending(out);
+        }
+    }
+
+    /// Ends the line in `out` before something from source line `line`,
+    /// keeping one blank line where the source had any, except right after
+    /// an opening brace.
+    fn newline(&self, out: &mut String, line: i32) {
+        if out.is_empty() {
+            return;
+        }
+        let opening = out == "{";
+        out.push('\n');
+        if line > self.last_line + 1 && !opening {
+            out.push('\n');
+        }
+    }
+
+    /// Writes the comments before the next token. One on the line of the
+    /// previous token stays at the end of that line.
+    fn comments(&mut self, out: &mut String) {
+        for comment in self.take_comments() {
+            if comment.line == self.last_line && !out.is_empty() {
+                out.push(' ');
+            } else {
+                self.newline(out, comment.line);
+                out.push_str(&self.indentation());
+            }
+            out.push_str(&comment.text);
+            self.last_line = comment.line;
+        }
+    }
+
+    /// The comments before the next token that are not written or queued
+    /// yet, which they then are.
+    fn take_comments(&mut self) -> Vec<Comment> {
+        if self.current < self.comments_done {
+            return Vec::new();
+        }
+        self.comments_done = self.current + 1;
+        self.peek().comments.clone()
+    }
+
+    /// Writes the comments met since the last statement or header: the first
+    /// at the end of the line, the rest on lines of their own.
+    fn flush_pending(&mut self, out: &mut String) {
+        for (i, comment) in std::mem::take(&mut self.pending).into_iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+                out.push_str(&self.indentation());
+            } else {
+                out.push(' ');
+            }
+            out.push_str(&comment.text);
+        }
+    }
+
+    /// Formats a statement, breaking its outermost call or list over several
+    /// lines if it does not fit.
+    fn wrapped_statement(&mut self, stmt: &Stmt) -> String {
+        let checkpoint = self.checkpoint();
+        let text = self.statement(stmt);
+        let simple = !matches!(
+            stmt,
+            Stmt::Block { .. }
+                | Stmt::If { .. }
+                | Stmt::While { .. }
+                | Stmt::Function { .. }
+                | Stmt::Try { .. }
+                | Stmt::Test { .. }
+        );
+        let width = self.indentation().len() + text.chars().count();
+        if !simple || width <= MAX_WIDTH {
+            return text;
+        }
+        self.restore(checkpoint);
+        self.wrap = true;
+        let text = self.statement(stmt);
+        self.wrap = false;
+        text
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Expression { expression, .. } => {
+                let expression = self.expr(expression);
+                expression + self.next(SEMICOLON)
+            }
+            Stmt::Print { expression, .. } => {
+                self.next(PRINT);
+                format!("print {}{}", self.expr(expression), self.next(SEMICOLON))
+            }
+            Stmt::Return { value, .. } => {
+                self.next(RETURN);
+                let value = if self.check(SEMICOLON) {
+                    String::new()
+                } else {
+                    format!(" {}", self.expr(value))
+                };
+                format!("return{}{}", value, self.next(SEMICOLON))
+            }
+            Stmt::Var { name, initializer } => {
+                self.next(VAR);
+                self.next(IDENTIFIER);
+                let mut text = format!("var {}", name.lexeme);
+                if let Some(initializer) = initializer {
+                    self.next(EQUAL);
+                    text.push_str(" = ");
+                    text.push_str(&self.expr(initializer));
+                }
+                text + self.next(SEMICOLON)
+            }
+            Stmt::Block { statements, .. } if self.check(FOR) => match statements.as_slice() {
+                [initializer, loop_stmt] => self.for_loop(Some(initializer), loop_stmt),
+                _ => unreachable!("for loops with an initializer have two statements"),
+            },
+            Stmt::Block { statements, .. } => self.block(statements),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.next(IF);
+                let header = format!("if {}", self.condition(condition));
+                let mut text = header + &self.body(then_branch);
+                if let Some(else_branch) = else_branch {
+                    let after_block = matches!(**then_branch, Stmt::Block { .. });
+                    self.continuation(&mut text, ELSE, after_block);
+                    text.push_str(&self.body(else_branch));
+                }
+                text
+            }
+            Stmt::While { .. } if self.check(FOR) => self.for_loop(None, stmt),
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.next(WHILE);
+                format!("while {}{}", self.condition(condition), self.body(body))
+            }
+            Stmt::Function { function } => {
+                self.next(FUN);
+                self.function(function)
+            }
+            Stmt::Throw { value, .. } => {
+                self.next(THROW);
+                format!("throw {}{}", self.expr(value), self.next(SEMICOLON))
+            }
+            Stmt::Import { path, alias, .. } => {
+                self.next(IMPORT);
+                self.next(STRING);
+                self.next(AS);
+                self.next(IDENTIFIER);
+                format!(
+                    "import {} as {}{}",
+                    path.lexeme,
+                    alias.lexeme,
+                    self.next(SEMICOLON)
+                )
+            }
+            Stmt::FromImport { path, names, .. } => {
+                self.next(FROM);
+                self.next(STRING);
+                self.next(IMPORT);
+                let mut imported = Vec::new();
+                for (i, name) in names.iter().enumerate() {
+                    if i > 0 {
+                        self.next(COMMA);
+                    }
+                    self.next(IDENTIFIER);
+                    imported.push(name.lexeme.as_str());
+                }
+                format!(
+                    "from {} import {}{}",
+                    path.lexeme,
+                    imported.join(", "),
+                    self.next(SEMICOLON)
+                )
+            }
+            Stmt::Try {
+                body,
+                catch_clause,
+                finally_body,
+                ..
+            } => {
+                self.next(TRY);
+                let mut text = format!("try {}", self.block(body));
+                if let Some(clause) = catch_clause {
+                    self.continuation(&mut text, CATCH, true);
+                    self.next(LEFT_PAREN);
+                    self.next(IDENTIFIER);
+                    self.next(RIGHT_PAREN);
+                    text.push_str(&format!(" ({}) ", clause.name.lexeme));
+                    text.push_str(&self.block(&clause.body));
+                }
+                if let Some(finally_body) = finally_body {
+                    self.continuation(&mut text, FINALLY, true);
+                    text.push(' ');
+                    text.push_str(&self.block(finally_body));
+                }
+                text
+            }
+            Stmt::Test { name, body, .. } => {
+                self.next(IDENTIFIER);
+                self.next(STRING);
+                format!("test {} {}", name.lexeme, self.block(body))
+            }
+        }
+    }
+
+    /// A `for` loop, which the parser turned into its `while` equivalent.
+    fn for_loop(&mut self, initializer: Option<&Stmt>, loop_stmt: &Stmt) -> String {
+        let Stmt::While {
+            condition, body, ..
+        } = loop_stmt
+        else {
+            unreachable!("for loops are parsed into while loops");
+        };
+        self.next(FOR);
+        self.next(LEFT_PAREN);
+        let mut header = match initializer {
+            Some(initializer) => self.statement(initializer),
+            None => self.next(SEMICOLON).to_string(),
+        };
+        if !self.check(SEMICOLON) {
+            header.push(' ');
+            header.push_str(&self.expr(condition));
+        }
+        header.push_str(self.next(SEMICOLON));
+
+        let mut body: &Stmt = body;
+        if !self.check(RIGHT_PAREN) {
+            let Stmt::Block { statements, .. } = body else {
+                unreachable!("a for loop increment is appended to its body");
+            };
+            let [inner, Stmt::Expression { expression, .. }] = statements.as_slice() else {
+                unreachable!("a for loop increment is appended to its body");
+            };
+            header.push(' ');
+            header.push_str(&self.expr(expression));
+            body = inner;
+        }
+        self.next(RIGHT_PAREN);
+        format!("for ({}){}", header, self.body(body))
+    }
+
+    /// `(condition)` of an `if` or `while`.
+    fn condition(&mut self, condition: &Expr) -> String {
+        self.next(LEFT_PAREN);
+        let condition = self.expr(condition);
+        self.next(RIGHT_PAREN);
+        format!("({})", condition)
+    }
+
+    /// The body of an `if`, `else` or loop, on the same line as its header.
+    /// A body other than a block goes on the next line if comments come
+    /// between them.
+    fn body(&mut self, body: &Stmt) -> String {
+        if self.check(LEFT_BRACE) || self.pending.is_empty() && !self.has_comments() {
+            return format!(" {}", self.statement(body));
+        }
+        let mut comments = std::mem::take(&mut self.pending);
+        comments.extend(self.take_comments());
+        self.indent += 1;
+        let mut text = String::new();
+        for (i, comment) in comments.iter().enumerate() {
+            if i == 0 && comment.line == self.last_line {
+                text.push(' ');
+            } else {
+                text.push('\n');
+                text.push_str(&self.indentation());
+            }
+            text.push_str(&comment.text);
+        }
+        text.push('\n');
+        text.push_str(&self.indentation());
+        text.push_str(&self.statement(body));
+        self.indent -= 1;
+        text
+    }
+
+    /// Appends `else`, `catch` or `finally` to `text`, which ends with the
+    /// statement or block before it. Comments before the keyword are kept
+    /// there, putting the keyword on a line of its own.
+    fn continuation(&mut self, text: &mut String, keyword: TokenType, after_block: bool) {
+        let length = text.len();
+        self.flush_pending(text);
+        self.comments(text);
+        if after_block && text.len() == length {
+            text.push(' ');
+        } else {
+            text.push('\n');
+            text.push_str(&self.indentation());
+        }
+        text.push_str(self.next(keyword));
+    }
+
+    fn function(&mut self, function: &LoxFunctionNode) -> String {
+        self.next(IDENTIFIER);
+        self.next(LEFT_PAREN);
+        let mut params = Vec::new();
+        for (i, param) in function.params.iter().enumerate() {
+            if i > 0 {
+                self.next(COMMA);
+            }
+            self.next(IDENTIFIER);
+            params.push(param.lexeme.as_str());
+        }
+        self.next(RIGHT_PAREN);
+        format!(
+            "fun {}({}) {}",
+            function.name.lexeme,
+            params.join(", "),
+            self.block(&function.body)
+        )
+    }
+
+    /// A block. Comments met in the header before it, such as in an `if`
+    /// condition, are written after its `{`.
+    fn block(&mut self, statements: &[Stmt]) -> String {
+        self.next(LEFT_BRACE);
+        self.indent += 1;
+        let mut out = "{".to_string();
+        self.flush_pending(&mut out);
+        self.statements(&mut out, statements);
+        self.comments(&mut out);
+        self.indent -= 1;
+        self.next(RIGHT_BRACE);
+        if out == "{" {
+            "{}".to_string()
+        } else {
+            format!("{}\n{}}}", out, self.indentation())
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> String {
+        match expr {
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                let left = self.expr(left);
+                let operator = self.next_any().to_string();
+                format!("{} {} {}", left, operator, self.expr(right))
+            }
+            Expr::Grouping { expression } => {
+                self.next(LEFT_PAREN);
+                let expression = self.expr(expression);
+                self.next(RIGHT_PAREN);
+                format!("({})", expression)
+            }
+            Expr::Literal { .. } => self.next_any().to_string(),
+            Expr::Unary { right, .. } => {
+                let operator = self.next_any().to_string();
+                operator + &self.expr(right)
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let callee = self.expr(callee);
+                callee + &self.items(LEFT_PAREN, arguments, RIGHT_PAREN)
+            }
+            Expr::Variable { name } => {
+                self.next(IDENTIFIER);
+                name.lexeme.clone()
+            }
+            Expr::Assign { name, value } => {
+                self.next(IDENTIFIER);
+                self.next(EQUAL);
+                format!("{} = {}", name.lexeme, self.expr(value))
+            }
+            Expr::Get { object, name } => {
+                let object = self.expr(object);
+                self.next(DOT);
+                self.next(IDENTIFIER);
+                format!("{}.{}", object, name.lexeme)
+            }
+            Expr::List { elements } => self.items(LEFT_BRACKET, elements, RIGHT_BRACKET),
+            Expr::Index { object, index, .. } => {
+                let object = self.expr(object);
+                object + &self.index(index)
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                let target = self.expr(object) + &self.index(index);
+                self.next(EQUAL);
+                format!("{} = {}", target, self.expr(value))
+            }
+        }
+    }
+
+    fn index(&mut self, index: &Expr) -> String {
+        self.next(LEFT_BRACKET);
+        let index = self.expr(index);
+        self.next(RIGHT_BRACKET);
+        format!("[{}]", index)
+    }
+
+    /// Comma-separated arguments or list elements between brackets. Comments
+    /// among them stay after the item they follow, which puts each item on
+    /// its own line.
+    fn items(&mut self, open: TokenType, items: &[Expr], close: TokenType) -> String {
+        let open = self.next(open).to_string();
+        let wrap = (std::mem::take(&mut self.wrap) && !items.is_empty()) || self.comment_inside();
+        if !wrap {
+            let mut texts = Vec::new();
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    self.next(COMMA);
+                }
+                texts.push(self.expr(item));
+            }
+            return format!("{}{}{}", open, texts.join(", "), self.next(close));
+        }
+
+        self.indent += 1;
+        let mut text = open;
+        self.item_comments(&mut text, Vec::new());
+        for (i, item) in items.iter().enumerate() {
+            let pending = self.pending.len();
+            text.push('\n');
+            text.push_str(&self.indentation());
+            text.push_str(&self.expr(item));
+            if i + 1 < items.len() {
+                text.push_str(self.next(COMMA));
+            }
+            let inside = self.pending.split_off(pending);
+            self.item_comments(&mut text, inside);
+        }
+        self.indent -= 1;
+        text.push('\n');
+        text.push_str(&self.indentation());
+        text + self.next(close)
+    }
+
+    /// Writes `inside`, the comments met in the item just written, then
+    /// those before the next token: one on the line of the previous token at
+    /// the end of the line, the rest on lines of their own.
+    fn item_comments(&mut self, text: &mut String, mut inside: Vec<Comment>) {
+        inside.extend(self.take_comments());
+        for comment in inside {
+            if comment.line == self.last_line {
+                text.push(' ');
+            } else {
+                text.push('\n');
+                text.push_str(&self.indentation());
+            }
+            text.push_str(&comment.text);
+        }
+    }
+
+    /// Whether any comment comes before the bracket closing the one just
+    /// consumed.
+    fn comment_inside(&self) -> bool {
+        let mut depth = 0;
+        for token in &self.tokens[self.current..] {
+            if !token.comments.is_empty() {
+                return true;
+            }
+            match token.token_type {
+                LEFT_PAREN | LEFT_BRACKET | LEFT_BRACE => depth += 1,
+                RIGHT_PAREN | RIGHT_BRACKET | RIGHT_BRACE if depth == 0 => return false,
+                RIGHT_PAREN | RIGHT_BRACKET | RIGHT_BRACE => depth -= 1,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Consumes the next token, which the tree says is `expected`.
+    fn next(&mut self, expected: TokenType) -> &'a str {
+        debug_assert_eq!(self.peek().token_type, expected);
+        self.next_any()
+    }
+
+    fn next_any(&mut self) -> &'a str {
+        let token = self.peek();
+        if self.current >= self.comments_done {
+            self.pending.extend(token.comments.iter().cloned());
+            self.comments_done = self.current + 1;
+        }
+        self.current += 1;
+        // A string can span lines.
+        self.last_line = token.line + token.lexeme.matches('\n').count() as i32;
+        &token.lexeme
+    }
+
+    /// Whether the next token has comments not yet written or queued.
+    fn has_comments(&self) -> bool {
+        self.current >= self.comments_done && !self.peek().comments.is_empty()
+    }
+
+    fn check(&self, token_type: TokenType) -> bool {
+        self.peek().token_type == token_type
+    }
+
+    fn peek(&self) -> &'a Token {
+        &self.tokens[self.current]
+    }
+
+    fn indentation(&self) -> String {
+        INDENT.repeat(self.indent)
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            current: self.current,
+            comments_done: self.comments_done,
+            pending: self.pending.len(),
+            last_line: self.last_line,
+        }
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.current = checkpoint.current;
+        self.comments_done = checkpoint.comments_done;
+        self.pending.truncate(checkpoint.pending);
+        self.last_line = checkpoint.last_line;
+    }
+}
+
+/// `rlox fmt [--check] [paths...]`: rewrites files in place, or with
+/// `--check` lists the ones that are not formatted. Directories are searched
+/// for `.lox` files; with no paths, standard input is formatted to standard
+/// output. Returns the exit code.
+pub(crate) fn run_command(args: Vec<String>) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if let Some(option) = paths.iter().find(|arg| arg.starts_with("--")) {
+        eprintln!("Unknown option '{}'.", option);
+        return 64;
+    }
+
+    if paths.is_empty() {
+        let mut source = String::new();
+        if let Err(error) = std::io::Read::read_to_string(&mut std::io::stdin(), &mut source) {
+            eprintln!("Could not read standard input: {}.", error);
+            return 74;
+        }
+        let Some(formatted) = format_source(source.clone()) else {
+            return 65;
+        };
+        if check {
+            return if formatted == source { 0 } else { 1 };
+        }
+        print!("{}", formatted);
+        return 0;
+    }
+
+    let mut files = Vec::new();
+    for path in paths {
+        collect_lox_files(Path::new(path), &mut files);
+    }
+    let mut code = 0;
+    for file in files {
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("Could not read '{}': {}.", file.display(), error);
+                code = 74;
+                continue;
+            }
+        };
+        let Some(formatted) = format_source(source.clone()) else {
+            code = 65;
+            continue;
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file.display());
+            code = code.max(1);
+        } else if let Err(error) = std::fs::write(&file, formatted) {
+            eprintln!("Could not write '{}': {}.", file.display(), error);
+            code = 74;
+        }
+    }
+    code
+}
+
+/// The file itself, or the `.lox` files under a directory in name order.
+pub(crate) fn collect_lox_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        files.push(path.to_path_buf());
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "lox") {
+            collect_lox_files(&entry, files);
+        }
+    }
+}
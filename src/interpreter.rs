@@ -1,53 +1,490 @@
+use crate::clock::{Clock, SystemClock};
 use crate::environment::Environment;
+use crate::execution_hook::ExecutionHook;
 use crate::expr::Expr;
 use crate::lox_callable::LoxCallable;
 use crate::lox_function::LoxFunction;
+use crate::lox_module::LoxModule;
 use crate::native_functions::global_env;
-use crate::runtime_error::{Return, RuntimeError};
-use crate::stmt::{LoxFunctionNode, Stmt};
+use crate::rng::Rng;
+use crate::runtime_error::{Exit, Return, RuntimeError, StackFrame, TailCall};
+use crate::stmt::{CatchClause, LoxFunctionNode, Stmt};
 use crate::token::{Literal, Token};
 use crate::token_type::TokenType;
 use crate::value::Value;
 use crate::value::Value::*;
 use crate::Lox;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::thread::JoinHandle;
+
+/// How many Lox calls may be active at once before a call fails with
+/// "Stack overflow.".
+pub(crate) const DEFAULT_MAX_CALL_DEPTH: usize = 4096;
+
+/// Native stack reserved for each allowed Lox call, generous enough for the
+/// nested visitor frames of an unoptimized build. Calls of deeply nested
+/// expressions can need more, which `STACK_LIMIT` catches.
+const STACK_BYTES_PER_CALL: usize = 32 * 1024;
+
+/// Native stack kept free when "Stack overflow." is raised, for the frames
+/// between one check and the next and for unwinding the error.
+const STACK_RESERVE: usize = 256 * 1024;
+
+thread_local! {
+    /// The address below which the interpreter's thread is out of stack, or
+    /// 0 on threads not started by `spawn_with_stack`.
+    static STACK_LIMIT: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs `f` on a new thread with enough native stack for `max_call_depth`
+/// nested Lox calls, recording where that stack ends so that a call or an
+/// expression going past it fails with "Stack overflow." instead of
+/// aborting the process.
+pub(crate) fn spawn_with_stack<T, F>(max_call_depth: usize, f: F) -> std::io::Result<JoinHandle<T>>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let size = Interpreter::stack_size(max_call_depth);
+    std::thread::Builder::new().stack_size(size).spawn(move || {
+        let limit = stack_address().saturating_sub(size) + STACK_RESERVE;
+        STACK_LIMIT.with(|cell| cell.set(limit));
+        f()
+    })
+}
+
+/// Roughly where the native stack currently ends. It grows downwards.
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// Whether the native stack is too low to go any deeper.
+fn stack_exhausted() -> bool {
+    stack_address() < STACK_LIMIT.with(Cell::get)
+}
+
+fn stack_overflow(token: &Token) -> Box<dyn Error> {
+    Box::new(RuntimeError::new(
+        token.clone(),
+        "Stack overflow.".to_string(),
+    ))
+}
 
 pub(crate) struct Interpreter {
     pub(crate) globals: Rc<RefCell<Environment>>,
     pub(crate) environment: Rc<RefCell<Environment>>,
+    call_stack: Vec<CallFrame>,
+    max_call_depth: usize,
+    /// How many `try` statements of the current call are being executed.
+    try_depth: usize,
+    /// The file being executed, which imports are resolved against. Calls
+    /// switch to the file their function was declared in.
+    current_file: Option<Rc<Path>>,
+    /// Imported modules by canonical path, so each file runs only once.
+    modules: HashMap<PathBuf, Rc<LoxModule>>,
+    /// Modules whose top level is still running, to detect import cycles.
+    loading: Vec<PathBuf>,
+    /// Whether natives that touch files or standard streams are refused.
+    sandboxed: bool,
+    /// The script's command-line arguments, bound to `args` in every module.
+    args: Value,
+    /// The generator behind the random natives, seeded from the clock unless
+    /// `--seed` or `seed()` fixes it.
+    rng: Rng,
+    /// Where `clock`, `now`, `sleep` and the date natives get the time.
+    clock: Box<dyn Clock>,
+    /// Watch the script for debugging, tracing and the like. Taken out while
+    /// they run, so statements they execute themselves are not seen by them.
+    hooks: Vec<Box<dyn ExecutionHook>>,
+    /// Where `print` writes.
+    output: Box<dyn Write>,
+}
+
+/// An active call: the function's name and the token of the call that
+/// entered it.
+struct CallFrame {
+    name: std::string::String,
+    call_site: Token,
+    native: bool,
+    /// The caller's `try_depth`, restored when the call returns.
+    try_depth: usize,
+    /// The caller's environment and file, for inspecting the frames below
+    /// the top.
+    environment: Rc<RefCell<Environment>>,
+    file: Option<Rc<Path>>,
 }
 
 impl Interpreter {
     pub(crate) fn new() -> Self {
         let globals = global_env();
+        globals
+            .borrow_mut()
+            .define("args".to_string(), Value::new_list(Vec::new()));
         let environment = Rc::clone(&globals);
 
         Self {
             globals,
             environment,
+            call_stack: Vec::new(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            try_depth: 0,
+            current_file: None,
+            modules: HashMap::new(),
+            loading: Vec::new(),
+            sandboxed: false,
+            args: Value::new_list(Vec::new()),
+            rng: Rng::from_time(),
+            clock: Box::new(SystemClock::new()),
+            hooks: Vec::new(),
+            output: Box::new(std::io::stdout()),
         }
     }
+
+    /// Makes the file and standard stream natives raise a runtime error
+    /// instead of doing I/O.
+    pub(crate) fn set_sandboxed(&mut self, sandboxed: bool) {
+        self.sandboxed = sandboxed;
+    }
+
+    pub(crate) fn sandboxed(&self) -> bool {
+        self.sandboxed
+    }
+
+    pub(crate) fn set_args(&mut self, args: Vec<std::string::String>) {
+        self.args = Value::new_list(args.into_iter().map(String).collect());
+        self.globals
+            .borrow_mut()
+            .define("args".to_string(), self.args.clone());
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Replaces the source of time, e.g. with a `FixedClock` so that a run
+    /// does not depend on when or how fast it happens.
+    pub(crate) fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub(crate) fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    pub(crate) fn add_hook(&mut self, hook: Box<dyn ExecutionHook>) {
+        self.hooks.push(hook);
+    }
+
+    /// Lets the hooks know the script has finished.
+    pub(crate) fn finish_hooks(&mut self) {
+        let _ = self.run_hooks(|hook, interpreter| {
+            hook.finish(interpreter);
+            Ok(())
+        });
+    }
+
+    /// Runs `f` on each hook in the order they were added, stopping at the
+    /// first error.
+    fn run_hooks(
+        &mut self,
+        mut f: impl FnMut(&mut dyn ExecutionHook, &mut Self) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut hooks = std::mem::take(&mut self.hooks);
+        let result = hooks.iter_mut().try_for_each(|hook| f(hook.as_mut(), self));
+        self.hooks = hooks;
+        result
+    }
+
+    /// Sends what `print` writes somewhere other than standard output, e.g.
+    /// to a debugger's client.
+    pub(crate) fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    pub(crate) fn current_file(&self) -> Option<&Path> {
+        self.current_file.as_deref()
+    }
+
+    /// The current file, for keeping without borrowing the interpreter.
+    pub(crate) fn shared_current_file(&self) -> Option<Rc<Path>> {
+        self.current_file.clone()
+    }
+
+    /// Sets the file imports are resolved against, returning the previous one.
+    pub(crate) fn set_current_file(&mut self, path: Option<Rc<Path>>) -> Option<Rc<Path>> {
+        std::mem::replace(&mut self.current_file, path)
+    }
+
+    /// Sets how deep Lox calls may nest. On a thread from `spawn_with_stack`
+    /// for the same depth the limit is usually reached before the native
+    /// stack runs low; either way the call fails with "Stack overflow.".
+    pub(crate) fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// The native stack size needed to run `max_call_depth` nested Lox calls.
+    pub(crate) fn stack_size(max_call_depth: usize) -> usize {
+        max_call_depth
+            .saturating_add(1)
+            .saturating_mul(STACK_BYTES_PER_CALL)
+            .saturating_add(STACK_RESERVE)
+    }
+
+    pub(crate) fn enter_call(
+        &mut self,
+        callee: &LoxCallable,
+        paren: &Token,
+        arguments: &[Value],
+    ) -> Result<(), Box<dyn Error>> {
+        if self.call_stack.len() >= self.max_call_depth || stack_exhausted() {
+            return Err(stack_overflow(paren));
+        }
+        let (name, native) = match callee {
+            LoxCallable::Function(f) => (f.declaration.name.lexeme.clone(), false),
+            LoxCallable::NativeFunction(f) => (f.name.clone(), true),
+        };
+        self.call_stack.push(CallFrame {
+            name,
+            call_site: paren.clone(),
+            native,
+            try_depth: std::mem::take(&mut self.try_depth),
+            environment: self.environment.clone(),
+            file: self.current_file.clone(),
+        });
+        if !self.hooks.is_empty() {
+            let _ = self.run_hooks(|hook, interpreter| {
+                hook.enter_function(interpreter, callee, arguments);
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// Leaves the call `callee` entered, which ended with `result`.
+    pub(crate) fn exit_call(&mut self, callee: &LoxCallable, result: Result<&Value, &dyn Error>) {
+        if !self.hooks.is_empty() {
+            let _ = self.run_hooks(|hook, interpreter| {
+                hook.exit_function(interpreter, callee, result);
+                Ok(())
+            });
+        }
+        if let Some(frame) = self.call_stack.pop() {
+            self.try_depth = frame.try_depth;
+        }
+    }
+
+    /// A tail call reuses the caller's frame, so the frame takes on the name
+    /// of the function being called.
+    pub(crate) fn enter_tail_call(&mut self, function: &LoxFunction, arguments: &[Value]) {
+        if let Some(frame) = self.call_stack.last_mut() {
+            frame.name = function.declaration.name.lexeme.clone();
+        }
+        if !self.hooks.is_empty() {
+            let callee = LoxCallable::Function(function.clone());
+            let _ = self.run_hooks(|hook, interpreter| {
+                hook.enter_function(interpreter, &callee, arguments);
+                Ok(())
+            });
+        }
+    }
+
+    /// Records the current call stack on a runtime error that does not have
+    /// one yet. Called as the error leaves each call, so the innermost call
+    /// is the one whose stack gets recorded.
+    pub(crate) fn attach_stack_trace(&self, error: Box<dyn Error>) -> Box<dyn Error> {
+        match error.downcast::<RuntimeError>() {
+            Ok(mut error) => {
+                if error.stack_trace.is_empty() {
+                    error.stack_trace = self.stack_trace(error.token.line);
+                }
+                error
+            }
+            Err(error) => error,
+        }
+    }
+
+    /// How many calls are active.
+    pub(crate) fn call_depth(&self) -> usize {
+        self.call_stack.len()
+    }
+
+    /// The active calls, innermost first, for a program stopped at `line`.
+    pub(crate) fn stack_trace(&self, line: i32) -> Vec<StackFrame> {
+        let mut trace = Vec::new();
+        let mut line = Some(line);
+        for frame in self.call_stack.iter().rev() {
+            trace.push(StackFrame {
+                function: frame.name.clone(),
+                line: if frame.native { None } else { line },
+            });
+            line = Some(frame.call_site.line);
+        }
+        trace.push(StackFrame {
+            function: "<script>".to_string(),
+            line,
+        });
+        trace
+    }
+
     pub(crate) fn interpret(&mut self, statements: Vec<Stmt>) {
         for i in statements {
             if let Err(e) = self.execute(&i) {
-                Lox::runtime_error(*e.downcast::<RuntimeError>().unwrap());
+                self.report(e);
                 return;
             }
         }
     }
+
+    /// Runs an entry typed at the prompt. When `echo` is set the last
+    /// statement is a bare expression, and its value is returned instead of
+    /// discarded.
+    pub(crate) fn interpret_entry(
+        &mut self,
+        mut statements: Vec<Stmt>,
+        echo: bool,
+    ) -> Option<Value> {
+        let last = if echo { statements.pop() } else { None };
+        for stmt in &statements {
+            if let Err(e) = self.execute(stmt) {
+                self.report(e);
+                return None;
+            }
+        }
+        let Some(Stmt::Expression { expression, .. }) = last else {
+            return None;
+        };
+        match self.evaluate(&expression) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.report(e);
+                None
+            }
+        }
+    }
+
+    /// Hands an error that escaped the script to `Lox`.
+    fn report(&mut self, e: Box<dyn Error>) {
+        if let Some(exit) = e.downcast_ref::<Exit>() {
+            Lox::exit(exit.0);
+            return;
+        }
+        let e = self.attach_stack_trace(e);
+        // The parser only allows `return` inside functions, but should one
+        // get out anyway it just ends the script.
+        if let Ok(error) = e.downcast::<RuntimeError>() {
+            Lox::runtime_error(*error);
+        }
+    }
+
+    /// Forgets every global and imported module, keeping the settings made
+    /// through the other setters.
+    pub(crate) fn reset(&mut self) {
+        self.globals = global_env();
+        self.globals
+            .borrow_mut()
+            .define("args".to_string(), self.args.clone());
+        self.environment = Rc::clone(&self.globals);
+        self.call_stack.clear();
+        self.try_depth = 0;
+        self.modules.clear();
+        self.loading.clear();
+    }
     fn evaluate(&mut self, expr: &Expr) -> Result<Value, Box<dyn Error>> {
+        // Outside any call, expressions nest no deeper than the parser, which
+        // ran at about the same depth, already managed.
+        if let Some(frame) = self.call_stack.last() {
+            if stack_exhausted() {
+                return Err(stack_overflow(&frame.call_site));
+            }
+        }
         expr.accept(self)
     }
 
+    /// Evaluates the condition of an `if` or `while` to whether it holds.
+    fn evaluate_condition(
+        &mut self,
+        keyword: &Token,
+        condition: &Expr,
+    ) -> Result<bool, Box<dyn Error>> {
+        let truthy = *self.evaluate(condition)?.as_ref();
+        self.branch(keyword, truthy);
+        Ok(truthy)
+    }
+
+    /// Lets the hooks know which way a condition went.
+    fn branch(&mut self, token: &Token, truthy: bool) {
+        if !self.hooks.is_empty() {
+            let _ = self.run_hooks(|hook, interpreter| {
+                hook.branch(interpreter, token, truthy);
+                Ok(())
+            });
+        }
+    }
+
     fn execute(&mut self, stmt: &Stmt) -> Result<(), Box<dyn Error>> {
-        stmt.accept(self)
+        if stack_exhausted() {
+            return Err(stack_overflow(stmt.token()));
+        }
+        if self.hooks.is_empty() {
+            return stmt.accept(self);
+        }
+        let result = self
+            .run_hooks(|hook, interpreter| hook.before_statement(interpreter, stmt))
+            .and_then(|()| stmt.accept(self));
+        let _ = self.run_hooks(|hook, interpreter| {
+            hook.after_statement(interpreter, stmt);
+            Ok(())
+        });
+        result
+    }
+
+    /// The environment of a frame of `stack_trace`, 0 being the innermost.
+    pub(crate) fn frame_environment(&self, frame: usize) -> Option<Rc<RefCell<Environment>>> {
+        match frame {
+            0 => Some(self.environment.clone()),
+            _ => self
+                .call_stack
+                .len()
+                .checked_sub(frame)
+                .map(|i| self.call_stack[i].environment.clone()),
+        }
+    }
+
+    /// The file of a frame of `stack_trace`, 0 being the innermost.
+    pub(crate) fn frame_file(&self, frame: usize) -> Option<&Path> {
+        match frame {
+            0 => self.current_file(),
+            _ => self
+                .call_stack
+                .len()
+                .checked_sub(frame)
+                .and_then(|i| self.call_stack[i].file.as_deref()),
+        }
+    }
+
+    /// Evaluates an expression as if it appeared where `environment` is
+    /// current, e.g. for a debugger.
+    pub(crate) fn evaluate_in(
+        &mut self,
+        expr: &Expr,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Value, Box<dyn Error>> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = self.evaluate(expr);
+        self.environment = previous;
+        result
     }
 
     pub(crate) fn execute_block(
         &mut self,
-        statements: &Vec<Stmt>,
+        statements: &[Stmt],
         environment: Rc<RefCell<Environment>>,
     ) -> Result<(), Box<dyn Error>> {
         let previous = self.environment.clone();
@@ -62,6 +499,119 @@ impl Interpreter {
         Ok(())
     }
 
+    /// Evaluates the callee and arguments of a call and checks the arity,
+    /// leaving the actual call to the caller.
+    fn evaluate_call(
+        &mut self,
+        callee: &Expr,
+        paren: &Token,
+        arguments: &[Expr],
+    ) -> Result<(Box<LoxCallable>, Vec<Value>), Box<dyn Error>> {
+        // getCallback(1+2)();
+        let callee = self.evaluate(callee);
+
+        let arguments = arguments
+            .iter()
+            .map(|f| self.evaluate(f))
+            .collect::<Result<Vec<Value>, Box<dyn Error>>>()?;
+
+        let function = match callee {
+            Ok(Callable(lox_callable)) => lox_callable,
+            _ => {
+                return Err(Box::new(RuntimeError::new(
+                    paren.clone(),
+                    "Can only call functions.".to_string(),
+                )))
+            }
+        };
+
+        let arity = function.arity();
+        if !arity.contains(&arguments.len()) {
+            let expected = if arity.start() == arity.end() {
+                arity.start().to_string()
+            } else if *arity.end() == usize::MAX {
+                format!("at least {}", arity.start())
+            } else {
+                format!("{} to {}", arity.start(), arity.end())
+            };
+            return Err(Box::new(RuntimeError::new(
+                paren.clone(),
+                format!(
+                    "Expected {} arguments but got {}.",
+                    expected,
+                    arguments.len()
+                ),
+            )));
+        }
+
+        Ok((function, arguments))
+    }
+
+    /// Loads the module at the string literal `path`, resolved relative to
+    /// the importing file, running it the first time it is imported.
+    fn import_module(&mut self, path: &Token) -> Result<Rc<LoxModule>, Box<dyn Error>> {
+        let Some(Literal::String(relative)) = &path.literal else {
+            unreachable!("Module path must be a string literal")
+        };
+        let base = self
+            .current_file
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let resolved = base.join(relative).canonicalize().map_err(|e| {
+            RuntimeError::new(
+                path.clone(),
+                format!("Cannot import '{}': {}.", relative, e),
+            )
+        })?;
+
+        if let Some(module) = self.modules.get(&resolved) {
+            return Ok(module.clone());
+        }
+        if let Some(start) = self.loading.iter().position(|p| *p == resolved) {
+            let cycle = self.loading[start..]
+                .iter()
+                .chain([&resolved])
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(Box::new(RuntimeError::new(
+                path.clone(),
+                format!("Import cycle: {}.", cycle),
+            )));
+        }
+
+        let source = std::fs::read_to_string(&resolved).map_err(|e| {
+            RuntimeError::new(
+                path.clone(),
+                format!("Cannot import '{}': {}.", relative, e),
+            )
+        })?;
+        let statements = Lox::parse_module(source).ok_or_else(|| {
+            RuntimeError::new(
+                path.clone(),
+                format!("Could not compile module '{}'.", relative),
+            )
+        })?;
+
+        let natives = global_env();
+        natives
+            .borrow_mut()
+            .define("args".to_string(), self.args.clone());
+        let environment = Environment::new_enclosing(natives);
+        self.loading.push(resolved.clone());
+        let previous_file = self.current_file.replace(resolved.as_path().into());
+        let result = self.execute_block(&statements, environment.clone());
+        self.current_file = previous_file;
+        self.loading.pop();
+        result?;
+
+        let module = Rc::new(LoxModule::new(resolved.clone(), environment));
+        self.modules.insert(resolved, module.clone());
+        Ok(module)
+    }
+
     fn check_number_operand(operator: &Token, operand: &Value) -> Result<(), Box<dyn Error>> {
         if let Number(_) = operand {
             return Ok(());
@@ -135,14 +685,8 @@ impl crate::expr::Visitor<Result<Value, Box<dyn Error>>> for Interpreter {
                 Self::check_number_operands(operator, &left_value, &right_value)?;
                 Ok(Boolean(left_value <= right_value))
             }
-            TokenType::BANG_EQUAL => {
-                Self::check_number_operands(operator, &left_value, &right_value)?;
-                Ok(Boolean(left_value != right_value))
-            }
-            TokenType::EQUAL_EQUAL => {
-                Self::check_number_operands(operator, &left_value, &right_value)?;
-                Ok(Boolean(left_value == right_value))
-            }
+            TokenType::BANG_EQUAL => Ok(Boolean(left_value != right_value)),
+            TokenType::EQUAL_EQUAL => Ok(Boolean(left_value == right_value)),
             _ => unreachable!("Invalid binary operator"),
         }
     }
@@ -180,51 +724,27 @@ impl crate::expr::Visitor<Result<Value, Box<dyn Error>>> for Interpreter {
         &mut self,
         callee: &Expr,
         paren: &Token,
-        arguments: &Vec<Box<Expr>>,
+        arguments: &[Expr],
     ) -> Result<Value, Box<dyn Error>> {
-        // getCallback(1+2)();
-        let callee = self.evaluate(callee);
-
-        let arguments = arguments
-            .into_iter()
-            .map(|f| match self.evaluate(f) {
-                Ok(v) => Ok(Box::new(v)),
-                Err(e) => Err(e),
-            })
-            .collect::<Result<Vec<Box<Value>>, Box<dyn Error>>>()?;
-
-        let function = match callee {
-            Ok(Callable(lox_callable)) => lox_callable,
-            _ => {
-                return Err(Box::new(RuntimeError::new(
-                    paren.clone(),
-                    "Can only call functions.".to_string(),
-                )))
-            }
-        };
-
-        if arguments.len() != function.arity() {
-            return Err(Box::new(RuntimeError {
-                token: paren.clone(),
-                message: format!(
-                    "Expected {} arguments but got {}.",
-                    function.arity(),
-                    arguments.len()
-                ),
-            }));
-        }
-
-        Ok(function.call(self, arguments)?)
+        let (function, arguments) = self.evaluate_call(callee, paren, arguments)?;
+        function.call(self, paren, arguments)
     }
 
     fn visit_variable_expr(&mut self, name: &Token) -> Result<Value, Box<dyn Error>> {
-        // Very interesting method to alter the return error type
-        Ok(self.environment.borrow().get(name)?)
+        // `?` would box the `Box<RuntimeError>` a second time, which hides it
+        // from `downcast`, so unsize it by hand.
+        self.environment
+            .borrow()
+            .get(name)
+            .map_err(|e| e as Box<dyn Error>)
     }
 
     fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Result<Value, Box<dyn Error>> {
         let value = self.evaluate(value)?;
-        self.environment.borrow_mut().assign(name, value.clone())?;
+        self.environment
+            .borrow_mut()
+            .assign(name, value.clone())
+            .map_err(|e| e as Box<dyn Error>)?;
         Ok(value)
     }
 
@@ -235,16 +755,119 @@ impl crate::expr::Visitor<Result<Value, Box<dyn Error>>> for Interpreter {
         right: &Expr,
     ) -> Result<Value, Box<dyn Error>> {
         let left = self.evaluate(left)?;
+        let truthy = *left.as_ref();
+        self.branch(operator, truthy);
         if operator.token_type == TokenType::OR {
-            if *left.as_ref() {
+            if truthy {
                 return Ok(left);
             }
-        } else if !*left.as_ref() {
+        } else if !truthy {
             return Ok(left);
         }
 
         self.evaluate(right)
     }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<Value, Box<dyn Error>> {
+        let object = self.evaluate(object)?;
+        let property = match &object {
+            Error(error) => match name.lexeme.as_str() {
+                "message" => Some(String(error.message.clone())),
+                "line" => Some(Number(error.line as f64)),
+                _ => None,
+            },
+            Module(module) => module
+                .environment
+                .borrow()
+                .values
+                .get(&name.lexeme)
+                .cloned(),
+            Map(map) => map.borrow().get(&name.lexeme).cloned(),
+            _ => {
+                return Err(Box::new(RuntimeError::new(
+                    name.clone(),
+                    "Only errors, modules and maps have properties.".to_string(),
+                )))
+            }
+        };
+        property.ok_or_else(|| {
+            Box::new(RuntimeError::new(
+                name.clone(),
+                format!("Undefined property '{}'.", name.lexeme),
+            )) as Box<dyn Error>
+        })
+    }
+
+    fn visit_list_expr(&mut self, elements: &[Expr]) -> Result<Value, Box<dyn Error>> {
+        let elements = elements
+            .iter()
+            .map(|e| self.evaluate(e))
+            .collect::<Result<Vec<Value>, Box<dyn Error>>>()?;
+        Ok(Value::new_list(elements))
+    }
+
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+    ) -> Result<Value, Box<dyn Error>> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        let error =
+            |message: &str| Box::new(RuntimeError::new(bracket.clone(), message.to_string()));
+
+        // Maps are indexed by key, and a missing key reads as nil.
+        if let Map(map) = &object {
+            return match index {
+                String(key) => Ok(map.borrow().get(&key).cloned().unwrap_or(Nil)),
+                _ => Err(error("Map keys must be strings.")),
+            };
+        }
+        let index = match index {
+            Number(n) if n >= 0.0 && n.fract() == 0.0 => n as usize,
+            Number(_) => return Err(error("Index must be a non-negative integer.")),
+            _ => return Err(error("Index must be a number.")),
+        };
+        let element = match &object {
+            // Strings are indexed by character, not by byte.
+            String(s) => s.chars().nth(index).map(|c| String(c.to_string())),
+            List(list) => list.borrow().get(index).cloned(),
+            _ => return Err(error("Only strings, lists and maps can be indexed.")),
+        };
+        element.ok_or_else(|| error("Index out of range.") as Box<dyn Error>)
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<Value, Box<dyn Error>> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        let value = self.evaluate(value)?;
+        let error =
+            |message: &str| Box::new(RuntimeError::new(bracket.clone(), message.to_string()));
+
+        match (&object, index) {
+            (Map(map), String(key)) => {
+                map.borrow_mut().insert(key, value.clone());
+            }
+            (Map(_), _) => return Err(error("Map keys must be strings.")),
+            (List(list), Number(n)) if n >= 0.0 && n.fract() == 0.0 => {
+                match list.borrow_mut().get_mut(n as usize) {
+                    Some(element) => *element = value.clone(),
+                    None => return Err(error("Index out of range.")),
+                }
+            }
+            (List(_), Number(_)) => return Err(error("Index must be a non-negative integer.")),
+            (List(_), _) => return Err(error("Index must be a number.")),
+            _ => return Err(error("Only lists and maps can be assigned by index.")),
+        }
+        Ok(value)
+    }
 }
 
 impl crate::stmt::Visitor<Result<(), Box<dyn Error>>> for Interpreter {
@@ -255,13 +878,38 @@ impl crate::stmt::Visitor<Result<(), Box<dyn Error>>> for Interpreter {
 
     fn visit_print_stmt(&mut self, expr: &Expr) -> Result<(), Box<dyn Error>> {
         let value = self.evaluate(expr)?;
-        println!("{}", value);
+        // Like `println!`, except that a closed output is not worth a panic.
+        let _ = writeln!(self.output, "{}", value);
         Ok(())
     }
 
-    fn visit_return_stmt(&mut self, keyword: &Token, value: &Expr) -> Result<(), Box<dyn Error>> {
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: &Expr) -> Result<(), Box<dyn Error>> {
+        // `return f(...)` is a tail call: hand it back to `LoxCallable::call`
+        // instead of calling it from here. Inside a `try` the call has to
+        // finish before the handlers stop applying, so it is made normally.
+        if let (
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            },
+            0,
+        ) = (value, self.try_depth)
+        {
+            let (function, arguments) = self.evaluate_call(callee, paren, arguments)?;
+            return match *function {
+                LoxCallable::Function(function) => {
+                    Err(Box::new(TailCall::new(function, arguments)))
+                }
+                function => {
+                    let value = function.call(self, paren, arguments)?;
+                    Err(Box::new(Return::new(value)))
+                }
+            };
+        }
+
         let value = self.evaluate(value)?;
-        Err(Box::new(Return(value)))
+        Err(Box::new(Return::new(value)))
     }
 
     fn visit_var_stmt(
@@ -279,7 +927,7 @@ impl crate::stmt::Visitor<Result<(), Box<dyn Error>>> for Interpreter {
         Ok(())
     }
 
-    fn visit_block_stmt(&mut self, statements: &Vec<Stmt>) -> Result<(), Box<dyn Error>> {
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<(), Box<dyn Error>> {
         self.execute_block(
             statements,
             Environment::new_enclosing(self.environment.clone()),
@@ -288,11 +936,12 @@ impl crate::stmt::Visitor<Result<(), Box<dyn Error>>> for Interpreter {
 
     fn visit_if_stmt(
         &mut self,
+        keyword: &Token,
         condition: &Expr,
         then_branch: &Stmt,
         else_branch: Option<&Stmt>,
     ) -> Result<(), Box<dyn Error>> {
-        if *self.evaluate(condition)?.as_ref() {
+        if self.evaluate_condition(keyword, condition)? {
             self.execute(then_branch)?;
         } else if let Some(else_branch) = else_branch {
             self.execute(else_branch)?;
@@ -301,20 +950,123 @@ impl crate::stmt::Visitor<Result<(), Box<dyn Error>>> for Interpreter {
         Ok(())
     }
 
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<(), Box<dyn Error>> {
-        while *self.evaluate(condition)?.as_ref() {
+    fn visit_while_stmt(
+        &mut self,
+        keyword: &Token,
+        condition: &Expr,
+        body: &Stmt,
+    ) -> Result<(), Box<dyn Error>> {
+        while self.evaluate_condition(keyword, condition)? {
             self.execute(body)?;
         }
 
         Ok(())
     }
 
-    fn visit_function_stmt(&mut self, stmt: Box<LoxFunctionNode>) -> Result<(), Box<dyn Error>> {
-        let function = LoxFunction::new(stmt.clone(), self.environment.clone());
+    fn visit_function_stmt(&mut self, stmt: Rc<LoxFunctionNode>) -> Result<(), Box<dyn Error>> {
+        let function = LoxFunction::new(
+            stmt.clone(),
+            self.environment.clone(),
+            self.current_file.clone(),
+        );
         self.environment.borrow_mut().define(
             stmt.name.lexeme.clone(),
             Callable(Box::new(LoxCallable::Function(function))),
         );
         Ok(())
     }
+
+    fn visit_throw_stmt(&mut self, keyword: &Token, value: &Expr) -> Result<(), Box<dyn Error>> {
+        let value = self.evaluate(value)?;
+        Err(Box::new(RuntimeError::thrown(keyword.clone(), value)))
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        body: &[Stmt],
+        catch_clause: Option<&CatchClause>,
+        finally_body: Option<&[Stmt]>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.try_depth += 1;
+        let mut result =
+            self.execute_block(body, Environment::new_enclosing(self.environment.clone()));
+
+        // Only errors are caught; `return` passes through to the finally.
+        if let Some(catch_clause) = catch_clause {
+            result = match result {
+                Err(error) if error.is::<RuntimeError>() => {
+                    let error = error.downcast::<RuntimeError>().unwrap();
+                    let environment = Environment::new_enclosing(self.environment.clone());
+                    environment
+                        .borrow_mut()
+                        .define(catch_clause.name.lexeme.clone(), error.into_value());
+                    self.execute_block(&catch_clause.body, environment)
+                }
+                result => result,
+            };
+        }
+        self.try_depth -= 1;
+
+        if let Some(finally_body) = finally_body {
+            self.execute_block(
+                finally_body,
+                Environment::new_enclosing(self.environment.clone()),
+            )?;
+        }
+        result
+    }
+
+    /// Tests only run under `rlox test`.
+    fn visit_test_stmt(
+        &mut self,
+        _keyword: &Token,
+        _name: &Token,
+        _body: &[Stmt],
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _keyword: &Token,
+        path: &Token,
+        alias: &Token,
+    ) -> Result<(), Box<dyn Error>> {
+        let module = self.import_module(path)?;
+        self.environment
+            .borrow_mut()
+            .define(alias.lexeme.clone(), Module(module));
+        Ok(())
+    }
+
+    fn visit_from_import_stmt(
+        &mut self,
+        _keyword: &Token,
+        path: &Token,
+        names: &[Token],
+    ) -> Result<(), Box<dyn Error>> {
+        let module = self.import_module(path)?;
+        for name in names {
+            let value = module
+                .environment
+                .borrow()
+                .values
+                .get(&name.lexeme)
+                .cloned();
+            let Some(value) = value else {
+                return Err(Box::new(RuntimeError::new(
+                    name.clone(),
+                    format!(
+                        "Module '{}' does not define '{}'.",
+                        path.lexeme.trim_matches('"'),
+                        name.lexeme
+                    ),
+                )));
+            };
+            self.environment
+                .borrow_mut()
+                .define(name.lexeme.clone(), value);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,398 @@
+use std::fmt::{Display, Formatter};
+
+/// How deeply arrays and objects may nest, so that a hostile document cannot
+/// exhaust the native stack of the recursive parser.
+pub(crate) const MAX_DEPTH: usize = 512;
+
+/// A JSON document. Objects keep their keys in source order.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// A syntax error in a JSON document, at a 1-based line and column.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct JsonError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl Json {
+    pub(crate) fn parse(source: &str) -> Result<Json, JsonError> {
+        let mut parser = JsonParser {
+            source: source.chars().collect(),
+            current: 0,
+            line: 1,
+            column: 1,
+            depth: 0,
+        };
+        parser.skip_whitespace();
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if !parser.is_at_end() {
+            return Err(parser.error("Unexpected data after the value."));
+        }
+        Ok(value)
+    }
+
+    /// The member `key` of an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Serializes on one line, or with `indent` spaces per nesting level.
+    pub(crate) fn stringify(&self, indent: Option<usize>) -> String {
+        let mut out = String::new();
+        self.write(&mut out, indent, 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: Option<usize>, depth: usize) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(&b.to_string()),
+            Json::Number(n) => out.push_str(&n.to_string()),
+            Json::String(s) => write_string(out, s),
+            Json::Array(elements) => {
+                if elements.is_empty() {
+                    out.push_str("[]");
+                    return;
+                }
+                out.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent, depth + 1);
+                    element.write(out, indent, depth + 1);
+                }
+                newline(out, indent, depth);
+                out.push(']');
+            }
+            Json::Object(members) => {
+                if members.is_empty() {
+                    out.push_str("{}");
+                    return;
+                }
+                out.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent, depth + 1);
+                    write_string(out, key);
+                    out.push(':');
+                    if indent.is_some() {
+                        out.push(' ');
+                    }
+                    value.write(out, indent, depth + 1);
+                }
+                newline(out, indent, depth);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn newline(out: &mut String, indent: Option<usize>, depth: usize) {
+    if let Some(indent) = indent {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent * depth));
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct JsonParser {
+    source: Vec<char>,
+    current: usize,
+    line: usize,
+    column: usize,
+    /// How many arrays and objects enclose the current position.
+    depth: usize,
+}
+
+impl JsonParser {
+    fn value(&mut self) -> Result<Json, JsonError> {
+        match self.peek() {
+            Some('{' | '[') if self.depth == MAX_DEPTH => Err(self.error("Nesting too deep.")),
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('n') => self.literal("null", Json::Null),
+            Some(c) => Err(self.error(&format!("Unexpected character '{}'.", c))),
+            None => Err(self.error("Unexpected end of input.")),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Json, JsonError>,
+    ) -> Result<Json, JsonError> {
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.advance();
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.match_char('}') {
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("Expected a string key."));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.match_char(':') {
+                return Err(self.error("Expected ':' after key."));
+            }
+            self.skip_whitespace();
+            let value = self.value()?;
+            match members.iter_mut().find(|(k, _)| *k == key) {
+                Some(member) => member.1 = value,
+                None => members.push((key, value)),
+            }
+            self.skip_whitespace();
+            if self.match_char('}') {
+                return Ok(Json::Object(members));
+            }
+            if !self.match_char(',') {
+                return Err(self.error("Expected ',' or '}' after object member."));
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.advance();
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.match_char(']') {
+            return Ok(Json::Array(elements));
+        }
+        loop {
+            self.skip_whitespace();
+            elements.push(self.value()?);
+            self.skip_whitespace();
+            if self.match_char(']') {
+                return Ok(Json::Array(elements));
+            }
+            if !self.match_char(',') {
+                return Err(self.error("Expected ',' or ']' after array element."));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.advance();
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("Unterminated string.")),
+                Some('"') => {
+                    self.advance();
+                    return Ok(s);
+                }
+                Some('\\') => {
+                    self.advance();
+                    let escaped = match self.peek() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            self.advance();
+                            s.push(self.unicode_escape()?);
+                            continue;
+                        }
+                        _ => return Err(self.error("Invalid escape sequence.")),
+                    };
+                    self.advance();
+                    s.push(escaped);
+                }
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(self.error("Control character in string."))
+                }
+                Some(c) => {
+                    self.advance();
+                    s.push(c);
+                }
+            }
+        }
+    }
+
+    /// The character after `\u`, combining a surrogate pair if there is one.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("Invalid unicode escape."));
+        }
+        if !(self.match_char('\\') && self.match_char('u')) {
+            return Err(self.error("Expected a low surrogate."));
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("Expected a low surrogate."));
+        }
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+        char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape."))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    self.advance();
+                    code = code * 16 + digit;
+                }
+                None => return Err(self.error("Expected four hex digits.")),
+            }
+        }
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.current;
+        let (line, column) = (self.line, self.column);
+        self.match_char('-');
+        if !self.match_char('0') && !self.digits() {
+            return Err(self.error("Expected a digit."));
+        }
+        if self.match_char('.') && !self.digits() {
+            return Err(self.error("Expected a digit after '.'."));
+        }
+        if self.match_char('e') || self.match_char('E') {
+            if !self.match_char('+') {
+                self.match_char('-');
+            }
+            if !self.digits() {
+                return Err(self.error("Expected a digit in the exponent."));
+            }
+        }
+        let text: String = self.source[start..self.current].iter().collect();
+        text.parse().map(Json::Number).map_err(|_| JsonError {
+            message: "Invalid number.".to_string(),
+            line,
+            column,
+        })
+    }
+
+    /// Consumes a run of digits, returning whether there were any.
+    fn digits(&mut self) -> bool {
+        let start = self.current;
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.advance();
+        }
+        self.current > start
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in word.chars() {
+            if !self.match_char(expected) {
+                return Err(self.error(&format!("Expected '{}'.", word)));
+            }
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.advance();
+        }
+    }
+
+    fn match_char(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn advance(&mut self) {
+        if self.peek() == Some('\n') {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        self.current += 1;
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source.get(self.current).copied()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.current >= self.source.len()
+    }
+
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            message: message.to_string(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
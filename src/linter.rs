@@ -0,0 +1,510 @@
+use crate::expr::{self, Expr};
+use crate::formatter::collect_lox_files;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::stmt::{self, CatchClause, LoxFunctionNode, Stmt};
+use crate::token::{Literal, Token};
+use crate::Lox;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+/// A problem the linter found, named by the rule that found it so it can be
+/// suppressed with a `// lint: allow(rule)` comment.
+#[derive(Debug, Clone)]
+pub(crate) struct Warning {
+    pub(crate) token: Token,
+    pub(crate) rule: &'static str,
+    pub(crate) message: String,
+}
+
+/// Lints a script, returning its warnings in source order, or `None` if it
+/// has syntax errors (which are reported as usual).
+pub(crate) fn lint_source(source: String) -> Option<Vec<Warning>> {
+    let (tokens, statements) = Lox::isolated(|| {
+        let tokens = Scanner::with_comments(source).scan_tokens();
+        let statements = Parser::new(tokens.clone()).parse();
+        (tokens, statements)
+    })?;
+    Some(lint(&tokens, &statements))
+}
+
+/// The warnings for a parsed script, in source order. `tokens` must have
+/// been scanned with comments so the `allow` comments can be found.
+pub(crate) fn lint(tokens: &[Token], statements: &[Stmt]) -> Vec<Warning> {
+    let mut linter = Linter::new(statements);
+    linter.statements(statements);
+    linter.end_scope();
+
+    let allowed = allowed_rules(tokens);
+    let mut warnings: Vec<Warning> = linter
+        .warnings
+        .into_iter()
+        .filter(|warning| {
+            let line = warning.token.line;
+            ![line, line - 1].iter().any(|line| {
+                allowed
+                    .get(line)
+                    .is_some_and(|rules| rules.iter().any(|rule| rule == warning.rule))
+            })
+        })
+        .collect();
+    warnings.sort_by_key(|warning| (warning.token.line, warning.token.column));
+    warnings
+}
+
+/// The rules allowed by `// lint: allow(a, b)` comments, by line. A comment
+/// covers its own line and the one after it.
+fn allowed_rules(tokens: &[Token]) -> HashMap<i32, Vec<String>> {
+    let mut allowed: HashMap<i32, Vec<String>> = HashMap::new();
+    for comment in tokens.iter().flat_map(|token| &token.comments) {
+        let text = comment.text.trim_start_matches('/').trim();
+        let Some(rules) = text
+            .strip_prefix("lint:")
+            .map(str::trim_start)
+            .and_then(|text| text.strip_prefix("allow("))
+            .and_then(|text| text.split(')').next())
+        else {
+            continue;
+        };
+        allowed
+            .entry(comment.line)
+            .or_default()
+            .extend(rules.split(',').map(|rule| rule.trim().to_string()));
+    }
+    allowed
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BindingKind {
+    Variable,
+    Function,
+    Parameter,
+    /// Catch variables and imports, which are never reported as unused.
+    Other,
+}
+
+struct Binding {
+    token: Token,
+    kind: BindingKind,
+    used: bool,
+}
+
+/// Walks the tree keeping track of the names in scope, the way the
+/// interpreter's environments will hold them.
+struct Linter {
+    /// Innermost last. The first is the top level, whose names may be used by
+    /// scripts importing this one.
+    scopes: Vec<HashMap<String, Binding>>,
+    /// Arities of the functions declared exactly once at the top level and
+    /// not redeclared as anything else there.
+    functions: HashMap<String, usize>,
+    warnings: Vec<Warning>,
+}
+
+impl Linter {
+    fn new(statements: &[Stmt]) -> Self {
+        let mut declarations: HashMap<&str, (usize, Option<usize>)> = HashMap::new();
+        for statement in statements {
+            let (name, arity) = match statement {
+                Stmt::Function { function } => (&function.name, Some(function.params.len())),
+                Stmt::Var { name, .. } | Stmt::Import { alias: name, .. } => (name, None),
+                Stmt::FromImport { names, .. } => {
+                    for name in names {
+                        declarations.entry(&name.lexeme).or_default().0 += 2;
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let declaration = declarations.entry(&name.lexeme).or_default();
+            declaration.0 += 1;
+            declaration.1 = arity;
+        }
+        let functions = declarations
+            .into_iter()
+            .filter_map(|(name, declaration)| match declaration {
+                (1, Some(arity)) => Some((name.to_string(), arity)),
+                _ => None,
+            })
+            .collect();
+
+        Linter {
+            scopes: vec![HashMap::new()],
+            functions,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, token: &Token, rule: &'static str, message: String) {
+        self.warnings.push(Warning {
+            token: token.clone(),
+            rule,
+            message,
+        });
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let is_top_level = self.scopes.len() == 1;
+        let scope = self.scopes.pop().unwrap();
+        if !is_top_level {
+            for binding in scope.into_values() {
+                self.check_used(binding);
+            }
+        }
+    }
+
+    fn check_used(&mut self, binding: Binding) {
+        if binding.used || binding.token.lexeme.starts_with('_') {
+            return;
+        }
+        let name = &binding.token.lexeme;
+        let (rule, message) = match binding.kind {
+            BindingKind::Variable => (
+                "unused-variable",
+                format!("Local variable '{}' is never used.", name),
+            ),
+            BindingKind::Function => (
+                "unused-variable",
+                format!("Local function '{}' is never used.", name),
+            ),
+            BindingKind::Parameter => (
+                "unused-parameter",
+                format!("Parameter '{}' is never used.", name),
+            ),
+            BindingKind::Other => return,
+        };
+        self.warn(&binding.token, rule, message);
+    }
+
+    fn declare(&mut self, name: &Token, kind: BindingKind) {
+        let is_local = self.scopes.len() > 1;
+        if is_local {
+            let earlier = self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(&name.lexeme))
+                .map(|binding| binding.token.line);
+            if let Some(line) = earlier {
+                self.warn(
+                    name,
+                    "shadowing",
+                    format!(
+                        "'{}' shadows the declaration on line {}.",
+                        name.lexeme, line
+                    ),
+                );
+            }
+        }
+        let binding = Binding {
+            token: name.clone(),
+            kind,
+            used: false,
+        };
+        let replaced = self
+            .scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.lexeme.clone(), binding);
+        if let Some(replaced) = replaced.filter(|_| is_local) {
+            self.check_used(replaced);
+        }
+    }
+
+    /// Marks the name as used, returning the depth of the scope declaring
+    /// it, or `None` if it is not declared in this script.
+    fn resolve(&mut self, name: &Token) -> Option<usize> {
+        let (depth, binding) = self
+            .scopes
+            .iter_mut()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, scope)| Some((depth, scope.get_mut(&name.lexeme)?)))?;
+        binding.used = true;
+        Some(depth)
+    }
+
+    /// Checks a block's statements, warning once about any that follow a
+    /// `return` or `throw`.
+    fn statements(&mut self, statements: &[Stmt]) {
+        let mut exit = None;
+        for statement in statements {
+            if let Some(keyword) = exit.take() {
+                self.warn(
+                    keyword,
+                    "unreachable-code",
+                    format!("Unreachable code after '{}'.", keyword.lexeme),
+                );
+            }
+            statement.accept(self);
+            exit = match statement {
+                Stmt::Return { keyword, .. } | Stmt::Throw { keyword, .. } => Some(keyword),
+                _ => None,
+            };
+        }
+    }
+
+    fn scoped_statements(&mut self, statements: &[Stmt]) {
+        self.begin_scope();
+        self.statements(statements);
+        self.end_scope();
+    }
+
+    /// `if (x = y)` is usually a mistyped `==`. Wrapping the assignment in
+    /// another pair of parentheses says it is meant.
+    fn check_condition(&mut self, condition: &Expr) {
+        if let Expr::Assign { name, .. } = condition {
+            self.warn(
+                name,
+                "assignment-in-condition",
+                "Assignment used as a condition; did you mean '=='?".to_string(),
+            );
+        }
+    }
+}
+
+impl expr::Visitor<()> for Linter {
+    fn visit_binary_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        left.accept(self);
+        right.accept(self);
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) {
+        expression.accept(self);
+    }
+
+    fn visit_literal_expr(&mut self, _value: &Literal) {}
+
+    fn visit_unary_expr(&mut self, _operator: &Token, right: &Expr) {
+        right.accept(self);
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) {
+        let mut target = callee;
+        while let Expr::Grouping { expression } = target {
+            target = expression;
+        }
+        match target {
+            Expr::Literal { .. } | Expr::List { .. } => self.warn(
+                paren,
+                "not-callable",
+                "Can only call functions.".to_string(),
+            ),
+            Expr::Variable { name } => {
+                let is_global = matches!(self.resolve(name), None | Some(0));
+                match self.functions.get(&name.lexeme) {
+                    Some(&arity) if is_global && arity != arguments.len() => self.warn(
+                        name,
+                        "arity-mismatch",
+                        format!("Expected {} arguments but got {}.", arity, arguments.len()),
+                    ),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        callee.accept(self);
+        for argument in arguments {
+            argument.accept(self);
+        }
+    }
+
+    fn visit_variable_expr(&mut self, name: &Token) {
+        self.resolve(name);
+    }
+
+    /// Assigning to a variable is not a use of it.
+    fn visit_assign_expr(&mut self, _name: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        left.accept(self);
+        right.accept(self);
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, _name: &Token) {
+        object.accept(self);
+    }
+
+    fn visit_list_expr(&mut self, elements: &[Expr]) {
+        for element in elements {
+            element.accept(self);
+        }
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, _bracket: &Token, index: &Expr) {
+        object.accept(self);
+        index.accept(self);
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) {
+        object.accept(self);
+        index.accept(self);
+        value.accept(self);
+    }
+}
+
+impl stmt::Visitor<()> for Linter {
+    fn visit_expression_stmt(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn visit_print_stmt(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) {
+        if let Some(initializer) = initializer {
+            initializer.accept(self);
+        }
+        self.declare(name, BindingKind::Variable);
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) {
+        self.scoped_statements(statements);
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        _keyword: &Token,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) {
+        self.check_condition(condition);
+        condition.accept(self);
+        then_branch.accept(self);
+        if let Some(else_branch) = else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, _keyword: &Token, condition: &Expr, body: &Stmt) {
+        self.check_condition(condition);
+        condition.accept(self);
+        body.accept(self);
+    }
+
+    /// Parameters share a scope with the top of the body, as they do when
+    /// the function is called.
+    fn visit_function_stmt(&mut self, stmt: Rc<LoxFunctionNode>) {
+        self.declare(&stmt.name, BindingKind::Function);
+        self.begin_scope();
+        for param in &stmt.params {
+            self.declare(param, BindingKind::Parameter);
+        }
+        self.statements(&stmt.body);
+        self.end_scope();
+    }
+
+    fn visit_throw_stmt(&mut self, _keyword: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    fn visit_import_stmt(&mut self, _keyword: &Token, _path: &Token, alias: &Token) {
+        self.declare(alias, BindingKind::Other);
+    }
+
+    fn visit_from_import_stmt(&mut self, _keyword: &Token, _path: &Token, names: &[Token]) {
+        for name in names {
+            self.declare(name, BindingKind::Other);
+        }
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        body: &[Stmt],
+        catch_clause: Option<&CatchClause>,
+        finally_body: Option<&[Stmt]>,
+    ) {
+        self.scoped_statements(body);
+        if let Some(clause) = catch_clause {
+            self.begin_scope();
+            self.declare(&clause.name, BindingKind::Other);
+            self.statements(&clause.body);
+            self.end_scope();
+        }
+        if let Some(statements) = finally_body {
+            self.scoped_statements(statements);
+        }
+    }
+
+    fn visit_test_stmt(&mut self, _keyword: &Token, _name: &Token, body: &[Stmt]) {
+        self.scoped_statements(body);
+    }
+}
+
+/// Runs `rlox lint [paths...]`, printing warnings like errors. With no paths
+/// it lints standard input. Exits with 1 if there were warnings and 65 if
+/// there were syntax errors.
+pub(crate) fn run_command(args: Vec<String>) -> i32 {
+    if let Some(option) = args.iter().find(|arg| arg.starts_with("--")) {
+        eprintln!("Unknown option '{}'.", option);
+        return 64;
+    }
+
+    if args.is_empty() {
+        let mut source = String::new();
+        if let Err(error) = std::io::Read::read_to_string(&mut std::io::stdin(), &mut source) {
+            eprintln!("Could not read standard input: {}.", error);
+            return 74;
+        }
+        return match lint_source(source) {
+            Some(warnings) => report(&warnings),
+            None => 65,
+        };
+    }
+
+    let mut files = Vec::new();
+    for path in &args {
+        collect_lox_files(Path::new(path), &mut files);
+    }
+    let mut code = 0;
+    for file in &files {
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("Could not read '{}': {}.", file.display(), error);
+                code = code.max(74);
+                continue;
+            }
+        };
+        // Diagnostics carry only a line, so name the file when there are
+        // several.
+        if files.len() > 1 {
+            eprintln!("{}:", file.display());
+        }
+        code = code.max(match lint_source(source) {
+            Some(warnings) => report(&warnings),
+            None => 65,
+        });
+    }
+    code
+}
+
+fn report(warnings: &[Warning]) -> i32 {
+    for warning in warnings {
+        Lox::warning_at_token(&warning.token, warning.rule, warning.message.clone());
+    }
+    if warnings.is_empty() {
+        0
+    } else {
+        1
+    }
+}
@@ -1,9 +1,9 @@
-use crate::environment::Environment;
 use crate::lox_function::LoxFunction;
-use crate::runtime_error::Return;
 use crate::{interpreter::Interpreter, token::Token, value::Value};
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::ops::RangeInclusive;
+use std::rc::Rc;
 
 #[derive(Clone, Debug)]
 pub enum LoxCallable {
@@ -15,47 +15,46 @@ impl LoxCallable {
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
-        arguments: Vec<Box<Value>>,
+        paren: &Token,
+        arguments: Vec<Value>,
     ) -> Result<Value, Box<dyn Error>> {
-        match self {
-            LoxCallable::Function(f) => {
-                let environment = Environment::new_enclosing(f.closure.clone());
-                for (i, param) in f.declaration.params.iter().enumerate() {
-                    environment
-                        .borrow_mut()
-                        .define(param.lexeme.clone(), *arguments.get(i).unwrap().clone());
-                }
-
-                match interpreter.execute_block(&f.declaration.body, environment) {
-                    Ok(value) => Ok(value),
-                    Err(e) => match e.downcast::<Return>() {
-                        Ok(value) => return Ok(value.0),
-                        Err(e) => Err(e),
-                    },
-                }?;
-                Ok(Value::Nil)
-            }
-            LoxCallable::NativeFunction(f) => Ok((f.function)(interpreter)),
+        interpreter.enter_call(self, paren, &arguments)?;
+        let result = match self {
+            LoxCallable::Function(f) => f.call(interpreter, arguments),
+            LoxCallable::NativeFunction(f) => (f.function)(interpreter, paren, arguments),
         }
+        .map_err(|e| interpreter.attach_stack_trace(e));
+        interpreter.exit_call(self, result.as_ref().map_err(|e| e.as_ref()));
+        result
     }
 
-    pub fn arity(&self) -> usize {
+    /// The accepted argument counts; variadic natives end at `usize::MAX`.
+    pub fn arity(&self) -> RangeInclusive<usize> {
         match self {
-            LoxCallable::Function(f) => f.declaration.params.len(),
-            LoxCallable::NativeFunction(f) => f.params.len(),
+            LoxCallable::Function(f) => {
+                let params = f.declaration.params.len();
+                params..=params
+            }
+            LoxCallable::NativeFunction(f) => f.arity.clone(),
         }
     }
 }
 
 impl PartialEq for LoxCallable {
     fn eq(&self, other: &Self) -> bool {
-        unimplemented!()
+        match (self, other) {
+            (LoxCallable::Function(a), LoxCallable::Function(b)) => {
+                Rc::ptr_eq(&a.declaration, &b.declaration) && Rc::ptr_eq(&a.closure, &b.closure)
+            }
+            (LoxCallable::NativeFunction(a), LoxCallable::NativeFunction(b)) => a == b,
+            _ => false,
+        }
     }
 }
 
 impl PartialOrd for LoxCallable {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        unimplemented!()
+    fn partial_cmp(&self, _other: &Self) -> Option<std::cmp::Ordering> {
+        None
     }
 }
 
@@ -65,27 +64,31 @@ impl Display for LoxCallable {
             LoxCallable::Function(func) => {
                 write!(f, "<fn {}>", func.declaration.name.lexeme)
             }
-            LoxCallable::NativeFunction(func) => {
+            LoxCallable::NativeFunction(_) => {
                 write!(f, "<native fn>")
             }
         }
     }
 }
 
+pub type NativeFn = fn(&mut Interpreter, &Token, Vec<Value>) -> Result<Value, Box<dyn Error>>;
+
+/// A function implemented in Rust. It is passed the closing paren of the call
+/// to report errors at, and arguments already checked against `arity`.
 #[derive(Debug, Clone)]
 pub struct LoxNativeFunction {
     pub name: String,
-    pub params: Vec<Token>,
-    pub function: fn(&mut Interpreter) -> Value,
+    pub arity: RangeInclusive<usize>,
+    pub function: NativeFn,
 }
 impl PartialEq for LoxNativeFunction {
     fn eq(&self, other: &Self) -> bool {
-        todo!()
+        self.name == other.name
     }
 }
 
 impl PartialOrd for LoxNativeFunction {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        todo!()
+    fn partial_cmp(&self, _other: &Self) -> Option<std::cmp::Ordering> {
+        None
     }
 }
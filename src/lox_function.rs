@@ -1,19 +1,79 @@
 use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::runtime_error::{Return, TailCall};
 use crate::stmt::LoxFunctionNode;
+use crate::value::Value;
 use std::cell::RefCell;
+use std::error::Error;
+use std::path::Path;
 use std::rc::Rc;
 
 #[derive(Clone, Debug)]
 pub struct LoxFunction {
-    pub(crate) declaration: Box<LoxFunctionNode>,
+    pub(crate) declaration: Rc<LoxFunctionNode>,
     pub(crate) closure: Rc<RefCell<Environment>>,
+    /// The file it was declared in.
+    pub(crate) file: Option<Rc<Path>>,
 }
 
 impl LoxFunction {
-    pub fn new(declaration: Box<LoxFunctionNode>, closure: Rc<RefCell<Environment>>) -> Self {
+    pub fn new(
+        declaration: Rc<LoxFunctionNode>,
+        closure: Rc<RefCell<Environment>>,
+        file: Option<Rc<Path>>,
+    ) -> Self {
         Self {
             declaration,
             closure,
+            file,
+        }
+    }
+
+    pub(crate) fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Box<dyn Error>> {
+        let caller_file = interpreter.set_current_file(self.file.clone());
+        let result = self.run(interpreter, arguments);
+        interpreter.set_current_file(caller_file);
+        result
+    }
+
+    fn run(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Box<dyn Error>> {
+        // Tail calls come back here as `TailCall` and are run by the loop, so
+        // neither the native stack nor the call depth grows with them.
+        let mut function = self.clone();
+        let mut arguments = arguments;
+        loop {
+            let environment = Environment::new_enclosing(function.closure.clone());
+            for (param, argument) in function.declaration.params.iter().zip(arguments) {
+                environment
+                    .borrow_mut()
+                    .define(param.lexeme.clone(), argument);
+            }
+
+            let error = match interpreter.execute_block(&function.declaration.body, environment) {
+                Ok(()) => return Ok(Value::Nil),
+                Err(e) => e,
+            };
+            let error = match error.downcast::<Return>() {
+                Ok(value) => return Ok(value.0),
+                Err(e) => e,
+            };
+            match error.downcast::<TailCall>() {
+                Ok(tail_call) => {
+                    interpreter.set_current_file(tail_call.function.file.clone());
+                    interpreter.enter_tail_call(&tail_call.function, &tail_call.arguments);
+                    function = tail_call.function;
+                    arguments = tail_call.arguments;
+                }
+                Err(e) => return Err(e),
+            }
         }
     }
 }
@@ -0,0 +1,38 @@
+use crate::environment::Environment;
+use std::cell::RefCell;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// A loaded Lox file. Its top-level bindings live in `environment`, which
+/// encloses a fresh set of native functions rather than the importer's
+/// globals.
+#[derive(Debug)]
+pub(crate) struct LoxModule {
+    pub(crate) path: PathBuf,
+    pub(crate) environment: Rc<RefCell<Environment>>,
+}
+
+impl LoxModule {
+    pub(crate) fn new(path: PathBuf, environment: Rc<RefCell<Environment>>) -> Self {
+        Self { path, environment }
+    }
+}
+
+impl PartialEq for LoxModule {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl PartialOrd for LoxModule {
+    fn partial_cmp(&self, _other: &Self) -> Option<std::cmp::Ordering> {
+        None
+    }
+}
+
+impl Display for LoxModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module {}>", self.path.display())
+    }
+}
@@ -0,0 +1,474 @@
+use crate::json::Json;
+use crate::lsp_document::{Definition, DefinitionKind, Document};
+use crate::native_functions;
+use crate::scanner::KEYWORDS;
+use crate::token::Token;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: f64 = -32700.0;
+const INVALID_REQUEST: f64 = -32600.0;
+const METHOD_NOT_FOUND: f64 = -32601.0;
+const INVALID_PARAMS: f64 = -32602.0;
+
+/// Runs `rlox lsp`, a language server speaking LSP over standard input and
+/// output. Exits with 0 after `shutdown` and `exit`, and 1 if the client
+/// went away without them.
+pub(crate) fn run_command(args: Vec<String>) -> i32 {
+    // Editors often pass `--stdio`, which is the only transport anyway.
+    if let Some(option) = args.iter().find(|arg| *arg != "--stdio") {
+        eprintln!("Unknown option '{}'.", option);
+        return 64;
+    }
+    let stdin = std::io::stdin();
+    let mut server = Server {
+        input: stdin.lock(),
+        output: std::io::stdout(),
+        documents: HashMap::new(),
+        shut_down: false,
+    };
+    server.run()
+}
+
+struct Server<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    /// Open documents by URI.
+    documents: HashMap<String, Document>,
+    shut_down: bool,
+}
+
+impl<R: BufRead, W: Write> Server<R, W> {
+    fn run(&mut self) -> i32 {
+        loop {
+            let message = match self.read_message() {
+                Ok(Some(message)) => message,
+                Ok(None) => return 1,
+                Err(error) => {
+                    self.send_error(&Json::Null, PARSE_ERROR, &error);
+                    continue;
+                }
+            };
+            let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+            if method == "exit" {
+                return if self.shut_down { 0 } else { 1 };
+            }
+            let params = message.get("params").cloned().unwrap_or(Json::Null);
+            match message.get("id") {
+                Some(id) => {
+                    let id = id.clone();
+                    match self.request(method, &params) {
+                        Ok(result) => self.send(object(vec![
+                            ("jsonrpc", string("2.0")),
+                            ("id", id),
+                            ("result", result),
+                        ])),
+                        Err((code, message)) => self.send_error(&id, code, &message),
+                    }
+                }
+                None => self.notification(method, &params),
+            }
+        }
+    }
+
+    fn read_message(&mut self) -> Result<Option<Json>, String> {
+        read_message(&mut self.input)
+    }
+
+    fn send(&mut self, message: Json) {
+        write_message(&mut self.output, &message);
+    }
+
+    fn send_error(&mut self, id: &Json, code: f64, message: &str) {
+        self.send(object(vec![
+            ("jsonrpc", string("2.0")),
+            ("id", id.clone()),
+            (
+                "error",
+                object(vec![
+                    ("code", Json::Number(code)),
+                    ("message", string(message)),
+                ]),
+            ),
+        ]));
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (f64, String)> {
+        if self.shut_down {
+            return Err((INVALID_REQUEST, "The server is shutting down.".to_string()));
+        }
+        match method {
+            "initialize" => Ok(object(vec![
+                (
+                    "capabilities",
+                    object(vec![
+                        ("textDocumentSync", Json::Number(1.0)),
+                        ("definitionProvider", Json::Bool(true)),
+                        ("referencesProvider", Json::Bool(true)),
+                        ("hoverProvider", Json::Bool(true)),
+                        ("documentSymbolProvider", Json::Bool(true)),
+                        ("completionProvider", object(vec![])),
+                    ]),
+                ),
+                ("serverInfo", object(vec![("name", string("rlox"))])),
+            ])),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => {
+                let (uri, document, line, column) = self.position(params)?;
+                Ok(document
+                    .definition_at(line, column)
+                    .map_or(Json::Null, |id| {
+                        location(uri, document, &document.definitions[id].name)
+                    }))
+            }
+            "textDocument/references" => {
+                let (uri, document, line, column) = self.position(params)?;
+                let Some(id) = document.definition_at(line, column) else {
+                    return Ok(Json::Array(Vec::new()));
+                };
+                let include_declaration = params
+                    .get("context")
+                    .and_then(|context| context.get("includeDeclaration"))
+                    == Some(&Json::Bool(true));
+                let mut tokens = document.references(id);
+                if include_declaration {
+                    tokens.insert(0, &document.definitions[id].name);
+                }
+                Ok(Json::Array(
+                    tokens
+                        .into_iter()
+                        .map(|token| location(uri, document, token))
+                        .collect(),
+                ))
+            }
+            "textDocument/hover" => {
+                let (_, document, line, column) = self.position(params)?;
+                Ok(hover(document, line, column))
+            }
+            "textDocument/documentSymbol" => {
+                let document = self.document(params)?.1;
+                Ok(Json::Array(document_symbols(document, None)))
+            }
+            "textDocument/completion" => {
+                let (_, document, line, column) = self.position(params)?;
+                Ok(Json::Array(completions(document, line, column)))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{}'.", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) {
+        let text_document = params.get("textDocument");
+        let Some(uri) = text_document
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .map(str::to_string)
+        else {
+            return;
+        };
+        let text = match method {
+            "textDocument/didOpen" => text_document.and_then(|document| document.get("text")),
+            // Only whole-document sync is offered, so the last change has
+            // the full text.
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .and_then(|changes| match changes {
+                    Json::Array(changes) => changes.last(),
+                    _ => None,
+                })
+                .and_then(|change| change.get("text")),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.publish_diagnostics(&uri, Vec::new());
+                return;
+            }
+            _ => return,
+        };
+        let Some(text) = text.and_then(Json::as_str) else {
+            return;
+        };
+        let document = Document::new(text.to_string());
+        let diagnostics = diagnostics(&document);
+        self.documents.insert(uri.clone(), document);
+        self.publish_diagnostics(&uri, diagnostics);
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Vec<Json>) {
+        self.send(object(vec![
+            ("jsonrpc", string("2.0")),
+            ("method", string("textDocument/publishDiagnostics")),
+            (
+                "params",
+                object(vec![
+                    ("uri", string(uri)),
+                    ("diagnostics", Json::Array(diagnostics)),
+                ]),
+            ),
+        ]));
+    }
+
+    fn document<'a>(&'a self, params: &'a Json) -> Result<(&'a str, &'a Document), (f64, String)> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .ok_or((INVALID_PARAMS, "Missing textDocument.uri.".to_string()))?;
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Document '{}' is not open.", uri)))?;
+        Ok((uri, document))
+    }
+
+    /// The document and 1-based line and column of a text document position.
+    fn position<'a>(
+        &'a self,
+        params: &'a Json,
+    ) -> Result<(&'a str, &'a Document, i32, i32), (f64, String)> {
+        let (uri, document) = self.document(params)?;
+        let position = params.get("position");
+        let coordinate = |name| {
+            position
+                .and_then(|position| position.get(name))
+                .and_then(Json::as_f64)
+                .filter(|n| *n >= 0.0)
+                .map(|n| n as usize)
+                .ok_or((INVALID_PARAMS, format!("Missing position.{}.", name)))
+        };
+        let (line, column) =
+            document.source_position(coordinate("line")?, coordinate("character")?);
+        Ok((uri, document, line, column))
+    }
+}
+
+/// Reads one `Content-Length` framed message, or `None` at the end of the
+/// input. The debug adapter protocol frames its messages the same way.
+pub(crate) fn read_message(input: &mut impl BufRead) -> Result<Option<Json>, String> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        match input.read_line(&mut header) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {}
+            Err(error) => return Err(error.to_string()),
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(length) = length else {
+        return Err("Missing Content-Length header.".to_string());
+    };
+    let mut body = vec![0; length];
+    if std::io::Read::read_exact(input, &mut body).is_err() {
+        return Ok(None);
+    }
+    let body = String::from_utf8(body).map_err(|_| "Message is not UTF-8.".to_string())?;
+    Json::parse(&body)
+        .map(Some)
+        .map_err(|error| format!("Invalid JSON at {}", error))
+}
+
+pub(crate) fn write_message(output: &mut impl Write, message: &Json) {
+    let body = message.stringify(None);
+    // The client reading our output is all that matters; if it is gone
+    // there is no one left to tell.
+    let _ = write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = output.flush();
+}
+
+pub(crate) fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+        members
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+pub(crate) fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+fn range(((start_line, start), (end_line, end)): ((usize, usize), (usize, usize))) -> Json {
+    let position = |line: usize, character: usize| {
+        object(vec![
+            ("line", Json::Number(line as f64)),
+            ("character", Json::Number(character as f64)),
+        ])
+    };
+    object(vec![
+        ("start", position(start_line, start)),
+        ("end", position(end_line, end)),
+    ])
+}
+
+fn location(uri: &str, document: &Document, token: &Token) -> Json {
+    object(vec![
+        ("uri", string(uri)),
+        ("range", range(document.lsp_range(token))),
+    ])
+}
+
+/// Syntax errors, or lint warnings if there are none.
+fn diagnostics(document: &Document) -> Vec<Json> {
+    let errors = document.errors.iter().map(|error| {
+        let range = match &error.token {
+            Some(token) => document.lsp_range(token),
+            None => document.lsp_line_range(error.line),
+        };
+        object(vec![
+            ("range", self::range(range)),
+            ("severity", Json::Number(1.0)),
+            ("source", string("rlox")),
+            ("message", string(&error.message)),
+        ])
+    });
+    let warnings = document.warnings.iter().map(|warning| {
+        object(vec![
+            ("range", range(document.lsp_range(&warning.token))),
+            ("severity", Json::Number(2.0)),
+            ("code", string(warning.rule)),
+            ("source", string("rlox")),
+            ("message", string(&warning.message)),
+        ])
+    });
+    errors.chain(warnings).collect()
+}
+
+/// How a definition reads in a hover or completion.
+fn describe(definition: &Definition) -> String {
+    let name = &definition.name.lexeme;
+    match definition.kind {
+        DefinitionKind::Function => definition.signature.clone().unwrap_or_default(),
+        DefinitionKind::Variable => format!("var {}", name),
+        DefinitionKind::Parameter => format!("(parameter) {}", name),
+        DefinitionKind::Import => format!("(import) {}", name),
+        DefinitionKind::Catch => format!("(catch) {}", name),
+    }
+}
+
+/// The completion kind and description of a native global. Most are
+/// functions; the rest are constants like `PI`.
+fn describe_native(name: &str, value: &Value) -> (f64, String) {
+    match value {
+        Value::Callable(_) => (3.0, format!("(native function) {}", name)),
+        _ => (21.0, format!("(native constant) {}", name)),
+    }
+}
+
+fn hover(document: &Document, line: i32, column: i32) -> Json {
+    let Some(token) = document.name_token_at(line, column) else {
+        return Json::Null;
+    };
+    let text = match document.definition_at(line, column) {
+        Some(id) => describe(&document.definitions[id]),
+        None => match native_functions::globals().get(&token.lexeme) {
+            Some(value) => describe_native(&token.lexeme, value).1,
+            None => return Json::Null,
+        },
+    };
+    object(vec![
+        (
+            "contents",
+            object(vec![
+                ("kind", string("markdown")),
+                ("value", string(&format!("```lox\n{}\n```", text))),
+            ]),
+        ),
+        ("range", range(document.lsp_range(token))),
+    ])
+}
+
+/// The functions, variables and imports declared directly in `parent` (the
+/// top level if `None`), with what each function declares as its children.
+fn document_symbols(document: &Document, parent: Option<usize>) -> Vec<Json> {
+    document
+        .definitions
+        .iter()
+        .enumerate()
+        .filter(|(_, definition)| {
+            definition.parent == parent
+                && !matches!(
+                    definition.kind,
+                    DefinitionKind::Parameter | DefinitionKind::Catch
+                )
+        })
+        .map(|(id, definition)| {
+            let selection = document.lsp_range(&definition.name);
+            let (kind, extent) = match (definition.kind, &definition.extent) {
+                (DefinitionKind::Function, Some(extent)) => {
+                    let end = document.token(*extent.end());
+                    let range = (
+                        document.lsp_range(document.token(*extent.start())).0,
+                        document.lsp_range(end).1,
+                    );
+                    (12.0, range)
+                }
+                (DefinitionKind::Import, _) => (2.0, selection),
+                _ => (13.0, selection),
+            };
+            let mut members = vec![
+                ("name", string(&definition.name.lexeme)),
+                ("detail", string(&describe(definition))),
+                ("kind", Json::Number(kind)),
+                ("range", range(extent)),
+                ("selectionRange", range(selection)),
+            ];
+            if definition.kind == DefinitionKind::Function {
+                members.push((
+                    "children",
+                    Json::Array(document_symbols(document, Some(id))),
+                ));
+            }
+            object(members)
+        })
+        .collect()
+}
+
+/// The names in scope at the cursor, then the natives, then the keywords.
+fn completions(document: &Document, line: i32, column: i32) -> Vec<Json> {
+    let mut items: Vec<(String, f64, String)> = Vec::new();
+    // Later definitions are the inner ones, which shadow the rest.
+    for definition in document.visible_at(line, column).into_iter().rev() {
+        let kind = match definition.kind {
+            DefinitionKind::Function => 3.0,
+            DefinitionKind::Import => 9.0,
+            _ => 6.0,
+        };
+        items.push((definition.name.lexeme.clone(), kind, describe(definition)));
+    }
+    let mut natives: Vec<(String, Value)> = native_functions::globals().into_iter().collect();
+    natives.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (name, value) in natives {
+        let (kind, detail) = describe_native(&name, &value);
+        items.push((name, kind, detail));
+    }
+    let mut keywords: Vec<&String> = KEYWORDS.keys().collect();
+    keywords.sort();
+    for keyword in keywords {
+        items.push((keyword.clone(), 14.0, "keyword".to_string()));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    items
+        .into_iter()
+        .filter(|(label, _, _)| seen.insert(label.clone()))
+        .map(|(label, kind, detail)| {
+            object(vec![
+                ("label", string(&label)),
+                ("kind", Json::Number(kind)),
+                ("detail", string(&detail)),
+            ])
+        })
+        .collect()
+}
@@ -0,0 +1,535 @@
+use crate::expr::{self, Expr};
+use crate::linter::{self, Warning};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::stmt::{self, CatchClause, LoxFunctionNode, Stmt};
+use crate::token::{Literal, Token};
+use crate::token_type::TokenType::{IDENTIFIER, LEFT_BRACE, RIGHT_BRACE};
+use crate::{Lox, SyntaxError};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum DefinitionKind {
+    Variable,
+    Function,
+    Parameter,
+    Import,
+    Catch,
+}
+
+/// A name declared in a document.
+#[derive(Debug)]
+pub(crate) struct Definition {
+    pub(crate) name: Token,
+    pub(crate) kind: DefinitionKind,
+    /// `fun name(a, b)`, for functions.
+    pub(crate) signature: Option<String>,
+    /// The function whose body declares it, as an index into `definitions`.
+    pub(crate) parent: Option<usize>,
+    /// The tokens a function declaration spans, from `fun` to its `}`.
+    pub(crate) extent: Option<RangeInclusive<usize>>,
+    /// Where the name can be used, as token indices. A cursor just before
+    /// token `i` is at index `i`.
+    visible: RangeInclusive<usize>,
+}
+
+/// An open file as the language server sees it: its tokens, syntax tree and
+/// what every name in it refers to.
+pub(crate) struct Document {
+    lines: Vec<String>,
+    tokens: Vec<Token>,
+    pub(crate) errors: Vec<SyntaxError>,
+    pub(crate) warnings: Vec<Warning>,
+    pub(crate) definitions: Vec<Definition>,
+    /// Uses of a name, by token index, with the definition they refer to.
+    references: Vec<(usize, usize)>,
+}
+
+impl Document {
+    pub(crate) fn new(text: String) -> Self {
+        let lines = text.split('\n').map(str::to_string).collect();
+        let ((tokens, statements), errors) = Lox::collect_errors(|| {
+            let tokens = Scanner::with_comments(text).scan_tokens();
+            let statements = Parser::new(tokens.clone()).parse();
+            (tokens, statements)
+        });
+        // A broken file would only add noise to the syntax errors.
+        let warnings = if errors.is_empty() {
+            linter::lint(&tokens, &statements)
+        } else {
+            Vec::new()
+        };
+
+        let mut index = Index::new(&tokens);
+        index.declare_top_level(&statements);
+        index.statements(&statements);
+        Document {
+            lines,
+            errors,
+            warnings,
+            definitions: index.definitions,
+            references: index.references,
+            tokens,
+        }
+    }
+
+    pub(crate) fn token(&self, index: usize) -> &Token {
+        &self.tokens[index]
+    }
+
+    /// The index of a token taken from the syntax tree.
+    fn token_index(&self, token: &Token) -> Option<usize> {
+        self.tokens
+            .binary_search_by_key(&(token.line, token.column), |t| (t.line, t.column))
+            .ok()
+    }
+
+    /// The name under the cursor at 1-based `line` and `column`. A cursor
+    /// just after a name is on it.
+    fn name_at(&self, line: i32, column: i32) -> Option<usize> {
+        self.tokens.iter().position(|token| {
+            let end = token.column + token.lexeme.chars().count() as i32;
+            token.token_type == IDENTIFIER
+                && token.line == line
+                && (token.column..=end).contains(&column)
+        })
+    }
+
+    /// The definition of the name under the cursor, which may be the
+    /// declaration itself.
+    pub(crate) fn definition_at(&self, line: i32, column: i32) -> Option<usize> {
+        let index = self.name_at(line, column)?;
+        self.references
+            .iter()
+            .find(|(token, _)| *token == index)
+            .map(|(_, definition)| *definition)
+            .or_else(|| {
+                self.definitions
+                    .iter()
+                    .position(|d| self.token_index(&d.name) == Some(index))
+            })
+    }
+
+    /// The identifier under the cursor, whatever it refers to.
+    pub(crate) fn name_token_at(&self, line: i32, column: i32) -> Option<&Token> {
+        self.name_at(line, column).map(|index| &self.tokens[index])
+    }
+
+    /// The uses of a definition in source order, not counting the
+    /// declaration.
+    pub(crate) fn references(&self, definition: usize) -> Vec<&Token> {
+        let mut uses: Vec<usize> = self
+            .references
+            .iter()
+            .filter(|(_, d)| *d == definition)
+            .map(|(token, _)| *token)
+            .collect();
+        uses.sort();
+        uses.into_iter().map(|token| &self.tokens[token]).collect()
+    }
+
+    /// The definitions in scope at the cursor.
+    pub(crate) fn visible_at(&self, line: i32, column: i32) -> Vec<&Definition> {
+        let cursor = self
+            .tokens
+            .partition_point(|token| (token.line, token.column) < (line, column));
+        self.definitions
+            .iter()
+            .filter(|definition| definition.visible.contains(&cursor))
+            .collect()
+    }
+
+    /// Converts a 1-based line and character column to a 0-based LSP
+    /// position, which counts UTF-16 code units.
+    pub(crate) fn lsp_position(&self, line: i32, column: i32) -> (usize, usize) {
+        let line = (line - 1).max(0) as usize;
+        let character = self.lines.get(line).map_or(0, |text| {
+            text.chars()
+                .take((column - 1).max(0) as usize)
+                .map(char::len_utf16)
+                .sum()
+        });
+        (line, character)
+    }
+
+    /// The inverse of `lsp_position`.
+    pub(crate) fn source_position(&self, line: usize, character: usize) -> (i32, i32) {
+        let mut units = 0;
+        let mut column = 1;
+        if let Some(text) = self.lines.get(line) {
+            for c in text.chars() {
+                if units >= character {
+                    break;
+                }
+                units += c.len_utf16();
+                column += 1;
+            }
+        }
+        (line as i32 + 1, column)
+    }
+
+    /// The 0-based LSP range of a token on its first line.
+    pub(crate) fn lsp_range(&self, token: &Token) -> ((usize, usize), (usize, usize)) {
+        let length = token
+            .lexeme
+            .split('\n')
+            .next()
+            .unwrap_or("")
+            .chars()
+            .count();
+        (
+            self.lsp_position(token.line, token.column),
+            self.lsp_position(token.line, token.column + length as i32),
+        )
+    }
+
+    /// The 0-based LSP range of a whole line, for errors without a token.
+    pub(crate) fn lsp_line_range(&self, line: i32) -> ((usize, usize), (usize, usize)) {
+        let length = self
+            .lines
+            .get((line - 1).max(0) as usize)
+            .map_or(0, |text| text.chars().count());
+        (
+            self.lsp_position(line, 1),
+            self.lsp_position(line, length as i32 + 1),
+        )
+    }
+}
+
+/// Builds the definitions and references of a document, resolving names the
+/// way the interpreter's environments will.
+struct Index<'a> {
+    tokens: &'a [Token],
+    /// For each token, the index of the `}` closing the block it is in, or
+    /// the end of the file.
+    scope_ends: Vec<usize>,
+    /// For each `{`, the index of its `}`.
+    closing: HashMap<usize, usize>,
+    scopes: Vec<HashMap<String, usize>>,
+    function: Option<usize>,
+    definitions: Vec<Definition>,
+    references: Vec<(usize, usize)>,
+}
+
+impl<'a> Index<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        let mut closing = HashMap::new();
+        let mut open = Vec::new();
+        let mut scope_ends = vec![tokens.len(); tokens.len()];
+        for (index, token) in tokens.iter().enumerate() {
+            match token.token_type {
+                LEFT_BRACE => open.push(index),
+                RIGHT_BRACE => {
+                    if let Some(start) = open.pop() {
+                        closing.insert(start, index);
+                    }
+                }
+                _ => {}
+            }
+        }
+        open.clear();
+        for (index, token) in tokens.iter().enumerate() {
+            if let Some(start) = open.last() {
+                scope_ends[index] = closing.get(start).copied().unwrap_or(tokens.len());
+            }
+            match token.token_type {
+                LEFT_BRACE => open.push(index),
+                RIGHT_BRACE => {
+                    open.pop();
+                }
+                _ => {}
+            }
+        }
+
+        Index {
+            tokens,
+            scope_ends,
+            closing,
+            scopes: vec![HashMap::new()],
+            function: None,
+            definitions: Vec::new(),
+            references: Vec::new(),
+        }
+    }
+
+    fn token_index(&self, token: &Token) -> usize {
+        self.tokens
+            .binary_search_by_key(&(token.line, token.column), |t| (t.line, t.column))
+            .unwrap_or(0)
+    }
+
+    /// The `{` after a token and its `}`, for parameters and catch variables,
+    /// whose scope is the block that follows them.
+    fn next_block(&self, index: usize) -> RangeInclusive<usize> {
+        let open = (index..self.tokens.len())
+            .find(|&i| self.tokens[i].token_type == LEFT_BRACE)
+            .unwrap_or(self.tokens.len());
+        let close = self
+            .closing
+            .get(&open)
+            .copied()
+            .unwrap_or(self.tokens.len());
+        open..=close
+    }
+
+    /// Top-level names are looked up when the code runs, so functions can
+    /// use the ones declared after them.
+    fn declare_top_level(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            match statement {
+                Stmt::Var { name, .. } => {
+                    self.define(name, DefinitionKind::Variable, None);
+                }
+                Stmt::Function { function } => {
+                    self.define_function(function);
+                }
+                Stmt::Import { alias, .. } => {
+                    self.define(alias, DefinitionKind::Import, None);
+                }
+                Stmt::FromImport { names, .. } => {
+                    for name in names {
+                        self.define(name, DefinitionKind::Import, None);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (id, definition) in self.definitions.iter_mut().enumerate() {
+            definition.visible = 0..=self.tokens.len();
+            self.scopes[0]
+                .entry(definition.name.lexeme.clone())
+                .or_insert(id);
+        }
+    }
+
+    /// Adds a definition, or finds the one `declare_top_level` added, and
+    /// puts it in the current scope.
+    fn define(&mut self, name: &Token, kind: DefinitionKind, signature: Option<String>) -> usize {
+        let index = self.token_index(name);
+        let id = match self
+            .definitions
+            .iter()
+            .position(|d| (d.name.line, d.name.column) == (name.line, name.column))
+        {
+            Some(id) => id,
+            None => {
+                let visible = match kind {
+                    DefinitionKind::Parameter | DefinitionKind::Catch => {
+                        let block = self.next_block(index);
+                        *block.start()..=*block.end()
+                    }
+                    _ => index + 1..=self.scope_ends[index],
+                };
+                self.definitions.push(Definition {
+                    name: name.clone(),
+                    kind,
+                    signature,
+                    parent: self.function,
+                    extent: None,
+                    visible,
+                });
+                self.definitions.len() - 1
+            }
+        };
+        if self.scopes.len() > 1 {
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .insert(name.lexeme.clone(), id);
+        }
+        id
+    }
+
+    fn define_function(&mut self, function: &LoxFunctionNode) -> usize {
+        let params: Vec<&str> = function.params.iter().map(|p| p.lexeme.as_str()).collect();
+        let signature = format!("fun {}({})", function.name.lexeme, params.join(", "));
+        let id = self.define(&function.name, DefinitionKind::Function, Some(signature));
+        let index = self.token_index(&function.name);
+        let body = self.next_block(index);
+        self.definitions[id].extent = Some(index.saturating_sub(1)..=*body.end());
+        id
+    }
+
+    fn resolve(&mut self, name: &Token) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).copied());
+        if let Some(id) = found {
+            let index = self.token_index(name);
+            self.references.push((index, id));
+        }
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn scoped_statements(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        self.statements(statements);
+        self.scopes.pop();
+    }
+}
+
+impl expr::Visitor<()> for Index<'_> {
+    fn visit_binary_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        left.accept(self);
+        right.accept(self);
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) {
+        expression.accept(self);
+    }
+
+    fn visit_literal_expr(&mut self, _value: &Literal) {}
+
+    fn visit_unary_expr(&mut self, _operator: &Token, right: &Expr) {
+        right.accept(self);
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) {
+        callee.accept(self);
+        for argument in arguments {
+            argument.accept(self);
+        }
+    }
+
+    fn visit_variable_expr(&mut self, name: &Token) {
+        self.resolve(name);
+    }
+
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) {
+        value.accept(self);
+        self.resolve(name);
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        left.accept(self);
+        right.accept(self);
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, _name: &Token) {
+        object.accept(self);
+    }
+
+    fn visit_list_expr(&mut self, elements: &[Expr]) {
+        for element in elements {
+            element.accept(self);
+        }
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, _bracket: &Token, index: &Expr) {
+        object.accept(self);
+        index.accept(self);
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) {
+        object.accept(self);
+        index.accept(self);
+        value.accept(self);
+    }
+}
+
+impl stmt::Visitor<()> for Index<'_> {
+    fn visit_expression_stmt(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn visit_print_stmt(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) {
+        if let Some(initializer) = initializer {
+            initializer.accept(self);
+        }
+        self.define(name, DefinitionKind::Variable, None);
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) {
+        self.scoped_statements(statements);
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        _keyword: &Token,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) {
+        condition.accept(self);
+        then_branch.accept(self);
+        if let Some(else_branch) = else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, _keyword: &Token, condition: &Expr, body: &Stmt) {
+        condition.accept(self);
+        body.accept(self);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: Rc<LoxFunctionNode>) {
+        let id = self.define_function(&stmt);
+        let enclosing = self.function.replace(id);
+        self.scopes.push(HashMap::new());
+        for param in &stmt.params {
+            self.define(param, DefinitionKind::Parameter, None);
+        }
+        self.statements(&stmt.body);
+        self.scopes.pop();
+        self.function = enclosing;
+    }
+
+    fn visit_throw_stmt(&mut self, _keyword: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    fn visit_import_stmt(&mut self, _keyword: &Token, _path: &Token, alias: &Token) {
+        self.define(alias, DefinitionKind::Import, None);
+    }
+
+    fn visit_from_import_stmt(&mut self, _keyword: &Token, _path: &Token, names: &[Token]) {
+        for name in names {
+            self.define(name, DefinitionKind::Import, None);
+        }
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        body: &[Stmt],
+        catch_clause: Option<&CatchClause>,
+        finally_body: Option<&[Stmt]>,
+    ) {
+        self.scoped_statements(body);
+        if let Some(clause) = catch_clause {
+            self.scopes.push(HashMap::new());
+            self.define(&clause.name, DefinitionKind::Catch, None);
+            self.statements(&clause.body);
+            self.scopes.pop();
+        }
+        if let Some(statements) = finally_body {
+            self.scoped_statements(statements);
+        }
+    }
+
+    fn visit_test_stmt(&mut self, _keyword: &Token, _name: &Token, body: &[Stmt]) {
+        self.scoped_statements(body);
+    }
+}
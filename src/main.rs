@@ -1,55 +1,305 @@
-// mod ast_printer;
+mod ast_json;
+mod ast_printer;
+mod clock;
+mod coverage;
+mod dap;
+mod debugger;
 mod environment;
+mod execution_hook;
 mod expr;
+mod formatter;
 mod interpreter;
+mod json;
+mod linter;
 mod lox_callable;
 mod lox_function;
+mod lox_map;
+mod lox_module;
+mod lsp;
+mod lsp_document;
+mod native_assert;
 mod native_functions;
+mod native_io;
+mod native_json;
+mod native_math;
+mod native_random;
+mod native_string;
+mod native_system;
+mod native_time;
 mod parser;
+mod profiler;
+mod repl;
+mod rng;
 mod runtime_error;
 mod scanner;
 mod stmt;
+mod test_runner;
 mod token;
 mod token_type;
+mod tracer;
 mod value;
 
+use crate::ast_json::ToJson;
+use crate::ast_printer::AstPrinter;
+use crate::clock::FixedClock;
+use crate::coverage::Coverage;
+use crate::debugger::Debugger;
 use crate::interpreter::Interpreter;
+use crate::profiler::Profiler;
+use crate::tracer::Tracer;
 use once_cell::unsync::Lazy;
 use scanner::Scanner;
 use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
 use token::Token;
 
 struct Lox {
     had_error: bool,
     had_runtime_error: bool,
+    /// Set when the script calls `exit`.
+    exit_code: Option<i32>,
+    /// Syntax errors go here instead of to stderr while set.
+    collected_errors: Option<Vec<SyntaxError>>,
     interpreter: Interpreter,
 }
 
-static mut LOX: Lazy<Lox> = Lazy::new(|| Lox::new());
+static mut LOX: Lazy<Lox> = Lazy::new(Lox::new);
+
+/// A syntax error collected by `Lox::collect_errors`. Scanner errors have no
+/// token, only a line.
+#[derive(Debug, Clone)]
+pub(crate) struct SyntaxError {
+    pub(crate) line: i32,
+    pub(crate) token: Option<Token>,
+    pub(crate) message: String,
+}
+
+/// What to do with the script.
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Run,
+    /// Print the syntax tree instead of running it.
+    DumpAst,
+    /// Print the tokens as JSON.
+    Tokens,
+    /// Print the syntax tree as JSON.
+    AstJson,
+    /// Only report syntax errors.
+    Check,
+}
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    match args.len() {
-        1 => Lox::run_prompt().unwrap(),
-        2 => Lox::run_file(args[1].clone()).unwrap(),
-        _ => {
-            println!("Usage: rlox [script]");
-            std::process::exit(64);
+    let mut args = std::env::args().skip(1).peekable();
+    let subcommand: Option<fn(Vec<String>) -> i32> = match args.peek().map(String::as_str) {
+        Some("fmt") => Some(formatter::run_command),
+        Some("lint") => Some(linter::run_command),
+        Some("lsp") => Some(lsp::run_command),
+        Some("dap") => Some(dap::run_command),
+        Some("test") => Some(test_runner::run_command),
+        _ => None,
+    };
+    if let Some(run_command) = subcommand {
+        args.next();
+        std::process::exit(run_command(args.collect()));
+    }
+
+    let mut script = None;
+    let mut mode = Mode::Run;
+    let mut max_call_depth = interpreter::DEFAULT_MAX_CALL_DEPTH;
+    let mut sandboxed = false;
+    let mut seed = None;
+    let mut fixed_time = None;
+    let mut debug = false;
+    let mut trace = false;
+    let mut trace_file = None;
+    let mut profile = None;
+    let mut coverage = None;
+    let mut script_args = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-call-depth" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => max_call_depth = n,
+                None => usage(),
+            },
+            "--sandbox" => sandboxed = true,
+            "--dump-ast" => mode = Mode::DumpAst,
+            "--tokens" => mode = Mode::Tokens,
+            "--ast-json" => mode = Mode::AstJson,
+            "--check" => mode = Mode::Check,
+            "--debug" => debug = true,
+            "--trace" => trace = true,
+            "--profile" => profile = Some("profile.folded".to_string()),
+            "--profile-folded" => match args.next() {
+                Some(path) => profile = Some(path),
+                None => usage(),
+            },
+            "--coverage" => coverage = Some("lcov.info".to_string()),
+            "--coverage-file" => match args.next() {
+                Some(path) => coverage = Some(path),
+                None => usage(),
+            },
+            "--trace-file" => match args.next() {
+                Some(path) => {
+                    trace = true;
+                    trace_file = Some(path);
+                }
+                None => usage(),
+            },
+            "--seed" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => seed = Some(n),
+                None => usage(),
+            },
+            "--fixed-time" => match args
+                .next()
+                .and_then(|n| n.parse::<f64>().ok())
+                .and_then(|n| Duration::try_from_secs_f64(n).ok())
+            {
+                Some(time) => fixed_time = Some(time),
+                None => usage(),
+            },
+            // Everything after the script belongs to the script.
+            _ if !arg.starts_with("--") => {
+                script = Some(arg);
+                script_args.extend(&mut args);
+            }
+            _ => usage(),
+        }
+    }
+
+    // Deep Lox recursion needs more native stack than the main thread has.
+    let spawned = interpreter::spawn_with_stack(max_call_depth, move || {
+        #[allow(static_mut_refs)]
+        unsafe {
+            LOX.interpreter.set_max_call_depth(max_call_depth);
+            LOX.interpreter.set_sandboxed(sandboxed);
+            LOX.interpreter.set_args(script_args);
+            if let Some(seed) = seed {
+                LOX.interpreter.rng().seed(seed);
+            }
+            if let Some(time) = fixed_time {
+                LOX.interpreter.set_clock(Box::new(FixedClock::new(time)));
+            }
+            if trace {
+                let output: Box<dyn std::io::Write> = match &trace_file {
+                    Some(path) => match std::fs::File::create(path) {
+                        Ok(file) => Box::new(std::io::LineWriter::new(file)),
+                        Err(error) => {
+                            eprintln!("Could not write '{}': {}.", path, error);
+                            std::process::exit(74);
+                        }
+                    },
+                    None => Box::new(std::io::stderr()),
+                };
+                LOX.interpreter.add_hook(Box::new(Tracer::new(output)));
+            }
+            if let Some(path) = &profile {
+                LOX.interpreter
+                    .add_hook(Box::new(Profiler::new(path.into())));
+            }
+            if let Some(path) = &coverage {
+                LOX.interpreter
+                    .add_hook(Box::new(Coverage::new(path.into())));
+            }
+            if let (true, Some(path)) = (debug, &script) {
+                // A script that cannot be read is reported by `run_file`.
+                if let Ok(source) = std::fs::read_to_string(path) {
+                    let debugger = Debugger::new(path.into(), &source);
+                    LOX.interpreter.add_hook(Box::new(debugger));
+                }
+            }
+        }
+        match (mode, script) {
+            (Mode::Run, Some(path)) => Lox::run_file(path),
+            (Mode::Run, None) if debug || profile.is_some() || coverage.is_some() => usage(),
+            (Mode::Run, None) => Lox::run_prompt(),
+            (_, None) => usage(),
+            (mode, Some(path)) => Lox::inspect(mode, path),
         }
+    });
+    let interpreter_thread = match spawned {
+        Ok(thread) => thread,
+        Err(error) => {
+            eprintln!(
+                "Could not allocate the stack for a call depth of {}: {}.",
+                max_call_depth, error
+            );
+            std::process::exit(70);
+        }
+    };
+    match interpreter_thread.join() {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => {
+            eprintln!("{}.", error);
+            std::process::exit(74);
+        }
+        // The thread has reported its panic already.
+        Err(_) => std::process::exit(70),
     }
 }
 
+fn usage() -> ! {
+    println!(
+        "\
+Usage: rlox [options] [script [args...]]
+       rlox fmt [--check] [paths...]
+       rlox lint [paths...]
+       rlox lsp
+       rlox dap
+       rlox test [paths...]
+
+Options:
+  --max-call-depth n  fail with \"Stack overflow.\" beyond n nested calls
+  --sandbox           refuse file, stream and environment access
+  --seed n            seed the random natives
+  --fixed-time secs   freeze the clock at secs since the epoch
+  --dump-ast          print the script's syntax tree instead of running it
+  --tokens            print the script's tokens as JSON instead of running it
+  --ast-json          print the script's syntax tree as JSON instead of running it
+  --check             only check the script for syntax errors
+  --debug             run the script under a line-oriented debugger
+  --trace             log each statement and call to stderr as it runs
+  --trace-file path   log them to a file instead
+  --profile           report where the script spends its time, and write its
+                      call stacks to profile.folded for flame graph tools
+  --profile-folded path
+                      profile, writing the call stacks to path instead
+  --coverage          report the lines and branches the script ran, and write
+                      them to lcov.info
+  --coverage-file path
+                      record coverage, writing it to path instead"
+    );
+    std::process::exit(64);
+}
+
 impl Lox {
     pub(crate) fn new() -> Self {
         Lox {
             had_error: false,
             had_runtime_error: false,
+            exit_code: None,
+            collected_errors: None,
             interpreter: Interpreter::new(),
         }
     }
     pub(crate) fn run_file(path: String) -> Result<(), std::io::Error> {
-        let source = std::fs::read_to_string(path)?;
+        let source = Self::read_script(&path)?;
+        #[allow(static_mut_refs)]
+        unsafe {
+            LOX.interpreter
+                .set_current_file(Some(Path::new(&path).into()));
+        }
         Self::run(source);
+        #[allow(static_mut_refs)]
+        unsafe {
+            if !LOX.had_error {
+                LOX.interpreter.finish_hooks();
+            }
+        }
+        if let Some(code) = unsafe { LOX.exit_code } {
+            std::process::exit(code);
+        }
         if unsafe { LOX.had_error } {
             std::process::exit(65);
         }
@@ -59,19 +309,52 @@ impl Lox {
         Ok(())
     }
 
-    pub(crate) fn run_prompt() -> Result<(), std::io::Error> {
-        loop {
-            print!("> ");
-            std::io::stdout().flush()?;
-            let mut line = String::new();
-            std::io::stdin().read_line(&mut line)?;
-            Self::run(line);
-            unsafe {
-                LOX.had_error = false;
-            }
+    /// Handles the modes that look at a script without running it. Syntax
+    /// errors exit with 65 like they do for a run.
+    /// The source of the script at `path`, with an error that names it.
+    fn read_script(path: &str) -> Result<String, std::io::Error> {
+        std::fs::read_to_string(path).map_err(|error| {
+            std::io::Error::new(
+                error.kind(),
+                format!("Could not read '{}': {}", path, error),
+            )
+        })
+    }
+
+    /// Prints the tokens or tree of a script. These are meant to be piped
+    /// into other tools, so a reader that stops early ends the output
+    /// quietly.
+    fn inspect(mode: Mode, path: String) -> Result<(), std::io::Error> {
+        let source = Self::read_script(&path)?;
+        let mut out = std::io::stdout().lock();
+        let tokens = Scanner::new(source).scan_tokens();
+        let mut written = Ok(());
+        if mode == Mode::Tokens {
+            let lines: Vec<String> = tokens
+                .iter()
+                .map(|token| format!("  {}", token.to_json().stringify(None)))
+                .collect();
+            written = writeln!(out, "[\n{}\n]", lines.join(",\n"));
+        }
+        let statements = parser::Parser::new(tokens).parse();
+        if unsafe { LOX.had_error } {
+            std::process::exit(65);
+        }
+        written = written.and_then(|()| match mode {
+            Mode::DumpAst => write!(out, "{}", AstPrinter.print_program(&statements)),
+            Mode::AstJson => writeln!(out, "{}", statements.to_json().stringify(Some(2))),
+            _ => Ok(()),
+        });
+        match written.and_then(|()| out.flush()) {
+            Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            written => written,
         }
     }
 
+    pub(crate) fn run_prompt() -> Result<(), std::io::Error> {
+        repl::run()
+    }
+
     pub(crate) fn run(source: String) {
         let scanner = Scanner::new(source);
         let tokens = scanner.scan_tokens();
@@ -81,11 +364,79 @@ impl Lox {
             return;
         }
 
-        unsafe { LOX.interpreter.interpret(statements) }
+        #[allow(static_mut_refs)]
+        unsafe {
+            LOX.interpreter.interpret(statements)
+        }
+    }
+
+    /// Scans and parses an imported file. Its syntax errors are reported as
+    /// usual but do not mark the running script as failed to compile.
+    pub(crate) fn parse_module(source: String) -> Option<Vec<stmt::Stmt>> {
+        Self::isolated(|| {
+            let tokens = Scanner::new(source).scan_tokens();
+            parser::Parser::new(tokens).parse()
+        })
+    }
+
+    /// Runs a scan or parse of some other source, returning `None` if it
+    /// reported syntax errors. Those errors do not count against the script.
+    pub(crate) fn isolated<T>(f: impl FnOnce() -> T) -> Option<T> {
+        let had_error = unsafe { LOX.had_error };
+        unsafe {
+            LOX.had_error = false;
+        }
+        let result = f();
+        let failed = unsafe { LOX.had_error };
+        unsafe {
+            LOX.had_error = had_error;
+        }
+        if failed {
+            None
+        } else {
+            Some(result)
+        }
+    }
+
+    /// Runs a scan or parse, returning the syntax errors it found instead of
+    /// printing them. Like `isolated`, they do not count against the script.
+    pub(crate) fn collect_errors<T>(f: impl FnOnce() -> T) -> (T, Vec<SyntaxError>) {
+        #[allow(static_mut_refs)]
+        unsafe {
+            let had_error = LOX.had_error;
+            let collected = LOX.collected_errors.replace(Vec::new());
+            let result = f();
+            let errors = std::mem::replace(&mut LOX.collected_errors, collected);
+            LOX.had_error = had_error;
+            (result, errors.unwrap_or_default())
+        }
+    }
+
+    /// Keeps the error if errors are being collected, otherwise hands it back
+    /// to be printed.
+    fn collect(error: SyntaxError) -> Option<SyntaxError> {
+        #[allow(static_mut_refs)]
+        match unsafe { LOX.collected_errors.as_mut() } {
+            Some(errors) => {
+                errors.push(error);
+                unsafe {
+                    LOX.had_error = true;
+                }
+                None
+            }
+            None => Some(error),
+        }
     }
 
     pub(crate) fn error_at_line(line: i32, message: String) {
-        Self::report(line, "".to_string(), message);
+        let error = SyntaxError {
+            line,
+            token: None,
+            message,
+        };
+        if let Some(error) = Self::collect(error) {
+            Self::report(line, "".to_string(), error.message);
+        }
     }
 
     pub(crate) fn report(line: i32, location: String, message: String) {
@@ -96,15 +447,58 @@ impl Lox {
     }
 
     pub(crate) fn error_at_token(token: Token, message: String) {
+        let line = token.line;
+        let location = Self::location(&token);
+        let error = SyntaxError {
+            line,
+            token: Some(token),
+            message,
+        };
+        if let Some(error) = Self::collect(error) {
+            Self::report(line, location, error.message);
+        }
+    }
+
+    /// Reports a lint warning like an error, naming the rule that raised it.
+    /// Warnings do not stop the script from running.
+    pub(crate) fn warning_at_token(token: &Token, rule: &str, message: String) {
+        eprintln!(
+            "[line {}] Warning {}: {} [{}]",
+            token.line,
+            Self::location(token),
+            message,
+            rule
+        );
+    }
+
+    fn location(token: &Token) -> String {
         if token.token_type == token_type::TokenType::EOF {
-            Self::report(token.line, " at end".to_string(), message);
+            " at end".to_string()
         } else {
-            Self::report(token.line, format!(" at '{}'", token.lexeme), message)
+            format!(" at '{}'", token.lexeme)
+        }
+    }
+
+    pub(crate) fn exit(code: i32) {
+        unsafe {
+            LOX.exit_code = Some(code);
         }
     }
 
     pub(crate) fn runtime_error(error: runtime_error::RuntimeError) {
         eprintln!("{}\n[line {}]", error.message, error.token.line);
+        // Runs of the same frame (usually runaway recursion) print only once.
+        let mut frames = error.stack_trace.iter().peekable();
+        while let Some(frame) = frames.next() {
+            eprintln!("  {}", frame);
+            let mut repeated = 0;
+            while frames.next_if_eq(&frame).is_some() {
+                repeated += 1;
+            }
+            if repeated > 0 {
+                eprintln!("  ... repeated {} more times", repeated);
+            }
+        }
         unsafe {
             LOX.had_runtime_error = true;
         }
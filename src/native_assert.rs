@@ -0,0 +1,32 @@
+use crate::native_functions::{define_native, native_error};
+use crate::value::Value;
+use crate::value::Value::Nil;
+use std::collections::HashMap;
+
+/// Assertions fail with a runtime error at the call, so a test stops at the
+/// first one that does not hold.
+pub(crate) fn define(globals: &mut HashMap<String, Value>) {
+    define_native(globals, "assert", 1..=2, |_, paren, arguments| {
+        if *arguments[0].as_ref() {
+            return Ok(Nil);
+        }
+        let message = match arguments.get(1) {
+            Some(message) => format!("Assertion failed: {}", message),
+            None => "Assertion failed.".to_string(),
+        };
+        Err(native_error(paren, message))
+    });
+    define_native(globals, "assertEqual", 2..=2, |_, paren, arguments| {
+        if arguments[0] == arguments[1] {
+            return Ok(Nil);
+        }
+        Err(native_error(
+            paren,
+            format!(
+                "Expected {} to equal {}.",
+                arguments[0].repr(),
+                arguments[1].repr()
+            ),
+        ))
+    });
+}
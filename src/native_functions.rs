@@ -1,33 +1,139 @@
 use crate::environment::Environment;
 use crate::interpreter::Interpreter;
-use crate::lox_callable::{LoxCallable, LoxNativeFunction};
+use crate::lox_callable::{LoxCallable, LoxNativeFunction, NativeFn};
+use crate::native_assert;
+use crate::native_io;
+use crate::native_json;
+use crate::native_math;
+use crate::native_random;
+use crate::native_string;
+use crate::native_system;
+use crate::native_time;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
 use crate::value::Value;
 use crate::value::Value::Callable;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::error::Error;
+use std::ops::RangeInclusive;
 use std::rc::Rc;
 
 pub fn global_env() -> Rc<RefCell<Environment>> {
-    let globals = globals();
-    Rc::new(RefCell::new(Environment {
-        values: globals,
-        enclosing: None,
-    }))
+    let environment = Environment::new();
+    environment.borrow_mut().values = globals();
+    environment
 }
 
 pub fn globals() -> HashMap<String, Value> {
     let mut globals = HashMap::new();
-    let clock_function: Value =
-        Callable(Box::new(LoxCallable::NativeFunction(LoxNativeFunction {
-            name: String::from("clock"),
-            params: vec![],
-            function: |interpreter: &mut Interpreter| {
-                let now = std::time::SystemTime::now();
-                let duration = now.duration_since(std::time::UNIX_EPOCH).unwrap();
-                Value::Number(duration.as_secs_f64())
-            },
-        })));
-
-    globals.insert(String::from("clock"), clock_function);
+    native_math::define(&mut globals);
+    native_random::define(&mut globals);
+    native_string::define(&mut globals);
+    native_io::define(&mut globals);
+    native_json::define(&mut globals);
+    native_system::define(&mut globals);
+    native_time::define(&mut globals);
+    native_assert::define(&mut globals);
     globals
 }
+
+pub(crate) fn define_native(
+    globals: &mut HashMap<String, Value>,
+    name: &str,
+    arity: RangeInclusive<usize>,
+    function: NativeFn,
+) {
+    let native = Callable(Box::new(LoxCallable::NativeFunction(LoxNativeFunction {
+        name: String::from(name),
+        arity,
+        function,
+    })));
+    globals.insert(String::from(name), native);
+}
+
+/// Natives with effects outside the interpreter stay defined in a sandbox but
+/// fail when called.
+pub(crate) fn check_allowed(
+    interpreter: &Interpreter,
+    paren: &Token,
+    name: &str,
+) -> Result<(), Box<dyn Error>> {
+    if interpreter.sandboxed() {
+        return Err(native_error(
+            paren,
+            format!("'{}' is not available in a sandbox.", name),
+        ));
+    }
+    Ok(())
+}
+
+/// The error natives raise for bad arguments, reported at the call's paren.
+pub(crate) fn native_error(paren: &Token, message: String) -> Box<dyn Error> {
+    Box::new(RuntimeError::new(paren.clone(), message))
+}
+
+pub(crate) fn number_argument(
+    name: &str,
+    paren: &Token,
+    arguments: &[Value],
+    index: usize,
+) -> Result<f64, Box<dyn Error>> {
+    match arguments[index] {
+        Value::Number(n) => Ok(n),
+        _ => Err(native_error(
+            paren,
+            format!("Argument {} to '{}' must be a number.", index + 1, name),
+        )),
+    }
+}
+
+pub(crate) fn string_argument<'a>(
+    name: &str,
+    paren: &Token,
+    arguments: &'a [Value],
+    index: usize,
+) -> Result<&'a str, Box<dyn Error>> {
+    match &arguments[index] {
+        Value::String(s) => Ok(s),
+        _ => Err(native_error(
+            paren,
+            format!("Argument {} to '{}' must be a string.", index + 1, name),
+        )),
+    }
+}
+
+/// A number argument used as a count or position.
+pub(crate) fn index_argument(
+    name: &str,
+    paren: &Token,
+    arguments: &[Value],
+    index: usize,
+) -> Result<usize, Box<dyn Error>> {
+    match arguments[index] {
+        Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+        _ => Err(native_error(
+            paren,
+            format!(
+                "Argument {} to '{}' must be a non-negative integer.",
+                index + 1,
+                name
+            ),
+        )),
+    }
+}
+
+pub(crate) fn list_argument(
+    name: &str,
+    paren: &Token,
+    arguments: &[Value],
+    index: usize,
+) -> Result<Rc<RefCell<Vec<Value>>>, Box<dyn Error>> {
+    match &arguments[index] {
+        Value::List(list) => Ok(list.clone()),
+        _ => Err(native_error(
+            paren,
+            format!("Argument {} to '{}' must be a list.", index + 1, name),
+        )),
+    }
+}
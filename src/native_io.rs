@@ -0,0 +1,110 @@
+use crate::native_functions::{check_allowed, define_native, native_error, string_argument};
+use crate::token::Token;
+use crate::value::Value;
+use crate::value::Value::{Boolean, Nil};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{Read, Write};
+
+pub(crate) fn define(globals: &mut HashMap<String, Value>) {
+    define_native(globals, "readLine", 0..=0, |interpreter, paren, _| {
+        check_allowed(interpreter, paren, "readLine")?;
+        let mut line = String::new();
+        let read = std::io::stdin()
+            .read_line(&mut line)
+            .map_err(|e| io_error(paren, "read a line from stdin", e))?;
+        if read == 0 {
+            return Ok(Nil);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Value::String(line))
+    });
+    define_native(globals, "readAll", 0..=0, |interpreter, paren, _| {
+        check_allowed(interpreter, paren, "readAll")?;
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| io_error(paren, "read stdin", e))?;
+        Ok(Value::String(input))
+    });
+    define_native(
+        globals,
+        "readFile",
+        1..=1,
+        |interpreter, paren, arguments| {
+            check_allowed(interpreter, paren, "readFile")?;
+            let path = string_argument("readFile", paren, &arguments, 0)?;
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| io_error(paren, &format!("read '{}'", path), e))?;
+            Ok(Value::String(contents))
+        },
+    );
+    define_native(
+        globals,
+        "writeFile",
+        2..=2,
+        |interpreter, paren, arguments| {
+            check_allowed(interpreter, paren, "writeFile")?;
+            let path = string_argument("writeFile", paren, &arguments, 0)?;
+            let contents = string_argument("writeFile", paren, &arguments, 1)?;
+            std::fs::write(path, contents)
+                .map_err(|e| io_error(paren, &format!("write '{}'", path), e))?;
+            Ok(Nil)
+        },
+    );
+    define_native(
+        globals,
+        "appendFile",
+        2..=2,
+        |interpreter, paren, arguments| {
+            check_allowed(interpreter, paren, "appendFile")?;
+            let path = string_argument("appendFile", paren, &arguments, 0)?;
+            let contents = string_argument("appendFile", paren, &arguments, 1)?;
+            std::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(contents.as_bytes()))
+                .map_err(|e| io_error(paren, &format!("append to '{}'", path), e))?;
+            Ok(Nil)
+        },
+    );
+    define_native(globals, "exists", 1..=1, |interpreter, paren, arguments| {
+        check_allowed(interpreter, paren, "exists")?;
+        let path = string_argument("exists", paren, &arguments, 0)?;
+        Ok(Boolean(std::path::Path::new(path).exists()))
+    });
+    define_native(
+        globals,
+        "listDir",
+        1..=1,
+        |interpreter, paren, arguments| {
+            check_allowed(interpreter, paren, "listDir")?;
+            let path = string_argument("listDir", paren, &arguments, 0)?;
+            let error = |e| io_error(paren, &format!("list '{}'", path), e);
+            let mut names = Vec::new();
+            for entry in std::fs::read_dir(path).map_err(error)? {
+                let entry = entry.map_err(error)?;
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+            names.sort();
+            Ok(Value::new_list(
+                names.into_iter().map(Value::String).collect(),
+            ))
+        },
+    );
+    define_native(globals, "eprint", 1..=1, |interpreter, paren, arguments| {
+        check_allowed(interpreter, paren, "eprint")?;
+        eprintln!("{}", arguments[0]);
+        Ok(Nil)
+    });
+}
+
+fn io_error(paren: &Token, action: &str, error: std::io::Error) -> Box<dyn Error> {
+    native_error(paren, format!("Could not {}: {}.", action, error))
+}
@@ -0,0 +1,101 @@
+use crate::json::{self, Json};
+use crate::lox_map::LoxMap;
+use crate::native_functions::{define_native, index_argument, native_error, string_argument};
+use crate::token::Token;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::rc::Rc;
+
+pub(crate) fn define(globals: &mut HashMap<String, Value>) {
+    define_native(globals, "jsonParse", 1..=1, |_, paren, arguments| {
+        let source = string_argument("jsonParse", paren, &arguments, 0)?;
+        match Json::parse(source) {
+            Ok(json) => Ok(from_json(json)),
+            Err(error) => Err(native_error(paren, format!("Invalid JSON at {}", error))),
+        }
+    });
+    define_native(globals, "jsonStringify", 1..=2, |_, paren, arguments| {
+        let indent = match arguments.len() {
+            2 => Some(index_argument("jsonStringify", paren, &arguments, 1)?),
+            _ => None,
+        };
+        let json = to_json(paren, &arguments[0], &mut Vec::new())?;
+        Ok(Value::String(json.stringify(indent)))
+    });
+}
+
+fn from_json(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Nil,
+        Json::Bool(b) => Value::Boolean(b),
+        Json::Number(n) => Value::Number(n),
+        Json::String(s) => Value::String(s),
+        Json::Array(elements) => Value::new_list(elements.into_iter().map(from_json).collect()),
+        Json::Object(members) => {
+            let mut map = LoxMap::new();
+            for (key, value) in members {
+                map.insert(key, from_json(value));
+            }
+            Value::new_map(map)
+        }
+    }
+}
+
+/// `open` holds the lists and maps being converted further up; meeting one
+/// again means the value contains itself. Values nested deeper than
+/// `jsonParse` accepts are refused too.
+fn to_json(paren: &Token, value: &Value, open: &mut Vec<usize>) -> Result<Json, Box<dyn Error>> {
+    let mut enter = |id: usize| {
+        if open.contains(&id) {
+            return Err(native_error(
+                paren,
+                "Cannot serialize a cyclic structure to JSON.".to_string(),
+            ));
+        }
+        if open.len() == json::MAX_DEPTH {
+            return Err(native_error(
+                paren,
+                format!(
+                    "Cannot serialize values nested more than {} deep to JSON.",
+                    json::MAX_DEPTH
+                ),
+            ));
+        }
+        open.push(id);
+        Ok(())
+    };
+    let json = match value {
+        Value::Nil => Json::Null,
+        Value::Boolean(b) => Json::Bool(*b),
+        Value::Number(n) if n.is_finite() => Json::Number(*n),
+        Value::String(s) => Json::String(s.clone()),
+        Value::List(list) => {
+            enter(Rc::as_ptr(list) as usize)?;
+            let elements = list
+                .borrow()
+                .iter()
+                .map(|element| to_json(paren, element, open))
+                .collect::<Result<_, _>>()?;
+            open.pop();
+            Json::Array(elements)
+        }
+        Value::Map(map) => {
+            enter(Rc::as_ptr(map) as usize)?;
+            let members = map
+                .borrow()
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), to_json(paren, value, open)?)))
+                .collect::<Result<_, Box<dyn Error>>>()?;
+            open.pop();
+            Json::Object(members)
+        }
+        _ => {
+            return Err(native_error(
+                paren,
+                format!("Cannot serialize {} to JSON.", value),
+            ))
+        }
+    };
+    Ok(json)
+}
@@ -0,0 +1,139 @@
+use crate::native_functions::{define_native, native_error, number_argument};
+use crate::token::Token;
+use crate::value::Value;
+use crate::value::Value::{Boolean, Number};
+use std::collections::HashMap;
+use std::error::Error;
+
+pub(crate) fn define(globals: &mut HashMap<String, Value>) {
+    globals.insert(String::from("PI"), Number(std::f64::consts::PI));
+    globals.insert(String::from("E"), Number(std::f64::consts::E));
+    globals.insert(String::from("INF"), Number(f64::INFINITY));
+    globals.insert(String::from("NAN"), Number(f64::NAN));
+
+    define_native(globals, "sqrt", 1..=1, |_, paren, arguments| {
+        unary("sqrt", paren, &arguments, f64::sqrt)
+    });
+    define_native(globals, "abs", 1..=1, |_, paren, arguments| {
+        unary("abs", paren, &arguments, f64::abs)
+    });
+    define_native(globals, "floor", 1..=1, |_, paren, arguments| {
+        unary("floor", paren, &arguments, f64::floor)
+    });
+    define_native(globals, "ceil", 1..=1, |_, paren, arguments| {
+        unary("ceil", paren, &arguments, f64::ceil)
+    });
+    define_native(globals, "round", 1..=1, |_, paren, arguments| {
+        unary("round", paren, &arguments, f64::round)
+    });
+    define_native(globals, "sin", 1..=1, |_, paren, arguments| {
+        unary("sin", paren, &arguments, f64::sin)
+    });
+    define_native(globals, "cos", 1..=1, |_, paren, arguments| {
+        unary("cos", paren, &arguments, f64::cos)
+    });
+    define_native(globals, "tan", 1..=1, |_, paren, arguments| {
+        unary("tan", paren, &arguments, f64::tan)
+    });
+    define_native(globals, "asin", 1..=1, |_, paren, arguments| {
+        unary("asin", paren, &arguments, f64::asin)
+    });
+    define_native(globals, "acos", 1..=1, |_, paren, arguments| {
+        unary("acos", paren, &arguments, f64::acos)
+    });
+    define_native(globals, "atan", 1..=1, |_, paren, arguments| {
+        unary("atan", paren, &arguments, f64::atan)
+    });
+    define_native(globals, "exp", 1..=1, |_, paren, arguments| {
+        unary("exp", paren, &arguments, f64::exp)
+    });
+    define_native(globals, "log", 1..=1, |_, paren, arguments| {
+        unary("log", paren, &arguments, f64::ln)
+    });
+    define_native(globals, "log2", 1..=1, |_, paren, arguments| {
+        unary("log2", paren, &arguments, f64::log2)
+    });
+    define_native(globals, "log10", 1..=1, |_, paren, arguments| {
+        unary("log10", paren, &arguments, f64::log10)
+    });
+    define_native(globals, "pow", 2..=2, |_, paren, arguments| {
+        binary("pow", paren, &arguments, f64::powf)
+    });
+    define_native(globals, "atan2", 2..=2, |_, paren, arguments| {
+        binary("atan2", paren, &arguments, f64::atan2)
+    });
+    define_native(globals, "min", 1..=usize::MAX, |_, paren, arguments| {
+        fold("min", paren, &arguments, f64::min)
+    });
+    define_native(globals, "max", 1..=usize::MAX, |_, paren, arguments| {
+        fold("max", paren, &arguments, f64::max)
+    });
+    define_native(globals, "isNaN", 1..=1, |_, paren, arguments| {
+        Ok(Boolean(
+            number_argument("isNaN", paren, &arguments, 0)?.is_nan(),
+        ))
+    });
+    define_native(globals, "isFinite", 1..=1, |_, paren, arguments| {
+        Ok(Boolean(
+            number_argument("isFinite", paren, &arguments, 0)?.is_finite(),
+        ))
+    });
+}
+
+fn unary(
+    name: &str,
+    paren: &Token,
+    arguments: &[Value],
+    f: fn(f64) -> f64,
+) -> Result<Value, Box<dyn Error>> {
+    let x = number_argument(name, paren, arguments, 0)?;
+    checked(name, paren, &[x], f(x))
+}
+
+fn binary(
+    name: &str,
+    paren: &Token,
+    arguments: &[Value],
+    f: fn(f64, f64) -> f64,
+) -> Result<Value, Box<dyn Error>> {
+    let x = number_argument(name, paren, arguments, 0)?;
+    let y = number_argument(name, paren, arguments, 1)?;
+    checked(name, paren, &[x, y], f(x, y))
+}
+
+/// `min` and `max`. Unlike `f64::min`, a NaN argument makes the result NaN
+/// instead of being skipped.
+fn fold(
+    name: &str,
+    paren: &Token,
+    arguments: &[Value],
+    f: fn(f64, f64) -> f64,
+) -> Result<Value, Box<dyn Error>> {
+    let mut result = number_argument(name, paren, arguments, 0)?;
+    for index in 1..arguments.len() {
+        let x = number_argument(name, paren, arguments, index)?;
+        result = if result.is_nan() || x.is_nan() {
+            f64::NAN
+        } else {
+            f(result, x)
+        };
+    }
+    Ok(Number(result))
+}
+
+/// A NaN out of non-NaN inputs means the inputs were outside the function's
+/// domain, such as `sqrt(-1)` or `asin(2)`.
+fn checked(
+    name: &str,
+    paren: &Token,
+    inputs: &[f64],
+    result: f64,
+) -> Result<Value, Box<dyn Error>> {
+    if result.is_nan() && !inputs.iter().any(|x| x.is_nan()) {
+        return Err(native_error(
+            paren,
+            format!("Math domain error in '{}'.", name),
+        ));
+    }
+    Ok(Number(result))
+}
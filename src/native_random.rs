@@ -0,0 +1,75 @@
+use crate::native_functions::{define_native, list_argument, native_error, number_argument};
+use crate::value::Value;
+use crate::value::Value::{Nil, Number};
+use std::collections::HashMap;
+
+const MAX_SPAN: f64 = 9007199254740992.0;
+
+pub(crate) fn define(globals: &mut HashMap<String, Value>) {
+    define_native(globals, "random", 0..=0, |interpreter, _, _| {
+        Ok(Number(interpreter.rng().next_f64()))
+    });
+    define_native(
+        globals,
+        "randomInt",
+        2..=2,
+        |interpreter, paren, arguments| {
+            let lo = number_argument("randomInt", paren, &arguments, 0)?;
+            let hi = number_argument("randomInt", paren, &arguments, 1)?;
+            if lo.fract() != 0.0 || hi.fract() != 0.0 || lo > hi {
+                return Err(native_error(
+                    paren,
+                    "Arguments to 'randomInt' must be integers with lo <= hi.".to_string(),
+                ));
+            }
+            // Beyond 2^53 apart, not every integer between them is a number.
+            if hi - lo > MAX_SPAN {
+                return Err(native_error(
+                    paren,
+                    "Arguments to 'randomInt' must be at most 2^53 apart.".to_string(),
+                ));
+            }
+            // Both bounds are inclusive.
+            let span = (hi - lo) as u64 + 1;
+            Ok(Number(lo + interpreter.rng().below(span) as f64))
+        },
+    );
+    define_native(
+        globals,
+        "shuffle",
+        1..=1,
+        |interpreter, paren, arguments| {
+            let list = list_argument("shuffle", paren, &arguments, 0)?;
+            let mut list = list.borrow_mut();
+            for i in (1..list.len()).rev() {
+                let j = interpreter.rng().below(i as u64 + 1) as usize;
+                list.swap(i, j);
+            }
+            Ok(Nil)
+        },
+    );
+    define_native(globals, "choice", 1..=1, |interpreter, paren, arguments| {
+        let list = list_argument("choice", paren, &arguments, 0)?;
+        let list = list.borrow();
+        if list.is_empty() {
+            return Err(native_error(
+                paren,
+                "Cannot choose from an empty list.".to_string(),
+            ));
+        }
+        let i = interpreter.rng().below(list.len() as u64) as usize;
+        Ok(list[i].clone())
+    });
+    define_native(globals, "seed", 1..=1, |interpreter, paren, arguments| {
+        let seed = number_argument("seed", paren, &arguments, 0)?;
+        // Any such number converts to a distinct `u64`, as `--seed` takes.
+        if !(0.0..18446744073709551616.0).contains(&seed) || seed.fract() != 0.0 {
+            return Err(native_error(
+                paren,
+                "Argument 1 to 'seed' must be a non-negative integer below 2^64.".to_string(),
+            ));
+        }
+        interpreter.rng().seed(seed as u64);
+        Ok(Nil)
+    });
+}
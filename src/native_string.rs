@@ -0,0 +1,184 @@
+use crate::native_functions::{
+    define_native, index_argument, list_argument, native_error, string_argument,
+};
+use crate::token::Token;
+use crate::value::Value;
+use crate::value::Value::{Boolean, Nil, Number};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// The longest string `repeat` builds, in bytes, so that a huge count is a
+/// runtime error rather than an allocation failure that aborts.
+const MAX_REPEAT_BYTES: usize = 1 << 28;
+
+pub(crate) fn define(globals: &mut HashMap<String, Value>) {
+    define_native(globals, "toString", 1..=1, |_, _, arguments| {
+        Ok(Value::String(arguments[0].to_string()))
+    });
+    define_native(globals, "parseNumber", 1..=1, |_, paren, arguments| {
+        let s = string_argument("parseNumber", paren, &arguments, 0)?;
+        Ok(s.trim().parse().map(Number).unwrap_or(Nil))
+    });
+    define_native(globals, "len", 1..=1, |_, paren, arguments| {
+        let length = match &arguments[0] {
+            Value::String(s) => s.chars().count(),
+            Value::List(list) => list.borrow().len(),
+            Value::Map(map) => map.borrow().len(),
+            _ => {
+                return Err(native_error(
+                    paren,
+                    "Argument 1 to 'len' must be a string, list or map.".to_string(),
+                ))
+            }
+        };
+        Ok(Number(length as f64))
+    });
+    define_native(globals, "substring", 2..=3, |_, paren, arguments| {
+        let s = string_argument("substring", paren, &arguments, 0)?;
+        let length = s.chars().count();
+        let start = index_argument("substring", paren, &arguments, 1)?;
+        let end = match arguments.len() {
+            3 => index_argument("substring", paren, &arguments, 2)?,
+            _ => length,
+        };
+        if start > end || end > length {
+            return Err(native_error(
+                paren,
+                format!(
+                    "Substring range {}..{} out of bounds for length {}.",
+                    start, end, length
+                ),
+            ));
+        }
+        Ok(Value::String(
+            s.chars().skip(start).take(end - start).collect(),
+        ))
+    });
+    define_native(globals, "indexOf", 2..=2, |_, paren, arguments| {
+        let index = match &arguments[0] {
+            Value::String(s) => {
+                let needle = string_argument("indexOf", paren, &arguments, 1)?;
+                s.find(needle).map(|byte| s[..byte].chars().count())
+            }
+            Value::List(list) => list.borrow().iter().position(|v| *v == arguments[1]),
+            _ => {
+                return Err(native_error(
+                    paren,
+                    "Argument 1 to 'indexOf' must be a string or a list.".to_string(),
+                ))
+            }
+        };
+        Ok(Number(index.map_or(-1.0, |i| i as f64)))
+    });
+    define_native(globals, "split", 2..=2, |_, paren, arguments| {
+        let s = string_argument("split", paren, &arguments, 0)?;
+        let separator = string_argument("split", paren, &arguments, 1)?;
+        let parts = if separator.is_empty() {
+            s.chars().map(|c| Value::String(c.to_string())).collect()
+        } else {
+            s.split(separator)
+                .map(|part| Value::String(part.to_string()))
+                .collect()
+        };
+        Ok(Value::new_list(parts))
+    });
+    define_native(globals, "join", 2..=2, |_, paren, arguments| {
+        let list = list_argument("join", paren, &arguments, 0)?;
+        let separator = string_argument("join", paren, &arguments, 1)?;
+        let parts: Vec<String> = list.borrow().iter().map(Value::to_string).collect();
+        Ok(Value::String(parts.join(separator)))
+    });
+    define_native(globals, "trim", 1..=1, |_, paren, arguments| {
+        let s = string_argument("trim", paren, &arguments, 0)?;
+        Ok(Value::String(s.trim().to_string()))
+    });
+    define_native(globals, "upper", 1..=1, |_, paren, arguments| {
+        let s = string_argument("upper", paren, &arguments, 0)?;
+        Ok(Value::String(s.to_uppercase()))
+    });
+    define_native(globals, "lower", 1..=1, |_, paren, arguments| {
+        let s = string_argument("lower", paren, &arguments, 0)?;
+        Ok(Value::String(s.to_lowercase()))
+    });
+    define_native(globals, "replace", 3..=3, |_, paren, arguments| {
+        let s = string_argument("replace", paren, &arguments, 0)?;
+        let from = string_argument("replace", paren, &arguments, 1)?;
+        let to = string_argument("replace", paren, &arguments, 2)?;
+        if from.is_empty() {
+            return Err(native_error(
+                paren,
+                "Argument 2 to 'replace' must not be empty.".to_string(),
+            ));
+        }
+        Ok(Value::String(s.replace(from, to)))
+    });
+    define_native(globals, "startsWith", 2..=2, |_, paren, arguments| {
+        let s = string_argument("startsWith", paren, &arguments, 0)?;
+        let prefix = string_argument("startsWith", paren, &arguments, 1)?;
+        Ok(Boolean(s.starts_with(prefix)))
+    });
+    define_native(globals, "endsWith", 2..=2, |_, paren, arguments| {
+        let s = string_argument("endsWith", paren, &arguments, 0)?;
+        let suffix = string_argument("endsWith", paren, &arguments, 1)?;
+        Ok(Boolean(s.ends_with(suffix)))
+    });
+    define_native(globals, "repeat", 2..=2, |_, paren, arguments| {
+        let s = string_argument("repeat", paren, &arguments, 0)?;
+        let count = index_argument("repeat", paren, &arguments, 1)?;
+        match s.len().checked_mul(count) {
+            Some(bytes) if bytes <= MAX_REPEAT_BYTES => Ok(Value::String(s.repeat(count))),
+            _ => Err(native_error(
+                paren,
+                "Result of 'repeat' is too large.".to_string(),
+            )),
+        }
+    });
+    define_native(globals, "format", 1..=usize::MAX, |_, paren, arguments| {
+        let template = string_argument("format", paren, &arguments, 0)?;
+        format(paren, template, &arguments[1..]).map(Value::String)
+    });
+}
+
+/// Replaces each `{}` in `template` with the next argument. `{{` and `}}`
+/// stand for literal braces.
+fn format(paren: &Token, template: &str, arguments: &[Value]) -> Result<String, Box<dyn Error>> {
+    let given = arguments.len();
+    let mut result = String::new();
+    let mut arguments = arguments.iter();
+    let mut placeholders = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+                if let Some(argument) = arguments.next() {
+                    result.push_str(&argument.to_string());
+                }
+            }
+            ('{', _) | ('}', _) => {
+                return Err(native_error(
+                    paren,
+                    "Unmatched brace in format string; use '{{' or '}}' for a literal brace."
+                        .to_string(),
+                ))
+            }
+            _ => result.push(c),
+        }
+    }
+
+    if placeholders != given {
+        return Err(native_error(
+            paren,
+            format!(
+                "Format string has {} placeholders but got {} arguments.",
+                placeholders, given
+            ),
+        ));
+    }
+    Ok(result)
+}
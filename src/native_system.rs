@@ -0,0 +1,26 @@
+use crate::native_functions::{check_allowed, define_native, native_error, string_argument};
+use crate::runtime_error::Exit;
+use crate::value::Value;
+use crate::value::Value::Nil;
+use std::collections::HashMap;
+
+pub(crate) fn define(globals: &mut HashMap<String, Value>) {
+    define_native(globals, "getenv", 1..=1, |interpreter, paren, arguments| {
+        check_allowed(interpreter, paren, "getenv")?;
+        let name = string_argument("getenv", paren, &arguments, 0)?;
+        Ok(std::env::var(name).map(Value::String).unwrap_or(Nil))
+    });
+    define_native(globals, "exit", 0..=1, |_, paren, arguments| {
+        let code = match arguments.first() {
+            None => 0,
+            Some(Value::Number(n)) if n.fract() == 0.0 && (0.0..=255.0).contains(n) => *n as i32,
+            Some(_) => {
+                return Err(native_error(
+                    paren,
+                    "Argument 1 to 'exit' must be an integer from 0 to 255.".to_string(),
+                ))
+            }
+        };
+        Err(Box::new(Exit(code)))
+    });
+}
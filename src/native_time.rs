@@ -0,0 +1,158 @@
+use crate::interpreter::Interpreter;
+use crate::lox_map::LoxMap;
+use crate::native_functions::{define_native, native_error, number_argument};
+use crate::token::Token;
+use crate::value::Value;
+use crate::value::Value::{Nil, Number};
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+/// How far from the epoch a date may be, in seconds: 100 million days either
+/// way, as in JavaScript, which keeps the date arithmetic exact.
+const MAX_TIMESTAMP: f64 = 8.64e12;
+
+pub(crate) fn define(globals: &mut HashMap<String, Value>) {
+    define_native(globals, "clock", 0..=0, |interpreter, _, _| {
+        Ok(Number(interpreter.clock().wall().as_secs_f64()))
+    });
+    define_native(globals, "now", 0..=0, |interpreter, _, _| {
+        Ok(Number(interpreter.clock().monotonic().as_secs_f64()))
+    });
+    define_native(globals, "sleep", 1..=1, |interpreter, paren, arguments| {
+        let ms = number_argument("sleep", paren, &arguments, 0)?;
+        if !(ms >= 0.0 && ms.is_finite()) {
+            return Err(native_error(
+                paren,
+                "Argument 1 to 'sleep' must be a non-negative number.".to_string(),
+            ));
+        }
+        match Duration::try_from_secs_f64(ms / 1000.0) {
+            Ok(duration) if interpreter.clock().sleep(duration) => Ok(Nil),
+            _ => Err(native_error(
+                paren,
+                "Argument 1 to 'sleep' is too large.".to_string(),
+            )),
+        }
+    });
+    define_native(globals, "date", 0..=1, |interpreter, paren, arguments| {
+        let date = DateTime::from_timestamp(timestamp("date", interpreter, paren, &arguments)?);
+        let mut map = LoxMap::new();
+        for (key, value) in [
+            ("year", date.year),
+            ("month", date.month),
+            ("day", date.day),
+            ("hour", date.hour),
+            ("minute", date.minute),
+            ("second", date.second),
+            ("millisecond", date.millisecond),
+        ] {
+            map.insert(key.to_string(), Number(value as f64));
+        }
+        Ok(Value::new_map(map))
+    });
+    define_native(
+        globals,
+        "isoDate",
+        0..=1,
+        |interpreter, paren, arguments| {
+            let date =
+                DateTime::from_timestamp(timestamp("isoDate", interpreter, paren, &arguments)?);
+            Ok(Value::String(date.to_iso8601()))
+        },
+    );
+}
+
+/// The timestamp argument in seconds since the epoch, or the current time.
+fn timestamp(
+    name: &str,
+    interpreter: &Interpreter,
+    paren: &Token,
+    arguments: &[Value],
+) -> Result<f64, Box<dyn Error>> {
+    match arguments.len() {
+        0 => {
+            let seconds = interpreter.clock().wall().as_secs_f64();
+            if seconds > MAX_TIMESTAMP {
+                return Err(native_error(
+                    paren,
+                    format!("The clock is too far from the epoch for '{}'.", name),
+                ));
+            }
+            Ok(seconds)
+        }
+        _ => {
+            let seconds = number_argument(name, paren, arguments, 0)?;
+            if !seconds.is_finite() {
+                return Err(native_error(
+                    paren,
+                    format!("Argument 1 to '{}' must be a finite number.", name),
+                ));
+            }
+            if seconds.abs() > MAX_TIMESTAMP {
+                return Err(native_error(
+                    paren,
+                    format!(
+                        "Argument 1 to '{}' must be within 100000000 days of the epoch.",
+                        name
+                    ),
+                ));
+            }
+            Ok(seconds)
+        }
+    }
+}
+
+/// A UTC date and time of day in the proleptic Gregorian calendar.
+struct DateTime {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    millisecond: i64,
+}
+
+impl DateTime {
+    fn from_timestamp(seconds: f64) -> Self {
+        let millis = (seconds * 1000.0).floor() as i64;
+        let days = millis.div_euclid(86_400_000);
+        let time = millis.rem_euclid(86_400_000);
+
+        // Howard Hinnant's days-to-civil algorithm, with eras of 400 years.
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        DateTime {
+            year,
+            month,
+            day,
+            hour: time / 3_600_000,
+            minute: time / 60_000 % 60,
+            second: time / 1000 % 60,
+            millisecond: time % 1000,
+        }
+    }
+
+    /// Formats as `YYYY-MM-DDTHH:MM:SSZ`, with milliseconds only when there
+    /// are some.
+    fn to_iso8601(&self) -> String {
+        let mut s = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        );
+        if self.millisecond != 0 {
+            s.push_str(&format!(".{:03}", self.millisecond));
+        }
+        s.push('Z');
+        s
+    }
+}
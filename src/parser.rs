@@ -1,19 +1,51 @@
 use crate::expr::Expr;
-use crate::stmt::LoxFunctionNode;
 use crate::stmt::Stmt;
+use crate::stmt::{CatchClause, LoxFunctionNode};
 use crate::token::{Literal, Token};
 use crate::token_type::TokenType;
 use crate::token_type::TokenType::*;
 use crate::Lox;
+use std::rc::Rc;
 
 pub(crate) struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /// Whether a final expression statement may leave out its semicolon, as
+    /// when typed at the prompt.
+    repl: bool,
+    /// Set when the last statement parsed was such an expression.
+    ends_with_expression: bool,
+    /// How many function bodies the parser is inside, as `return` only
+    /// makes sense in one.
+    function_depth: usize,
+    /// How many blocks the parser is inside, as tests are only found at the
+    /// top level.
+    block_depth: usize,
 }
 
 impl Parser {
     pub(crate) fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            repl: false,
+            ends_with_expression: false,
+            function_depth: 0,
+            block_depth: 0,
+        }
+    }
+
+    pub(crate) fn new_repl(tokens: Vec<Token>) -> Self {
+        Parser {
+            repl: true,
+            ..Self::new(tokens)
+        }
+    }
+
+    /// Whether the input ended with a bare expression, whose value the REPL
+    /// echoes.
+    pub(crate) fn ends_with_expression(&self) -> bool {
+        self.ends_with_expression
     }
 
     pub(crate) fn parse(&mut self) -> Vec<Stmt> {
@@ -23,9 +55,24 @@ impl Parser {
                 statements.push(stmt);
             }
         }
+        // Only a bare expression at the top level is echoed, not one ending
+        // the body of an `if` or loop.
+        self.ends_with_expression = self.repl
+            && matches!(statements.last(), Some(Stmt::Expression { .. }))
+            && self.previous().token_type != SEMICOLON;
         statements
     }
 
+    /// Parses a lone expression, reporting an error if anything follows it.
+    pub(crate) fn parse_expression(&mut self) -> Option<Expr> {
+        let expr = self.expression().ok()?;
+        if !self.is_at_end() {
+            Self::error(self.peek(), "Expect end of expression.".to_string());
+            return None;
+        }
+        Some(expr)
+    }
+
     fn declaration(&mut self) -> Option<Stmt> {
         if self.match_token(&[VAR]) {
             match self.var_declaration() {
@@ -36,7 +83,22 @@ impl Parser {
             }
         }
         if self.match_token(&[FUN]) {
-            return Some(self.function("function".to_string()).unwrap());
+            match self.function("function".to_string()) {
+                Ok(stmt) => return Some(stmt),
+                Err(_) => {
+                    self.synchronize();
+                    return None;
+                }
+            }
+        }
+        if self.block_depth == 0 && self.check_test() {
+            match self.test_declaration() {
+                Ok(stmt) => return Some(stmt),
+                Err(_) => {
+                    self.synchronize();
+                    return None;
+                }
+            }
         }
 
         match self.statement() {
@@ -57,11 +119,26 @@ impl Parser {
         if self.match_token(&[EQUAL]) {
             let equals = self.previous();
             let value = self.assignment()?;
-            if let Expr::Variable { name } = expr {
-                return Ok(Expr::Assign {
-                    name,
-                    value: Box::new(value),
-                });
+            match expr {
+                Expr::Variable { name } => {
+                    return Ok(Expr::Assign {
+                        name,
+                        value: Box::new(value),
+                    })
+                }
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                } => {
+                    return Ok(Expr::SetIndex {
+                        object,
+                        bracket,
+                        index,
+                        value: Box::new(value),
+                    })
+                }
+                _ => {}
             }
             return Err(Self::error(
                 equals,
@@ -72,6 +149,13 @@ impl Parser {
     }
 
     fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.check_test() {
+            Self::error(
+                self.peek(),
+                "Tests must be declared at the top level.".to_string(),
+            );
+            return self.test_declaration();
+        }
         if self.match_token(&[IF]) {
             return self.if_statement();
         }
@@ -81,8 +165,21 @@ impl Parser {
         if self.match_token(&[RETURN]) {
             return self.return_statement();
         }
+        if self.match_token(&[THROW]) {
+            return self.throw_statement();
+        }
+        if self.match_token(&[TRY]) {
+            return self.try_statement();
+        }
+        if self.match_token(&[IMPORT]) {
+            return self.import_statement();
+        }
+        if self.match_token(&[FROM]) {
+            return self.selective_import_statement();
+        }
         if self.match_token(&[LEFT_BRACE]) {
             return Ok(Stmt::Block {
+                brace: self.previous(),
                 statements: self.block()?,
             });
         }
@@ -96,15 +193,23 @@ impl Parser {
     }
 
     fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
         let value = self.expression()?;
         self.consume(SEMICOLON, "Expect ';' after value.".to_string())?;
         Ok(Stmt::Print {
+            keyword,
             expression: Box::new(value),
         })
     }
 
     fn return_statement(&mut self) -> Result<Stmt, ParseError> {
         let keyword: Token = self.previous();
+        if self.function_depth == 0 {
+            Self::error(
+                keyword.clone(),
+                "Can't return from top-level code.".to_string(),
+            );
+        }
         let mut value = Expr::Literal {
             value: Literal::Nil,
         };
@@ -121,6 +226,109 @@ impl Parser {
         })
     }
 
+    fn throw_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
+        let value = self.expression()?;
+        self.consume(SEMICOLON, "Expect ';' after thrown value.".to_string())?;
+        Ok(Stmt::Throw {
+            keyword,
+            value: Box::new(value),
+        })
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
+        self.consume(LEFT_BRACE, "Expect '{' after 'try'.".to_string())?;
+        let body = self.block()?;
+
+        let mut catch_clause = None;
+        if self.match_token(&[CATCH]) {
+            self.consume(LEFT_PAREN, "Expect '(' after 'catch'.".to_string())?;
+            let name = self.consume(IDENTIFIER, "Expect error variable name.".to_string())?;
+            self.consume(RIGHT_PAREN, "Expect ')' after error variable.".to_string())?;
+            self.consume(LEFT_BRACE, "Expect '{' before catch body.".to_string())?;
+            catch_clause = Some(CatchClause {
+                name,
+                body: self.block()?,
+            });
+        }
+
+        let mut finally_body = None;
+        if self.match_token(&[FINALLY]) {
+            self.consume(LEFT_BRACE, "Expect '{' after 'finally'.".to_string())?;
+            finally_body = Some(self.block()?);
+        }
+
+        if catch_clause.is_none() && finally_body.is_none() {
+            return Err(Self::error(
+                self.peek(),
+                "Expect 'catch' or 'finally' after try block.".to_string(),
+            ));
+        }
+
+        Ok(Stmt::Try {
+            keyword,
+            body,
+            catch_clause,
+            finally_body,
+        })
+    }
+
+    /// Whether a test declaration starts here. `test` is only a keyword
+    /// before the test's name, so scripts can still use it as a name.
+    fn check_test(&self) -> bool {
+        let token = self.peek();
+        token.token_type == IDENTIFIER
+            && token.lexeme == "test"
+            && self
+                .tokens
+                .get(self.current + 1)
+                .is_some_and(|next| next.token_type == STRING)
+    }
+
+    fn test_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.advance();
+        let name = self.advance();
+        self.consume(LEFT_BRACE, "Expect '{' before test body.".to_string())?;
+        Ok(Stmt::Test {
+            keyword,
+            name,
+            body: self.block()?,
+        })
+    }
+
+    fn import_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
+        let path = self.consume(STRING, "Expect module path after 'import'.".to_string())?;
+        self.consume(AS, "Expect 'as' after module path.".to_string())?;
+        let alias = self.consume(IDENTIFIER, "Expect module name after 'as'.".to_string())?;
+        self.consume(SEMICOLON, "Expect ';' after import.".to_string())?;
+        Ok(Stmt::Import {
+            keyword,
+            path,
+            alias,
+        })
+    }
+
+    fn selective_import_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
+        let path = self.consume(STRING, "Expect module path after 'from'.".to_string())?;
+        self.consume(IMPORT, "Expect 'import' after module path.".to_string())?;
+        let mut names = Vec::new();
+        loop {
+            names.push(self.consume(IDENTIFIER, "Expect name to import.".to_string())?);
+            if !self.match_token(&[COMMA]) {
+                break;
+            }
+        }
+        self.consume(SEMICOLON, "Expect ';' after import.".to_string())?;
+        Ok(Stmt::FromImport {
+            keyword,
+            path,
+            names,
+        })
+    }
+
     fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
         let name: Token = self.consume(IDENTIFIER, "Expect variable name.".to_string())?;
         let mut initializer = None;
@@ -135,15 +343,21 @@ impl Parser {
     }
 
     fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
         self.consume(LEFT_PAREN, "Expect '(' after 'while'.".to_string())?;
         let condition = Box::new(self.expression()?);
         self.consume(RIGHT_PAREN, "Expect ')' after condition.".to_string())?;
         let body = Box::new(self.statement()?);
 
-        Ok(Stmt::While { condition, body })
+        Ok(Stmt::While {
+            keyword,
+            condition,
+            body,
+        })
     }
 
     fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
         // start reading the for loop header
 
         self.consume(LEFT_PAREN, "Expect '(' after 'for'.".to_string())?;
@@ -164,7 +378,7 @@ impl Parser {
 
         self.consume(SEMICOLON, "Expect ';' after loop condition.".to_string())?;
         let increment = if !self.check(&RIGHT_PAREN) {
-            Some(self.expression()?)
+            Some((self.peek(), self.expression()?))
         } else {
             None
         };
@@ -178,12 +392,14 @@ impl Parser {
 
         // desugar
 
-        if let Some(increment) = increment {
+        if let Some((start, increment)) = increment {
             body = Box::new(Stmt::Block {
+                brace: keyword.clone(),
                 statements: vec![
                     *body,
                     Stmt::Expression {
                         expression: Box::new(increment),
+                        start,
                     },
                 ],
             });
@@ -195,7 +411,8 @@ impl Parser {
         */
 
         body = Box::new(Stmt::While {
-            condition: Box::new(condition.unwrap_or_else(|| Expr::Literal {
+            keyword: keyword.clone(),
+            condition: Box::new(condition.unwrap_or(Expr::Literal {
                 value: Literal::Bool(true),
             })),
             body,
@@ -205,6 +422,7 @@ impl Parser {
 
         if let Some(initializer) = initializer {
             body = Box::new(Stmt::Block {
+                brace: keyword,
                 statements: vec![initializer, *body],
             });
         }
@@ -212,20 +430,30 @@ impl Parser {
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.peek();
         let expr = self.expression()?;
+        if self.repl && self.is_at_end() {
+            return Ok(Stmt::Expression {
+                expression: Box::new(expr),
+                start,
+            });
+        }
         self.consume(SEMICOLON, "Expect ';' after expression.".to_string())?;
         Ok(Stmt::Expression {
             expression: Box::new(expr),
+            start,
         })
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
         let mut statements = Vec::new();
+        self.block_depth += 1;
         while !self.check(&RIGHT_BRACE) && !self.is_at_end() {
             if let Some(stmt) = self.declaration() {
                 statements.push(stmt);
             }
         }
+        self.block_depth -= 1;
         self.consume(RIGHT_BRACE, "Expect '}' after block.".to_string())?;
         Ok(statements)
     }
@@ -252,9 +480,12 @@ impl Parser {
         self.consume(RIGHT_PAREN, "Expect ')' after parameters.".to_string())?;
 
         self.consume(LEFT_BRACE, format!("Expect '{{' before {} body.", kind))?;
-        let body = self.block()?;
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+        let body = body?;
         Ok(Stmt::Function {
-            function: Box::new(LoxFunctionNode { name, body, params }),
+            function: Rc::new(LoxFunctionNode { name, body, params }),
         })
     }
 
@@ -361,6 +592,21 @@ impl Parser {
         loop {
             if self.match_token(&[LEFT_PAREN]) {
                 primary = self.finish_call(primary?);
+            } else if self.match_token(&[LEFT_BRACKET]) {
+                let index = self.expression()?;
+                let bracket = self.consume(RIGHT_BRACKET, "Expect ']' after index.".to_string())?;
+                primary = Ok(Expr::Index {
+                    object: Box::new(primary?),
+                    bracket,
+                    index: Box::new(index),
+                });
+            } else if self.match_token(&[DOT]) {
+                let name =
+                    self.consume(IDENTIFIER, "Expect property name after '.'.".to_string())?;
+                primary = Ok(Expr::Get {
+                    object: Box::new(primary?),
+                    name,
+                });
             } else {
                 break;
             }
@@ -379,7 +625,7 @@ impl Parser {
                         "Cannot have more than 255 arguments.".to_string(),
                     );
                 }
-                arguments.push(Box::new(self.expression()?));
+                arguments.push(self.expression()?);
 
                 if !self.match_token(&[COMMA]) {
                     break;
@@ -420,6 +666,19 @@ impl Parser {
                 name: self.previous(),
             });
         }
+        if self.match_token(&[LEFT_BRACKET]) {
+            let mut elements = Vec::new();
+            if !self.check(&RIGHT_BRACKET) {
+                loop {
+                    elements.push(self.expression()?);
+                    if !self.match_token(&[COMMA]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(RIGHT_BRACKET, "Expect ']' after list elements.".to_string())?;
+            return Ok(Expr::List { elements });
+        }
         if self.match_token(&[LEFT_PAREN]) {
             let expr = self.expression()?;
             self.consume(RIGHT_PAREN, "Expect ')' after expression.".to_string())?;
@@ -485,7 +744,8 @@ impl Parser {
                 return;
             }
             match self.peek().token_type {
-                CLASS | FUN | VAR | FOR | IF | WHILE | PRINT | RETURN => return,
+                CLASS | FUN | VAR | FOR | IF | WHILE | PRINT | RETURN | THROW | TRY | IMPORT
+                | FROM => return,
                 _ => (),
             }
             self.advance();
@@ -493,6 +753,7 @@ impl Parser {
     }
 
     fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
         self.consume(LEFT_PAREN, "Expect '(' after 'if'.".to_string())?;
         let condition = Box::new(self.expression()?);
         self.consume(RIGHT_PAREN, "Expect ')' after if condition.".to_string())?;
@@ -504,6 +765,7 @@ impl Parser {
             None
         };
         Ok(Stmt::If {
+            keyword,
             condition,
             then_branch,
             else_branch,
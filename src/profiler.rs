@@ -0,0 +1,334 @@
+use crate::execution_hook::{ExecutionHook, SourceLines};
+use crate::interpreter::Interpreter;
+use crate::lox_callable::LoxCallable;
+use crate::stmt::Stmt;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// A Lox function, or the top level of the script.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct FunctionKey {
+    name: String,
+    file: Option<Rc<Path>>,
+    /// The line it is declared on, 0 for the script.
+    line: i32,
+}
+
+impl Display for FunctionKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.line == 0 {
+            return write!(f, "{}", self.name);
+        }
+        match self.file.as_deref().and_then(Path::file_name) {
+            Some(file) => write!(
+                f,
+                "{} ({}:{})",
+                self.name,
+                file.to_string_lossy(),
+                self.line
+            ),
+            None => write!(f, "{} (line {})", self.name, self.line),
+        }
+    }
+}
+
+type LineKey = (Option<Rc<Path>>, i32);
+
+#[derive(Default)]
+struct Stats {
+    /// Calls of a function, or statements run on a line.
+    count: u64,
+    /// Time with the function or line active, counting recursive activity
+    /// once.
+    inclusive: Duration,
+    /// Time spent in the function or line itself, leaving out the calls or
+    /// nested statements it made.
+    exclusive: Duration,
+}
+
+/// A function or statement being timed.
+struct Active<K> {
+    key: K,
+    start: Duration,
+    /// Time spent in the functions or statements it is waiting on.
+    children: Duration,
+}
+
+/// An active call, with the `;`-separated names of the calls leading to it
+/// and how deep in the interpreter's call stack it is.
+struct Frame {
+    active: Active<FunctionKey>,
+    stack: String,
+    depth: usize,
+}
+
+/// The `--profile` instrumentation. It times every Lox function and every
+/// line with the interpreter's clock, then writes a report sorted by
+/// exclusive time to stderr and the time of each call stack in the folded
+/// format flame graph tools read. Natives count toward their caller.
+pub(crate) struct Profiler {
+    folded: PathBuf,
+    functions: HashMap<FunctionKey, Stats>,
+    lines: HashMap<LineKey, Stats>,
+    /// Exclusive time by call stack.
+    stacks: HashMap<String, Duration>,
+    /// The active calls, the script first.
+    frames: Vec<Frame>,
+    /// The active statements, innermost last.
+    statements: Vec<Active<LineKey>>,
+    /// How many active calls or statements each function or line has, so
+    /// recursion counts toward inclusive time once.
+    active_functions: HashMap<FunctionKey, usize>,
+    active_lines: HashMap<LineKey, usize>,
+}
+
+impl Profiler {
+    /// Profiles the script, writing the folded stacks to `folded` when it
+    /// finishes.
+    pub(crate) fn new(folded: PathBuf) -> Self {
+        Profiler {
+            folded,
+            functions: HashMap::new(),
+            lines: HashMap::new(),
+            stacks: HashMap::new(),
+            frames: Vec::new(),
+            statements: Vec::new(),
+            active_functions: HashMap::new(),
+            active_lines: HashMap::new(),
+        }
+    }
+
+    fn open_frame(&mut self, key: FunctionKey, start: Duration, depth: usize) {
+        let stack = match self.frames.last() {
+            Some(caller) => format!("{};{}", caller.stack, key),
+            None => key.to_string(),
+        };
+        *self.active_functions.entry(key.clone()).or_default() += 1;
+        self.frames.push(Frame {
+            active: Active {
+                key,
+                start,
+                children: Duration::ZERO,
+            },
+            stack,
+            depth,
+        });
+    }
+
+    fn close_frame(&mut self, now: Duration) {
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        let elapsed = now.saturating_sub(frame.active.start);
+        let exclusive = elapsed.saturating_sub(frame.active.children);
+        *self.stacks.entry(frame.stack).or_default() += exclusive;
+        if let Some(caller) = self.frames.last_mut() {
+            caller.active.children += elapsed;
+        }
+        let key = frame.active.key;
+        let outermost = release(&mut self.active_functions, &key);
+        record(
+            self.functions.entry(key).or_default(),
+            elapsed,
+            exclusive,
+            outermost,
+        );
+    }
+
+    /// The script's frame, opened by its first statement.
+    fn open_script(&mut self, interpreter: &Interpreter, now: Duration) {
+        if self.frames.is_empty() {
+            let key = FunctionKey {
+                name: "<script>".to_string(),
+                file: interpreter.shared_current_file(),
+                line: 0,
+            };
+            self.open_frame(key, now, 0);
+        }
+    }
+
+    fn report(&self) -> String {
+        let mut sources = SourceLines::default();
+        let total = self
+            .functions
+            .iter()
+            .find(|(key, _)| key.line == 0)
+            .map_or(Duration::ZERO, |(_, stats)| stats.inclusive);
+        let mut report = format!("Profile: {} ms in total\n", millis(total));
+
+        report.push_str("\nFunctions by exclusive time:\n");
+        report.push_str("   calls  inclusive ms  exclusive ms  function\n");
+        for (key, stats) in sorted(&self.functions) {
+            report.push_str(&row(stats, &key.to_string()));
+        }
+
+        report.push_str("\nLines by exclusive time:\n");
+        report.push_str("    runs  inclusive ms  exclusive ms  line\n");
+        for ((file, line), stats) in sorted(&self.lines) {
+            let location = match file.as_deref() {
+                Some(path) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    format!("{}:{}  {}", name, line, sources.get(path, *line))
+                }
+                None => format!("line {}", line),
+            };
+            report.push_str(&row(stats, &location));
+        }
+        report
+    }
+
+    fn write_folded(&self) -> std::io::Result<()> {
+        let mut stacks: Vec<_> = self
+            .stacks
+            .iter()
+            .filter(|(_, time)| time.as_micros() > 0)
+            .collect();
+        stacks.sort();
+        let folded: String = stacks
+            .into_iter()
+            .map(|(stack, time)| format!("{} {}\n", stack, time.as_micros()))
+            .collect();
+        std::fs::write(&self.folded, folded)
+    }
+}
+
+impl ExecutionHook for Profiler {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+    ) -> Result<(), Box<dyn Error>> {
+        // Its statements are timed instead.
+        if matches!(stmt, Stmt::Block { .. }) {
+            return Ok(());
+        }
+        let now = interpreter.clock().monotonic();
+        self.open_script(interpreter, now);
+        let key = (interpreter.shared_current_file(), stmt.line());
+        *self.active_lines.entry(key.clone()).or_default() += 1;
+        self.statements.push(Active {
+            key,
+            start: now,
+            children: Duration::ZERO,
+        });
+        Ok(())
+    }
+
+    fn after_statement(&mut self, interpreter: &mut Interpreter, stmt: &Stmt) {
+        if matches!(stmt, Stmt::Block { .. }) {
+            return;
+        }
+        let Some(statement) = self.statements.pop() else {
+            return;
+        };
+        let elapsed = interpreter
+            .clock()
+            .monotonic()
+            .saturating_sub(statement.start);
+        let exclusive = elapsed.saturating_sub(statement.children);
+        if let Some(outer) = self.statements.last_mut() {
+            outer.children += elapsed;
+        }
+        let outermost = release(&mut self.active_lines, &statement.key);
+        let stats = self.lines.entry(statement.key).or_default();
+        record(stats, elapsed, exclusive, outermost);
+    }
+
+    fn enter_function(
+        &mut self,
+        interpreter: &mut Interpreter,
+        callee: &LoxCallable,
+        _arguments: &[Value],
+    ) {
+        let LoxCallable::Function(function) = callee else {
+            return;
+        };
+        let now = interpreter.clock().monotonic();
+        let depth = interpreter.call_depth();
+        // A tail call takes over its caller's frame, which returns here.
+        if self.frames.last().is_some_and(|frame| frame.depth == depth) {
+            self.close_frame(now);
+        }
+        let key = FunctionKey {
+            name: function.declaration.name.lexeme.clone(),
+            file: function.file.clone(),
+            line: function.declaration.name.line,
+        };
+        self.open_frame(key, now, depth);
+    }
+
+    fn exit_function(
+        &mut self,
+        interpreter: &mut Interpreter,
+        callee: &LoxCallable,
+        _result: Result<&Value, &dyn Error>,
+    ) {
+        if let LoxCallable::Function(_) = callee {
+            self.close_frame(interpreter.clock().monotonic());
+        }
+    }
+
+    fn finish(&mut self, interpreter: &mut Interpreter) {
+        let now = interpreter.clock().monotonic();
+        while !self.frames.is_empty() {
+            self.close_frame(now);
+        }
+        eprint!("{}", self.report());
+        match self.write_folded() {
+            Ok(()) => eprintln!("\nFolded stacks written to {}.", self.folded.display()),
+            Err(error) => eprintln!("Could not write '{}': {}.", self.folded.display(), error),
+        }
+    }
+}
+
+/// Marks one activity of `key` over, returning whether it was the outermost.
+fn release<K: std::hash::Hash + Eq>(active: &mut HashMap<K, usize>, key: &K) -> bool {
+    match active.get_mut(key) {
+        Some(count) if *count > 1 => {
+            *count -= 1;
+            false
+        }
+        _ => {
+            active.remove(key);
+            true
+        }
+    }
+}
+
+fn record(stats: &mut Stats, elapsed: Duration, exclusive: Duration, outermost: bool) {
+    stats.count += 1;
+    stats.exclusive += exclusive;
+    if outermost {
+        stats.inclusive += elapsed;
+    }
+}
+
+/// Most exclusive time first, then most inclusive, then by key.
+fn sorted<K: Ord>(stats: &HashMap<K, Stats>) -> Vec<(&K, &Stats)> {
+    let mut rows: Vec<_> = stats.iter().collect();
+    rows.sort_by(|(a_key, a), (b_key, b)| {
+        (b.exclusive, b.inclusive)
+            .cmp(&(a.exclusive, a.inclusive))
+            .then_with(|| a_key.cmp(b_key))
+    });
+    rows
+}
+
+fn row(stats: &Stats, name: &str) -> String {
+    format!(
+        "{:>8}  {:>12}  {:>12}  {}\n",
+        stats.count,
+        millis(stats.inclusive),
+        millis(stats.exclusive),
+        name
+    )
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64() * 1000.0)
+}
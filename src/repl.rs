@@ -0,0 +1,214 @@
+use crate::ast_printer::AstPrinter;
+use crate::lox_callable::LoxCallable;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::value::Value;
+use crate::{Lox, LOX};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+const HELP: &str = "\
+Enter statements, or an expression to see its value. Commands:
+  :env           list the globals defined so far
+  :ast <expr>    show the syntax tree of an expression
+  :tokens <src>  show the tokens of some source
+  :load <file>   run a file in this session
+  :reset         forget all globals and modules
+  :time <entry>  run an entry and show how long it took
+  :help          show this message";
+
+/// Runs the interactive prompt until end of input. An entry that leaves a
+/// bracket or string open keeps reading lines until it is complete. A line
+/// starting with `:` is a command to the REPL itself.
+pub(crate) fn run() -> Result<(), std::io::Error> {
+    let mut editor = DefaultEditor::new().map_err(std::io::Error::other)?;
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing history file just means a first run.
+        let _ = editor.load_history(path);
+    }
+
+    let mut entry = String::new();
+    loop {
+        let prompt = if entry.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        match editor.readline(prompt) {
+            Ok(line) if entry.is_empty() && line.trim_start().starts_with(':') => {
+                let _ = editor.add_history_entry(line.trim());
+                meta_command(line.trim());
+            }
+            Ok(line) => {
+                entry.push_str(&line);
+                entry.push('\n');
+                if is_incomplete(&entry) {
+                    continue;
+                }
+                let source = std::mem::take(&mut entry);
+                if source.trim().is_empty() {
+                    continue;
+                }
+                let _ = editor.add_history_entry(source.trim_end());
+                run_entry(source);
+            }
+            // Ctrl-C abandons the entry being typed.
+            Err(ReadlineError::Interrupted) => entry.clear(),
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(std::io::Error::other(error)),
+        }
+        if let Some(code) = unsafe { LOX.exit_code } {
+            save_history(&mut editor, &history);
+            std::process::exit(code);
+        }
+        unsafe {
+            LOX.had_error = false;
+        }
+    }
+    save_history(&mut editor, &history);
+    Ok(())
+}
+
+/// Runs an entry, echoing the value of a final bare expression.
+fn run_entry(source: String) {
+    let tokens = Scanner::new(source).scan_tokens();
+    let mut parser = Parser::new_repl(tokens);
+    let statements = parser.parse();
+    if unsafe { LOX.had_error } {
+        return;
+    }
+    #[allow(static_mut_refs)]
+    let value = unsafe {
+        LOX.interpreter
+            .interpret_entry(statements, parser.ends_with_expression())
+    };
+    if let Some(value) = value {
+        println!("{}", value.repr());
+    }
+}
+
+fn meta_command(line: &str) {
+    let (command, argument) = match line.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (line, ""),
+    };
+    match (command, argument) {
+        (":help", _) => println!("{}", HELP),
+        (":env", _) => print_globals(),
+        (":ast", source) if !source.is_empty() => {
+            let tokens = Scanner::new(source.to_string()).scan_tokens();
+            if let Some(expr) = Parser::new(tokens).parse_expression() {
+                println!("{}", AstPrinter.print_expr(&expr));
+            }
+        }
+        (":tokens", source) if !source.is_empty() => {
+            for token in Scanner::new(source.to_string()).scan_tokens() {
+                match &token.literal {
+                    Some(literal) => println!(
+                        "{} {:?} {} {}",
+                        token.line, token.token_type, token.lexeme, literal
+                    ),
+                    None => println!("{} {:?} {}", token.line, token.token_type, token.lexeme),
+                }
+            }
+        }
+        (":load", path) if !path.is_empty() => match std::fs::read_to_string(path) {
+            Ok(source) => {
+                // Imports in the file are resolved against the file.
+                #[allow(static_mut_refs)]
+                let previous = unsafe {
+                    LOX.interpreter
+                        .set_current_file(Some(Path::new(path).into()))
+                };
+                Lox::run(source);
+                #[allow(static_mut_refs)]
+                unsafe {
+                    LOX.interpreter.set_current_file(previous);
+                }
+            }
+            Err(error) => eprintln!("Could not read '{}': {}.", path, error),
+        },
+        (":reset", _) => {
+            #[allow(static_mut_refs)]
+            unsafe {
+                LOX.interpreter.reset();
+            }
+        }
+        (":time", source) if !source.is_empty() => {
+            let start = Instant::now();
+            run_entry(source.to_string());
+            println!("took {:.3} ms", start.elapsed().as_secs_f64() * 1000.0);
+        }
+        (":ast" | ":tokens" | ":load" | ":time", _) => {
+            eprintln!("Usage: {} <{}>", command, usage_argument(command))
+        }
+        _ => eprintln!("Unknown command '{}'. Type :help for a list.", command),
+    }
+}
+
+fn usage_argument(command: &str) -> &str {
+    match command {
+        ":ast" => "expr",
+        ":tokens" => "src",
+        ":load" => "file",
+        _ => "entry",
+    }
+}
+
+/// Prints the globals in name order, leaving out the built-in natives.
+fn print_globals() {
+    #[allow(static_mut_refs)]
+    let globals = unsafe { LOX.interpreter.globals.borrow() };
+    let mut names: Vec<_> = globals
+        .values
+        .iter()
+        .filter(|(_, value)| {
+            !matches!(value, Value::Callable(callable)
+                if matches!(**callable, LoxCallable::NativeFunction(_)))
+        })
+        .collect();
+    names.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in names {
+        println!("{} = {}", name, value.repr());
+    }
+}
+
+/// `$RLOX_HISTORY`, or `.rlox_history` in the home directory.
+fn history_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("RLOX_HISTORY") {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".rlox_history"))
+}
+
+fn save_history(editor: &mut DefaultEditor, history: &Option<PathBuf>) {
+    if let Some(path) = history {
+        let _ = editor.save_history(path);
+    }
+}
+
+/// Whether `source` ends inside a string or with more brackets opened than
+/// closed. Extra closing brackets count as complete so the parser can report
+/// them.
+fn is_incomplete(source: &str) -> bool {
+    let mut depth = 0i32;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if !chars.by_ref().any(|c| c == '"') => return true,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0
+}
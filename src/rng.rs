@@ -0,0 +1,55 @@
+/// A small xorshift64* generator. It is not cryptographically secure, but it
+/// is fast and produces the same sequence for the same seed on every platform.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        let mut rng = Rng { state: 0 };
+        rng.seed(seed);
+        rng
+    }
+
+    /// A generator seeded from the system clock.
+    pub(crate) fn from_time() -> Self {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        Self::new(now.as_nanos() as u64)
+    }
+
+    pub(crate) fn seed(&mut self, seed: u64) {
+        // Scramble with splitmix64 so that nearby seeds give unrelated
+        // sequences; xorshift needs a nonzero state.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        self.state = if z == 0 { 1 } else { z };
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A float in `[0, 1)`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// An integer in `[0, n)`, without modulo bias. `n` must be nonzero.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
@@ -1,16 +1,48 @@
+use crate::lox_function::LoxFunction;
 use crate::{token::Token, value::Value};
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
 
 #[derive(Debug)]
 pub(crate) struct RuntimeError {
     pub(crate) token: Token,
     pub(crate) message: String,
+    /// Innermost frame first. Empty until the error leaves the call it was
+    /// raised in, see `Interpreter::attach_stack_trace`.
+    pub(crate) stack_trace: Vec<StackFrame>,
+    /// The value of a `throw` statement; `None` for errors raised by the
+    /// interpreter itself.
+    pub(crate) thrown: Option<Value>,
 }
 
 impl RuntimeError {
     pub(crate) fn new(token: Token, message: String) -> RuntimeError {
-        RuntimeError { token, message }
+        RuntimeError {
+            token,
+            message,
+            stack_trace: Vec::new(),
+            thrown: None,
+        }
+    }
+
+    pub(crate) fn thrown(keyword: Token, value: Value) -> RuntimeError {
+        RuntimeError {
+            thrown: Some(value.clone()),
+            ..RuntimeError::new(keyword, value.to_string())
+        }
+    }
+
+    /// The value a `catch` clause binds: whatever was thrown, or an error
+    /// value describing a built-in error.
+    pub(crate) fn into_value(self) -> Value {
+        match self.thrown {
+            Some(value) => value,
+            None => Value::Error(Rc::new(LoxError {
+                message: self.message,
+                line: self.token.line,
+            })),
+        }
     }
 }
 
@@ -22,6 +54,30 @@ impl Display for RuntimeError {
 
 impl Error for RuntimeError {}
 
+/// A built-in runtime error caught by a `catch` clause.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub(crate) struct LoxError {
+    pub(crate) message: String,
+    pub(crate) line: i32,
+}
+
+/// One line of a traceback: the function and the line it was executing, or
+/// no line for a native function.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StackFrame {
+    pub(crate) function: String,
+    pub(crate) line: Option<i32>,
+}
+
+impl Display for StackFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "at {} (line {})", self.function, line),
+            None => write!(f, "at {} (native)", self.function),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Return(pub(crate) Value);
 
@@ -38,3 +94,46 @@ impl Display for Return {
 }
 
 impl Error for Return {}
+
+/// Raised by the `exit` native. Unlike a `RuntimeError` it is not caught by
+/// `catch`, so it unwinds the whole program, running `finally` blocks.
+#[derive(Debug)]
+pub(crate) struct Exit(pub(crate) i32);
+
+impl Display for Exit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Exit: {}", self.0)
+    }
+}
+
+impl Error for Exit {}
+
+/// A `return f(...)` whose callee is a Lox function. Instead of calling it in
+/// place, the call is unwound to the enclosing `LoxCallable::call`, which runs
+/// it in the same native frame.
+#[derive(Debug)]
+pub(crate) struct TailCall {
+    pub(crate) function: LoxFunction,
+    pub(crate) arguments: Vec<Value>,
+}
+
+impl TailCall {
+    pub(crate) fn new(function: LoxFunction, arguments: Vec<Value>) -> Self {
+        TailCall {
+            function,
+            arguments,
+        }
+    }
+}
+
+impl Display for TailCall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TailCall: <fn {}>",
+            self.function.declaration.name.lexeme
+        )
+    }
+}
+
+impl Error for TailCall {}
@@ -5,24 +5,32 @@ use crate::token::Token;
 use crate::token_type::TokenType;
 use crate::token_type::TokenType::*;
 use crate::token::Literal;
+use crate::token::Comment;
 
 lazy_static! {
-    static ref KEYWORDS: HashMap<String, TokenType> = {
+    pub(crate) static ref KEYWORDS: HashMap<String, TokenType> = {
         [
             ("and", AND),
+            ("as", AS),
+            ("catch", CATCH),
             ("class", CLASS),
             ("else", ELSE),
             ("false", FALSE),
+            ("finally", FINALLY),
             ("for", FOR), 
+            ("from", FROM),
             ("fun", FUN),
             ("if", IF), 
+            ("import", IMPORT),
             ("nil", NIL),
             ("or", OR),
             ("print", PRINT),
             ("return", RETURN),
             ("super", SUPER),
             ("this", THIS), 
+            ("throw", THROW),
             ("true", TRUE), 
+            ("try", TRY),
             ("var", VAR), 
             ("while", WHILE),
         ]
@@ -33,31 +41,58 @@ lazy_static! {
 }
 
 pub(crate) struct Scanner {
-    source: String,
+    // 按字符而不是字节存储源码，这样非ASCII字符也能正确扫描
+    source: Vec<char>,
     tokens: Vec<Token>,
     start: i32,
     current: i32,
     line: i32,
+    // 当前行第一个字符的下标，用来计算列号
+    line_start: i32,
+    // 当前token开始处的行号和列号（列号从1开始，按字符计）
+    start_line: i32,
+    start_column: i32,
+    // 是否保留注释（格式化时需要），保留的注释挂在下一个token上
+    keep_comments: bool,
+    comments: Vec<Comment>,
 }
 
 impl Scanner {
     pub(crate) fn new(source: String) -> Scanner {
         Scanner {
-            source,
+            source: source.chars().collect(),
             tokens: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
+            keep_comments: false,
+            comments: Vec::new(),
+        }
+    }
+
+    // 保留注释的扫描模式
+    pub(crate) fn with_comments(source: String) -> Scanner {
+        Scanner {
+            keep_comments: true,
+            ..Scanner::new(source)
         }
     }
 
     pub(crate) fn scan_tokens(mut self) -> Vec<Token> {
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.current - self.line_start + 1;
             self.scan_token();
         }
 
-        self.tokens.push(Token::new(EOF, String::from(""), None, self.line));
+        let column = self.current - self.line_start + 1;
+        let mut eof = Token::new(EOF, String::from(""), None, self.line, column);
+        eof.comments = std::mem::take(&mut self.comments);
+        self.tokens.push(eof);
         self.tokens
     }
 
@@ -72,6 +107,8 @@ impl Scanner {
             ')' => self.add_token(RIGHT_PAREN),
             '{' => self.add_token(LEFT_BRACE),
             '}' => self.add_token(RIGHT_BRACE),
+            '[' => self.add_token(LEFT_BRACKET),
+            ']' => self.add_token(RIGHT_BRACKET),
             ',' => self.add_token(COMMA),
             '.' => self.add_token(DOT),
             '-' => self.add_token(MINUS),
@@ -99,12 +136,19 @@ impl Scanner {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    if self.keep_comments {
+                        let text = self.text(self.start as usize, self.current as usize);
+                        self.comments.push(Comment {
+                            text: text.trim_end().to_string(),
+                            line: self.start_line,
+                        });
+                    }
                 } else {
                     self.add_token(SLASH);
                 }
             }
             ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
+            '\n' => self.new_line(),
             '"' => self.string(),
             c if Scanner::is_digit(c) => self.number(),
             c if Scanner::is_alpha(c) => self.identifier(),
@@ -118,8 +162,8 @@ impl Scanner {
         while Scanner::is_alphanumeric(self.peek()) {
             self.advance();
         }
-        let text = &self.source[self.start as usize..self.current as usize];
-        let token_type = *KEYWORDS.get(text).unwrap_or(&IDENTIFIER);
+        let text = self.text(self.start as usize, self.current as usize);
+        let token_type = *KEYWORDS.get(&text).unwrap_or(&IDENTIFIER);
         match token_type {
             TRUE => self.add_token_with_literal(TRUE, Some(Literal::Bool(true))),
             FALSE => self.add_token_with_literal(FALSE, Some(Literal::Bool(false))),
@@ -129,10 +173,9 @@ impl Scanner {
     }
     fn string(&mut self) {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == '\n' {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
@@ -142,8 +185,8 @@ impl Scanner {
 
         self.advance();
 
-        let value = &self.source[self.start as usize + 1..self.current as usize - 1];
-        self.add_token_with_literal(STRING, Some(Literal::String(value.to_string())));
+        let value = self.text(self.start as usize + 1, self.current as usize - 1);
+        self.add_token_with_literal(STRING, Some(Literal::String(value)));
     }
 
     fn number(&mut self) {
@@ -158,7 +201,7 @@ impl Scanner {
             }
         }
 
-        let value = &self.source[self.start as usize..self.current as usize];
+        let value = self.text(self.start as usize, self.current as usize);
         self.add_token_with_literal(NUMBER, Some(Literal::Number(value.parse().unwrap())));
     }
 
@@ -167,7 +210,7 @@ impl Scanner {
         if self.is_at_end() {
             return false;
         }
-        if self.source.chars().nth(self.current as usize).unwrap() != expected {
+        if self.source[self.current as usize] != expected {
             return false;
         }
 
@@ -180,7 +223,7 @@ impl Scanner {
         if self.is_at_end() {
             return '\0';
         }
-        self.source.chars().nth(self.current as usize).unwrap()
+        self.source[self.current as usize]
     }
 
     // 预览下一个字符
@@ -188,7 +231,7 @@ impl Scanner {
         if self.current + 1 >= self.source.len() as i32 {
             return '\0';
         }
-        self.source.chars().nth((self.current + 1) as usize).unwrap()
+        self.source[(self.current + 1) as usize]
     }
 
     // 判断是否是字母
@@ -206,22 +249,34 @@ impl Scanner {
         c.is_ascii_digit()
     }
 
+    // 刚消费了一个换行符，进入下一行
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
     // 查看当前字符并将current指针后移一位
     fn advance(&mut self) -> char {
         self.current += 1;
-        self.source.chars().nth((self.current - 1) as usize).unwrap()
+        self.source[(self.current - 1) as usize]
+    }
+
+    // 取出源码中[start, end)范围的文本
+    fn text(&self, start: usize, end: usize) -> String {
+        self.source[start..end].iter().collect()
     }
 
     // 添加token
     fn add_token(&mut self, token_type: TokenType) {
-        let text = &self.source[self.start as usize..self.current as usize];
-        self.tokens.push(Token::new(token_type, text.to_string(), None, self.line));
+        self.add_token_with_literal(token_type, None);
     }
 
     // 添加带有字面量的token
     fn add_token_with_literal(&mut self, token_type: TokenType, literal: Option<Literal>) {
-        let text = &self.source[self.start as usize..self.current as usize];
-        self.tokens.push(Token::new(token_type, text.to_string(), literal, self.line));
+        let text = self.text(self.start as usize, self.current as usize);
+        let mut token = Token::new(token_type, text, literal, self.start_line, self.start_column);
+        token.comments = std::mem::take(&mut self.comments);
+        self.tokens.push(token);
     }
 }
 
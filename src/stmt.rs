@@ -1,28 +1,43 @@
 use crate::expr::Expr;
 use crate::token::Token;
+use std::rc::Rc;
 
 pub(crate) trait Visitor<R> {
     fn visit_expression_stmt(&mut self, expr: &Expr) -> R;
     fn visit_print_stmt(&mut self, expr: &Expr) -> R;
     fn visit_return_stmt(&mut self, keyword: &Token, value: &Expr) -> R;
     fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> R;
-    fn visit_block_stmt(&mut self, statements: &Vec<Stmt>) -> R;
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> R;
     fn visit_if_stmt(
         &mut self,
+        keyword: &Token,
         condition: &Expr,
         then_branch: &Stmt,
         else_branch: Option<&Stmt>,
     ) -> R;
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> R;
-    fn visit_function_stmt(&mut self, stmt: Box<LoxFunctionNode>) -> R;
+    fn visit_while_stmt(&mut self, keyword: &Token, condition: &Expr, body: &Stmt) -> R;
+    fn visit_function_stmt(&mut self, stmt: Rc<LoxFunctionNode>) -> R;
+    fn visit_throw_stmt(&mut self, keyword: &Token, value: &Expr) -> R;
+    fn visit_import_stmt(&mut self, keyword: &Token, path: &Token, alias: &Token) -> R;
+    fn visit_from_import_stmt(&mut self, keyword: &Token, path: &Token, names: &[Token]) -> R;
+    fn visit_try_stmt(
+        &mut self,
+        body: &[Stmt],
+        catch_clause: Option<&CatchClause>,
+        finally_body: Option<&[Stmt]>,
+    ) -> R;
+    fn visit_test_stmt(&mut self, keyword: &Token, name: &Token, body: &[Stmt]) -> R;
 }
 
 #[derive(Debug, Clone)]
 pub(crate) enum Stmt {
     Expression {
         expression: Box<Expr>,
+        /// The first token of the expression.
+        start: Token,
     },
     Print {
+        keyword: Token,
         expression: Box<Expr>,
     },
     Return {
@@ -34,40 +49,123 @@ pub(crate) enum Stmt {
         initializer: Option<Box<Expr>>,
     },
     Block {
+        /// The `{`, or the `for` of a loop with an initializer.
+        brace: Token,
         statements: Vec<Stmt>,
     },
     If {
+        keyword: Token,
         condition: Box<Expr>,
         then_branch: Box<Stmt>,
         else_branch: Option<Box<Stmt>>,
     },
     While {
+        /// The `while`, or the `for` of a loop parsed into this.
+        keyword: Token,
         condition: Box<Expr>,
         body: Box<Stmt>,
     },
     Function {
-        function: Box<LoxFunctionNode>,
+        function: Rc<LoxFunctionNode>,
         // name: Token,
         // params: Vec<Token>,
         // body: Vec<Stmt>,
     },
+    Throw {
+        keyword: Token,
+        value: Box<Expr>,
+    },
+    Import {
+        keyword: Token,
+        path: Token,
+        alias: Token,
+    },
+    FromImport {
+        keyword: Token,
+        path: Token,
+        names: Vec<Token>,
+    },
+    Try {
+        keyword: Token,
+        body: Vec<Stmt>,
+        catch_clause: Option<CatchClause>,
+        finally_body: Option<Vec<Stmt>>,
+    },
+    /// `test "name" { body }`, which only `rlox test` runs.
+    Test {
+        /// The `test`, an identifier anywhere else.
+        keyword: Token,
+        name: Token,
+        body: Vec<Stmt>,
+    },
 }
 
 impl Stmt {
+    /// The line the statement starts on.
+    pub(crate) fn line(&self) -> i32 {
+        self.token().line
+    }
+
+    /// The token the statement starts with, or its name for a function.
+    pub(crate) fn token(&self) -> &Token {
+        match self {
+            Stmt::Expression { start: token, .. }
+            | Stmt::Block { brace: token, .. }
+            | Stmt::Var { name: token, .. }
+            | Stmt::Print { keyword: token, .. }
+            | Stmt::Return { keyword: token, .. }
+            | Stmt::If { keyword: token, .. }
+            | Stmt::While { keyword: token, .. }
+            | Stmt::Throw { keyword: token, .. }
+            | Stmt::Import { keyword: token, .. }
+            | Stmt::FromImport { keyword: token, .. }
+            | Stmt::Try { keyword: token, .. }
+            | Stmt::Test { keyword: token, .. } => token,
+            Stmt::Function { function } => &function.name,
+        }
+    }
+
     pub(crate) fn accept<R>(&self, visitor: &mut impl Visitor<R>) -> R {
         match self {
-            Stmt::Print { expression } => visitor.visit_print_stmt(expression),
-            Stmt::Expression { expression } => visitor.visit_expression_stmt(expression),
+            Stmt::Print { expression, .. } => visitor.visit_print_stmt(expression),
+            Stmt::Expression { expression, .. } => visitor.visit_expression_stmt(expression),
             Stmt::Return { keyword, value } => visitor.visit_return_stmt(keyword, value),
             Stmt::Var { name, initializer } => visitor.visit_var_stmt(name, initializer.as_deref()),
-            Stmt::Block { statements } => visitor.visit_block_stmt(statements),
+            Stmt::Block { statements, .. } => visitor.visit_block_stmt(statements),
             Stmt::If {
+                keyword,
                 condition,
                 then_branch,
                 else_branch,
-            } => visitor.visit_if_stmt(condition, then_branch, else_branch.as_deref()),
-            Stmt::While { condition, body } => visitor.visit_while_stmt(condition, body),
+            } => visitor.visit_if_stmt(keyword, condition, then_branch, else_branch.as_deref()),
+            Stmt::While {
+                keyword,
+                condition,
+                body,
+            } => visitor.visit_while_stmt(keyword, condition, body),
             Stmt::Function { function } => visitor.visit_function_stmt(function.clone()),
+            Stmt::Throw { keyword, value } => visitor.visit_throw_stmt(keyword, value),
+            Stmt::Import {
+                keyword,
+                path,
+                alias,
+            } => visitor.visit_import_stmt(keyword, path, alias),
+            Stmt::FromImport {
+                keyword,
+                path,
+                names,
+            } => visitor.visit_from_import_stmt(keyword, path, names),
+            Stmt::Try {
+                body,
+                catch_clause,
+                finally_body,
+                ..
+            } => visitor.visit_try_stmt(body, catch_clause.as_ref(), finally_body.as_deref()),
+            Stmt::Test {
+                keyword,
+                name,
+                body,
+            } => visitor.visit_test_stmt(keyword, name, body),
         }
     }
 }
@@ -77,4 +175,11 @@ pub struct LoxFunctionNode {
     pub name: Token,
     pub params: Vec<Token>,
     pub body: Vec<Stmt>,
-}
\ No newline at end of file
+}
+
+/// `catch (name) { body }`
+#[derive(Debug, Clone)]
+pub struct CatchClause {
+    pub name: Token,
+    pub body: Vec<Stmt>,
+}
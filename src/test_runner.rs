@@ -0,0 +1,161 @@
+use crate::environment::Environment;
+use crate::formatter::collect_lox_files;
+use crate::interpreter::{self, Interpreter};
+use crate::parser::Parser;
+use crate::runtime_error::{Exit, RuntimeError};
+use crate::scanner::Scanner;
+use crate::stmt::Stmt;
+use crate::token::{Literal, Token};
+use crate::Lox;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// A test that did not pass, with the line it failed on when known.
+struct Failure {
+    file: PathBuf,
+    name: String,
+    line: Option<i32>,
+    message: String,
+}
+
+/// Runs `rlox test [paths...]`: every `test` declaration in the `.lox` files
+/// under the paths, or under the current directory if there are none. Each
+/// test gets a fresh interpreter that has run the rest of its file first, so
+/// one test cannot disturb another. The top level therefore runs once per
+/// test, printing its output and repeating its side effects each time; keep
+/// setup that should happen once out of test files. Exits with 1 if a test
+/// failed, 65 if a file had syntax errors and 74 if one could not be read.
+pub(crate) fn run_command(args: Vec<String>) -> i32 {
+    if let Some(option) = args.iter().find(|arg| arg.starts_with("--")) {
+        eprintln!("Unknown option '{}'.", option);
+        return 64;
+    }
+    // The tests need the same stack they would get from `rlox script`.
+    interpreter::spawn_with_stack(interpreter::DEFAULT_MAX_CALL_DEPTH, move || run_tests(args))
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+fn run_tests(args: Vec<String>) -> i32 {
+    let paths = if args.is_empty() {
+        vec![".".to_string()]
+    } else {
+        args
+    };
+    let mut files = Vec::new();
+    for path in &paths {
+        collect_lox_files(Path::new(path), &mut files);
+    }
+    // Files found under "." are named as they would be typed.
+    for file in &mut files {
+        if let Ok(relative) = file.strip_prefix(".") {
+            *file = relative.to_path_buf();
+        }
+    }
+
+    let mut code = 0;
+    let mut passed = 0;
+    let mut failures = Vec::new();
+    for file in &files {
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("Could not read '{}': {}.", file.display(), error);
+                code = code.max(74);
+                continue;
+            }
+        };
+        let (statements, errors) = Lox::collect_errors(|| {
+            let tokens = Scanner::new(source).scan_tokens();
+            Parser::new(tokens).parse()
+        });
+        if !errors.is_empty() {
+            eprintln!("{}:", file.display());
+            for error in errors {
+                let location = error.token.as_ref().map(Lox::location).unwrap_or_default();
+                eprintln!("[line {}] Error{}: {}", error.line, location, error.message);
+            }
+            code = code.max(65);
+            continue;
+        }
+
+        for stmt in &statements {
+            let Stmt::Test { name, body, .. } = stmt else {
+                continue;
+            };
+            let name = test_name(name);
+            match run_test(file, &statements, body) {
+                Ok(()) => {
+                    println!("ok      {}: {}", file.display(), name);
+                    passed += 1;
+                }
+                Err(error) => {
+                    println!("FAILED  {}: {}", file.display(), name);
+                    let (line, message) = describe(error);
+                    failures.push(Failure {
+                        file: file.clone(),
+                        name,
+                        line,
+                        message,
+                    });
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nFailures:");
+        for failure in &failures {
+            let location = match failure.line {
+                Some(line) => format!("{}:{}", failure.file.display(), line),
+                None => failure.file.display().to_string(),
+            };
+            println!("  {} in {}: {}", location, failure.name, failure.message);
+        }
+        code = code.max(1);
+    }
+    match passed + failures.len() {
+        0 => println!("No tests found."),
+        total => println!(
+            "\n{} {}: {} passed, {} failed.",
+            total,
+            if total == 1 { "test" } else { "tests" },
+            passed,
+            failures.len()
+        ),
+    }
+    code
+}
+
+/// Runs one test in a fresh interpreter, after the top level of its file.
+/// Functions close over the globals they were declared with, so sharing one
+/// run of the top level between tests would share its state too.
+fn run_test(file: &Path, statements: &[Stmt], body: &[Stmt]) -> Result<(), Box<dyn Error>> {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_current_file(Some(Rc::from(file)));
+    let globals = interpreter.globals.clone();
+    interpreter
+        .execute_block(statements, globals.clone())
+        .and_then(|()| interpreter.execute_block(body, Environment::new_enclosing(globals)))
+        .map_err(|error| interpreter.attach_stack_trace(error))
+}
+
+/// The line a test failed on, if it has one, and why.
+fn describe(error: Box<dyn Error>) -> (Option<i32>, String) {
+    if let Some(exit) = error.downcast_ref::<Exit>() {
+        return (None, format!("Exited with code {}.", exit.0));
+    }
+    match error.downcast::<RuntimeError>() {
+        Ok(error) => (Some(error.token.line), error.message),
+        Err(error) => (None, error.to_string()),
+    }
+}
+
+fn test_name(name: &Token) -> String {
+    match &name.literal {
+        Some(Literal::String(name)) => name.clone(),
+        _ => name.lexeme.clone(),
+    }
+}
@@ -7,15 +7,29 @@ pub(crate) struct Token{
     pub(crate) lexeme: String,
     pub(crate) literal: Option<Literal>,
     pub(crate) line: i32,
+    /// 1-based, counted in characters.
+    pub(crate) column: i32,
+    /// Comments between the previous token and this one, kept only by
+    /// `Scanner::with_comments`.
+    pub(crate) comments: Vec<Comment>,
+}
+
+/// A `//` comment, with the line it is on.
+#[derive(Debug,Clone,PartialEq)]
+pub(crate) struct Comment {
+    pub(crate) text: String,
+    pub(crate) line: i32,
 }
 
 impl Token {
-    pub(crate) fn new(token_type: TokenType, lexeme:String, literal: Option<Literal>, line: i32) -> Token {
+    pub(crate) fn new(token_type: TokenType, lexeme:String, literal: Option<Literal>, line: i32, column: i32) -> Token {
         Token {
             token_type,
             lexeme,
             literal,
             line,
+            column,
+            comments: Vec::new(),
         }
     }
 }
@@ -3,7 +3,7 @@
 #[allow(clippy::upper_case_acronyms)]
 pub(crate) enum TokenType{
     // Single-character tokens.
-    LEFT_PAREN, RIGHT_PAREN, LEFT_BRACE, RIGHT_BRACE,
+    LEFT_PAREN, RIGHT_PAREN, LEFT_BRACE, RIGHT_BRACE, LEFT_BRACKET, RIGHT_BRACKET,
     COMMA, DOT, MINUS, PLUS, SEMICOLON, SLASH, STAR,
 
     // One or two character tokens.
@@ -16,8 +16,8 @@ pub(crate) enum TokenType{
     IDENTIFIER, STRING, NUMBER,
 
     // Keywords.
-    AND, CLASS, ELSE, FALSE, FUN, FOR, IF, NIL, OR,
-    PRINT, RETURN, SUPER, THIS, TRUE, VAR, WHILE,
+    AND, AS, CATCH, CLASS, ELSE, FALSE, FINALLY, FUN, FOR, FROM, IF, IMPORT, NIL, OR,
+    PRINT, RETURN, SUPER, THIS, THROW, TRUE, TRY, VAR, WHILE,
 
     EOF
 }
\ No newline at end of file
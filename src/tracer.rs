@@ -0,0 +1,107 @@
+use crate::execution_hook::{ExecutionHook, SourceLines};
+use crate::interpreter::Interpreter;
+use crate::lox_callable::LoxCallable;
+use crate::stmt::Stmt;
+use crate::value::Value;
+use std::error::Error;
+use std::io::Write;
+
+/// The `--trace` log. Each statement is written with its line before it
+/// runs, and each call as it enters and leaves, indented by call depth:
+///
+/// ```text
+/// [line 5] print add(1, 2);
+/// -> add(1, 2)
+///   [line 2] return a + b;
+/// <- add returned 3
+/// ```
+pub(crate) struct Tracer {
+    output: Box<dyn Write>,
+    sources: SourceLines,
+    /// The callee of each active call, innermost last. A tail call takes
+    /// over the innermost one.
+    calls: Vec<String>,
+}
+
+impl Tracer {
+    pub(crate) fn new(output: Box<dyn Write>) -> Self {
+        Tracer {
+            output,
+            sources: SourceLines::default(),
+            calls: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, depth: usize, text: std::fmt::Arguments) {
+        // A trace that cannot be written is not worth stopping the script.
+        let _ = writeln!(self.output, "{:width$}{}", "", text, width = depth * 2);
+    }
+}
+
+impl ExecutionHook for Tracer {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+    ) -> Result<(), Box<dyn Error>> {
+        // Its statements are traced instead.
+        if matches!(stmt, Stmt::Block { .. }) {
+            return Ok(());
+        }
+        let line = stmt.line();
+        let text = match interpreter.current_file() {
+            Some(path) => self.sources.get(path, line).to_string(),
+            None => String::new(),
+        };
+        self.write(
+            interpreter.call_depth(),
+            format_args!("[line {}] {}", line, text),
+        );
+        Ok(())
+    }
+
+    fn enter_function(
+        &mut self,
+        interpreter: &mut Interpreter,
+        callee: &LoxCallable,
+        arguments: &[Value],
+    ) {
+        let name = callee_name(callee);
+        let arguments: Vec<String> = arguments.iter().map(Value::repr).collect();
+        let depth = interpreter.call_depth();
+        if depth == self.calls.len() {
+            self.write(
+                depth - 1,
+                format_args!("-> {}({}) (tail call)", name, arguments.join(", ")),
+            );
+            self.calls.pop();
+        } else {
+            self.write(
+                depth - 1,
+                format_args!("-> {}({})", name, arguments.join(", ")),
+            );
+        }
+        self.calls.push(name);
+    }
+
+    fn exit_function(
+        &mut self,
+        interpreter: &mut Interpreter,
+        _callee: &LoxCallable,
+        result: Result<&Value, &dyn Error>,
+    ) {
+        let name = self.calls.pop().unwrap_or_default();
+        let depth = interpreter.call_depth() - 1;
+        match result {
+            Ok(value) => self.write(depth, format_args!("<- {} returned {}", name, value.repr())),
+            Err(error) => self.write(depth, format_args!("<- {} failed: {}", name, error)),
+        }
+    }
+}
+
+fn callee_name(callee: &LoxCallable) -> String {
+    match callee {
+        LoxCallable::Function(function) => function.declaration.name.lexeme.clone(),
+        LoxCallable::NativeFunction(function) => function.name.clone(),
+    }
+}
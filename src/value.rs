@@ -1,24 +1,144 @@
-use std::{any::Any, fmt::Display, rc::Rc};
+use std::{cell::RefCell, collections::HashSet, fmt::Display, rc::Rc};
 
 use crate::lox_callable::LoxCallable;
+use crate::lox_map::LoxMap;
+use crate::lox_module::LoxModule;
+use crate::runtime_error::LoxError;
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, PartialOrd)]
 pub(crate) enum Value {
     Number(f64),
     Boolean(bool),
     String(String),
     Callable(Box<LoxCallable>),
+    Error(Rc<LoxError>),
+    Module(Rc<LoxModule>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<LoxMap>>),
     Nil,
 }
 
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, &mut Vec::new())
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other, &mut HashSet::new())
+    }
+}
+
+impl Value {
+    pub(crate) fn new_list(elements: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(elements)))
+    }
+
+    pub(crate) fn new_map(map: LoxMap) -> Value {
+        Value::Map(Rc::new(RefCell::new(map)))
+    }
+
+    /// Lists are equal when their elements are, maps when they have the same
+    /// keys with equal values in any order. `seen` holds the
+    /// pairs of lists and maps already compared or being compared further
+    /// up; meeting one again cannot make them unequal, so cyclic values
+    /// terminate.
+    fn equals(&self, other: &Value, seen: &mut HashSet<(usize, usize)>) -> bool {
+        match (self, other) {
+            (Value::List(a), Value::List(b)) => {
+                let pair = (Rc::as_ptr(a) as usize, Rc::as_ptr(b) as usize);
+                if Rc::ptr_eq(a, b) || !seen.insert(pair) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.equals(y, seen))
+            }
+            (Value::Map(a), Value::Map(b)) => {
+                let pair = (Rc::as_ptr(a) as usize, Rc::as_ptr(b) as usize);
+                if Rc::ptr_eq(a, b) || !seen.insert(pair) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(k, x)| b.get(k).is_some_and(|y| x.equals(y, seen)))
+            }
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Callable(a), Value::Callable(b)) => a == b,
+            (Value::Error(a), Value::Error(b)) => a == b,
+            (Value::Module(a), Value::Module(b)) => a == b,
+            (Value::Nil, Value::Nil) => true,
+            _ => false,
+        }
+    }
+
+    /// `open` holds the lists and maps being written further up, which are
+    /// shown as `[...]` or `{...}` so that cyclic values terminate.
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, open: &mut Vec<usize>) -> std::fmt::Result {
         match self {
             Value::Number(n) => write!(f, "{}", n),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::String(s) => write!(f, "{}", s),
             Value::Nil => write!(f, "nil"),
-            Value::Callable(c) => write!(f, "{:?}", c),
+            Value::Callable(c) => write!(f, "{}", c),
+            Value::Error(e) => write!(f, "{}", e.message),
+            Value::Module(m) => write!(f, "{}", m),
+            Value::List(list) => {
+                let id = Rc::as_ptr(list) as usize;
+                if open.contains(&id) {
+                    return write!(f, "[...]");
+                }
+                open.push(id);
+                write!(f, "[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.write_element(f, open)?;
+                }
+                open.pop();
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                let id = Rc::as_ptr(map) as usize;
+                if open.contains(&id) {
+                    return write!(f, "{{...}}");
+                }
+                open.push(id);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: ", key)?;
+                    value.write_element(f, open)?;
+                }
+                open.pop();
+                write!(f, "}}")
+            }
+        }
+    }
+
+    /// How the REPL echoes a value: like `print`, but with strings quoted.
+    pub(crate) fn repr(&self) -> String {
+        match self {
+            Value::String(s) => format!("{:?}", s),
+            value => value.to_string(),
+        }
+    }
+
+    /// Inside a list or map, strings are quoted.
+    fn write_element(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        open: &mut Vec<usize>,
+    ) -> std::fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{:?}", s),
+            element => element.write(f, open),
         }
     }
 }
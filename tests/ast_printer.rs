@@ -0,0 +1,63 @@
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+/// Each `tests/golden/ast/*.lox` must dump to the S-expressions in the `.ast`
+/// file next to it. Run with `UPDATE_GOLDEN=1` to rewrite the `.ast` files
+/// after an intended change.
+#[test]
+fn dump_ast_matches_golden_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/ast");
+    let mut scripts: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    scripts.sort();
+    assert!(!scripts.is_empty());
+
+    for script in scripts {
+        let output = Command::new(env!("CARGO_BIN_EXE_lox1"))
+            .arg("--dump-ast")
+            .arg(&script)
+            .output()
+            .unwrap();
+        assert_eq!(common::stderr(&output), "", "{}", script.display());
+        let golden = script.with_extension("ast");
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&golden, &output.stdout).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&golden).unwrap();
+        assert_eq!(common::stdout(&output), expected, "{}", script.display());
+    }
+}
+
+#[test]
+fn dump_ast_does_not_run_the_script() {
+    let output = common::run_with_args("print \"ran\";\n", &["--dump-ast"]);
+    assert_eq!(common::stdout(&output), "(print \"ran\")\n");
+}
+
+#[test]
+fn dump_ast_reports_syntax_errors() {
+    let output = common::run_with_args("print (1;\n", &["--dump-ast"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(common::stdout(&output), "");
+    assert_eq!(
+        common::stderr(&output),
+        "[line 1] Error  at ';': Expect ')' after expression.\n"
+    );
+}
+
+#[test]
+fn repl_ast_command_prints_s_expressions() {
+    let dir = common::fresh_dir();
+    let history = dir.join("history");
+    let output = common::run_repl(":ast -a.b[1] + f(2)\n", &history);
+    assert_eq!(
+        common::stdout(&output),
+        "(+ (- (index (. a b) 1)) (call f 2))\n"
+    );
+}
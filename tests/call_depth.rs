@@ -0,0 +1,60 @@
+mod common;
+
+const INFINITE_RECURSION: &str = r#"
+fun f(n) {
+  return 1 + f(n + 1);
+}
+print "before";
+f(0);
+print "after";
+"#;
+
+#[test]
+fn infinite_recursion_is_a_runtime_error() {
+    let output = common::run(INFINITE_RECURSION);
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(common::stdout(&output), "before\n");
+    assert_eq!(
+        common::stderr(&output),
+        "Stack overflow.\n[line 3]\n  at f (line 3)\n  ... repeated 4095 more times\n  at <script> (line 6)\n"
+    );
+}
+
+#[test]
+fn max_call_depth_is_configurable() {
+    let source = r#"
+fun depth(n) {
+  if (n <= 1) return 1;
+  return 1 + depth(n - 1);
+}
+print depth(10);
+print depth(11);
+"#;
+    let output = common::run_with_args(source, &["--max-call-depth", "10"]);
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(common::stdout(&output), "10\n");
+    assert!(common::stderr(&output).starts_with("Stack overflow.\n[line 4]\n"));
+}
+
+#[test]
+fn call_depth_beyond_any_stack_is_an_error() {
+    let output = common::run_with_args("print 1;\n", &["--max-call-depth", "100000000000000"]);
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(common::stdout(&output), "");
+    assert!(common::stderr(&output)
+        .starts_with("Could not allocate the stack for a call depth of 100000000000000: "));
+}
+
+#[test]
+fn recursion_inside_nested_expressions_is_a_runtime_error() {
+    let nested = 15;
+    let source = format!(
+        "fun f(n) {{\n  return {}f(n + 1){};\n}}\nprint \"before\";\nf(0);\n",
+        "(1 + ".repeat(nested),
+        ")".repeat(nested)
+    );
+    let output = common::run(&source);
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(common::stdout(&output), "before\n");
+    assert!(common::stderr(&output).starts_with("Stack overflow.\n[line 2]\n  at f (line 2)\n"));
+}
@@ -0,0 +1,147 @@
+#![allow(dead_code)]
+
+use std::ffi::OsStr;
+use std::io::{ErrorKind, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static SCRIPT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A path in a temporary directory that belongs to one test alone. The
+/// directory is removed when this is dropped.
+pub struct TempPath {
+    path: PathBuf,
+    dir: PathBuf,
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<OsStr> for TempPath {
+    fn as_ref(&self) -> &OsStr {
+        self.path.as_os_str()
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Creates an empty directory no other test or earlier run is using.
+pub fn fresh_dir() -> TempPath {
+    loop {
+        let id = SCRIPT_ID.fetch_add(1, Ordering::SeqCst);
+        let dir = std::env::temp_dir().join(format!("rlox-tests-{}-{}", std::process::id(), id));
+        match std::fs::create_dir(&dir) {
+            Ok(()) => {
+                return TempPath {
+                    path: dir.clone(),
+                    dir,
+                }
+            }
+            // Left behind by an earlier run with the same process id.
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+            Err(error) => panic!("Could not create '{}': {}", dir.display(), error),
+        }
+    }
+}
+
+/// Writes `source` to a script file in a fresh directory and returns its
+/// path.
+pub fn write_script(source: &str) -> TempPath {
+    let mut script = fresh_dir();
+    script.path.push("script.lox");
+    std::fs::write(&script, source).unwrap();
+    script
+}
+
+/// Writes each `(relative path, contents)` pair under a fresh directory and
+/// returns the directory.
+pub fn write_tree(files: &[(&str, &str)]) -> TempPath {
+    let dir = fresh_dir();
+    for (name, contents) in files {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+/// Runs the script at `path` with the given extra command-line arguments.
+pub fn run_file(path: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap()
+}
+
+/// Runs `source` as a script file with the given extra command-line arguments.
+pub fn run_with_args(source: &str, args: &[&str]) -> Output {
+    run_file(&write_script(source), args)
+}
+
+/// Runs `source` as a script file with `input` as its standard input.
+pub fn run_with_input(source: &str, args: &[&str], input: &str) -> Output {
+    let script = write_script(source);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .args(args)
+        .arg(&script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Runs the interactive prompt on `input`, keeping history in `history`.
+pub fn run_repl(input: &str, history: &Path) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .env("RLOX_HISTORY", history)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+pub fn run(source: &str) -> Output {
+    run_with_args(source, &[])
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
@@ -0,0 +1,86 @@
+mod common;
+
+const SCRIPT: &str = "fun sign(n) {
+  if (n < 0) {
+    return -1;
+  }
+  if (n == 0 or n != n) {
+    return 0;
+  }
+  return 1;
+}
+var i = 0;
+while (i < 2) {
+  print sign(i);
+  i = i + 1;
+}
+";
+
+#[test]
+fn writes_lcov_and_a_summary() {
+    let script = common::write_script(SCRIPT);
+    let lcov = script.with_file_name("coverage.info");
+    let output = common::run_file(&script, &["--coverage-file", lcov.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(common::stdout(&output), "0\n1\n");
+    assert_eq!(
+        common::stderr(&output),
+        format!(
+            "\
+Coverage:
+       lines         branches  file
+   9/10  90.0%      7/8  87.5%  {}
+
+LCOV written to {}.
+",
+            script.display(),
+            lcov.display()
+        )
+    );
+    assert_eq!(
+        std::fs::read_to_string(lcov).unwrap(),
+        format!(
+            "\
+TN:
+SF:{}
+DA:1,1
+DA:2,2
+DA:3,0
+DA:5,2
+DA:6,1
+DA:8,1
+DA:10,1
+DA:11,1
+DA:12,2
+DA:13,2
+LF:10
+LH:9
+BRDA:2,0,0,0
+BRDA:2,0,1,2
+BRDA:5,0,0,1
+BRDA:5,0,1,1
+BRDA:5,1,0,1
+BRDA:5,1,1,1
+BRDA:11,0,0,2
+BRDA:11,0,1,1
+BRF:8
+BRH:7
+end_of_record
+",
+            script.display()
+        )
+    );
+}
+
+#[test]
+fn marks_branches_never_reached() {
+    let script =
+        common::write_script("fun never(x) {\n  if (x) print x;\n}\nprint true or false;\n");
+    let lcov = script.with_file_name("coverage.info");
+    let output = common::run_file(&script, &["--coverage-file", lcov.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let lcov = std::fs::read_to_string(lcov).unwrap();
+    assert!(lcov.contains("DA:2,0\nDA:4,1\nLF:3\nLH:2\n"));
+    assert!(lcov.contains("BRDA:2,0,0,-\nBRDA:2,0,1,-\nBRDA:4,0,0,1\nBRDA:4,0,1,0\n"));
+    assert!(lcov.contains("BRF:4\nBRH:1\n"));
+}
@@ -0,0 +1,279 @@
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+/// A scripted client, sending one request at a time like an editor would.
+struct Client {
+    child: Child,
+    output: BufReader<ChildStdout>,
+    seq: u32,
+    /// The events received so far, in order.
+    events: Vec<String>,
+    /// The script being debugged, removed when the session ends.
+    script: Option<common::TempPath>,
+}
+
+impl Client {
+    fn new() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_lox1"))
+            .arg("dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let output = BufReader::new(child.stdout.take().unwrap());
+        Client {
+            child,
+            output,
+            seq: 0,
+            events: Vec::new(),
+            script: None,
+        }
+    }
+
+    /// Starts a session debugging `source`, stopping on its first line
+    /// if `stop_on_entry` and at the given breakpoints.
+    fn launch(source: &str, stop_on_entry: bool, breakpoints: &[u32]) -> Self {
+        let script = common::write_script(source);
+        let path = script.to_str().unwrap().replace('\\', "\\\\");
+        let mut client = Client::new();
+        client.script = Some(script);
+        client.request("initialize", r#"{"adapterID":"rlox"}"#);
+        client.wait_for("initialized");
+        client.request(
+            "launch",
+            &format!(
+                r#"{{"program":"{}","stopOnEntry":{}}}"#,
+                path, stop_on_entry
+            ),
+        );
+        let lines: Vec<String> = breakpoints
+            .iter()
+            .map(|line| format!(r#"{{"line":{}}}"#, line))
+            .collect();
+        client.request(
+            "setBreakpoints",
+            &format!(
+                r#"{{"source":{{"path":"{}"}},"breakpoints":[{}]}}"#,
+                path,
+                lines.join(",")
+            ),
+        );
+        client.request("configurationDone", "{}");
+        client
+    }
+
+    fn read(&mut self) -> String {
+        let mut header = String::new();
+        self.output.read_line(&mut header).unwrap();
+        let length: usize = header
+            .trim_end()
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        self.output.read_line(&mut header).unwrap();
+        let mut body = vec![0; length];
+        self.output.read_exact(&mut body).unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    /// Sends a request and returns the response to it.
+    fn request(&mut self, command: &str, arguments: &str) -> String {
+        self.seq += 1;
+        let message = format!(
+            r#"{{"seq":{},"type":"request","command":"{}","arguments":{}}}"#,
+            self.seq, command, arguments
+        );
+        let stdin = self.child.stdin.as_mut().unwrap();
+        write!(
+            stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            message.len(),
+            message
+        )
+        .unwrap();
+        stdin.flush().unwrap();
+        let request_seq = format!(r#""request_seq":{},"#, self.seq);
+        loop {
+            let message = self.read();
+            if message.contains(&request_seq) {
+                return message;
+            }
+            self.events.push(message);
+        }
+    }
+
+    /// Reads until the named event arrives and returns it.
+    fn wait_for(&mut self, event: &str) -> String {
+        let event = format!(r#""event":"{}""#, event);
+        if let Some(i) = self
+            .events
+            .iter()
+            .position(|message| message.contains(&event))
+        {
+            return self.events.remove(i);
+        }
+        loop {
+            let message = self.read();
+            if message.contains(&event) {
+                return message;
+            }
+            self.events.push(message);
+        }
+    }
+
+    fn finish(mut self) -> Option<i32> {
+        self.request("disconnect", "{}");
+        drop(self.child.stdin.take());
+        self.child.wait().unwrap().code()
+    }
+}
+
+const SCRIPT: &str = r#"var names = ["ada", "bob"];
+var epoch = date(0);
+fun greet(name) {
+  var greeting = "hi " + name;
+  print greeting;
+  return greeting;
+}
+fun greetAll() {
+  for (var i = 0; i < len(names); i = i + 1) {
+    var done = greet(names[i]);
+  }
+}
+greetAll();
+"#;
+
+#[test]
+fn stops_at_breakpoints_and_shows_the_stack_and_variables() {
+    let mut client = Client::launch(SCRIPT, false, &[4]);
+    assert!(client
+        .wait_for("stopped")
+        .contains(r#""reason":"breakpoint","threadId":1"#));
+
+    assert!(client
+        .request("threads", "{}")
+        .contains(r#""threads":[{"id":1,"name":"main"}]"#));
+    let trace = client.request("stackTrace", r#"{"threadId":1}"#);
+    assert!(trace
+        .contains(r#"{"id":1,"name":"greet","line":4,"column":1,"source":{"name":"script.lox""#));
+    assert!(trace.contains(r#"{"id":2,"name":"greetAll","line":10,"#));
+    assert!(trace.contains(r#"{"id":3,"name":"<script>","line":13,"#));
+    assert!(trace.contains(r#""totalFrames":3"#));
+
+    let scopes = client.request("scopes", r#"{"frameId":1}"#);
+    assert!(scopes.contains(r#"{"name":"Locals","variablesReference":1,"expensive":false}"#));
+    assert!(scopes.contains(r#"{"name":"Globals","variablesReference":2,"expensive":false}"#));
+    assert!(client
+        .request("variables", r#"{"variablesReference":1}"#)
+        .contains(r#""variables":[{"name":"name","value":"\"ada\"","variablesReference":0}]"#));
+    let globals = client.request("variables", r#"{"variablesReference":2}"#);
+    assert!(globals.contains(r#"{"name":"epoch","value":"{\"year\": 1970, \"month\": 1, "#));
+    assert!(
+        globals.contains(r#"{"name":"names","value":"[\"ada\", \"bob\"]","variablesReference":5}"#)
+    );
+    assert!(client
+        .request("variables", r#"{"variablesReference":4}"#)
+        .contains(r#"[{"name":"\"year\"","value":"1970","variablesReference":0},"#));
+    assert!(client
+        .request("variables", r#"{"variablesReference":5}"#)
+        .contains(r#"[{"name":"0","value":"\"ada\"","variablesReference":0},{"name":"1","value":"\"bob\"","variablesReference":0}]"#));
+
+    client.request("continue", r#"{"threadId":1}"#);
+    client.wait_for("stopped");
+    assert!(client
+        .request("evaluate", r#"{"expression":"name","frameId":1}"#)
+        .contains(r#""result":"\"bob\"""#));
+    client.request(
+        "setBreakpoints",
+        r#"{"source":{"path":"elsewhere.lox"},"breakpoints":[]}"#,
+    );
+    client.request("continue", r#"{"threadId":1}"#);
+    assert!(client.wait_for("exited").contains(r#""exitCode":0"#));
+    client.wait_for("terminated");
+    let output: Vec<&String> = client
+        .events
+        .iter()
+        .filter(|event| event.contains(r#""event":"output""#))
+        .collect();
+    assert_eq!(output.len(), 2);
+    assert!(output[0].contains(r#""category":"stdout","output":"hi ada\n""#));
+    assert!(output[1].contains(r#""output":"hi bob\n""#));
+    assert_eq!(client.finish(), Some(0));
+}
+
+#[test]
+fn steps_in_over_and_out_of_calls() {
+    let mut client = Client::launch(SCRIPT, true, &[]);
+    assert!(client.wait_for("stopped").contains(r#""reason":"entry""#));
+    let mut step = |command: &str| {
+        client.request(command, r#"{"threadId":1}"#);
+        assert!(client.wait_for("stopped").contains(r#""reason":"step""#));
+        let trace = client.request("stackTrace", r#"{"threadId":1,"levels":1}"#);
+        let frame = trace.split(r#""stackFrames":[{"#).nth(1).unwrap();
+        frame.split(r#","column""#).next().unwrap().to_string()
+    };
+    assert_eq!(step("next"), r#""id":1,"name":"<script>","line":2"#);
+    assert_eq!(step("next"), r#""id":1,"name":"<script>","line":3"#);
+    assert_eq!(step("next"), r#""id":1,"name":"<script>","line":8"#);
+    assert_eq!(step("next"), r#""id":1,"name":"<script>","line":13"#);
+    assert_eq!(step("stepIn"), r#""id":1,"name":"greetAll","line":9"#);
+    assert_eq!(step("stepIn"), r#""id":1,"name":"greetAll","line":10"#);
+    assert_eq!(step("stepIn"), r#""id":1,"name":"greet","line":4"#);
+    assert_eq!(step("stepOut"), r#""id":1,"name":"greetAll","line":9"#);
+    assert_eq!(step("next"), r#""id":1,"name":"greetAll","line":10"#);
+    assert_eq!(step("next"), r#""id":1,"name":"greetAll","line":9"#);
+}
+
+#[test]
+fn evaluates_in_the_selected_frame() {
+    let mut client = Client::launch(SCRIPT, false, &[5]);
+    client.wait_for("stopped");
+    let mut evaluate = |expression: &str, frame: u32| {
+        client.request(
+            "evaluate",
+            &format!(r#"{{"expression":"{}","frameId":{}}}"#, expression, frame),
+        )
+    };
+    assert!(evaluate("greeting", 1)
+        .contains(r#""body":{"result":"\"hi ada\"","variablesReference":0}"#));
+    assert!(evaluate("i", 2).contains(r#""result":"0""#));
+    assert!(evaluate("greeting = \\\"changed\\\"", 1).contains(r#""success":true"#));
+    let error = evaluate("greeting", 2);
+    assert!(error.contains(r#""success":false"#));
+    assert!(error.contains(r#""message":"Undefined variable 'greeting'.""#));
+    assert!(evaluate("1 +", 1).contains(r#""message":"Expect expression.""#));
+    assert!(evaluate("names", 3).contains(r#""variablesReference":1"#));
+    assert!(evaluate("names", 4).contains(r#""message":"Unknown frameId 4.""#));
+
+    client.request(
+        "setBreakpoints",
+        &format!(r#"{{"source":{{"path":"{}"}},"breakpoints":[]}}"#, "x"),
+    );
+    client.request("continue", r#"{"threadId":1}"#);
+    client.wait_for("stopped");
+    client.request("continue", r#"{"threadId":1}"#);
+    client.wait_for("terminated");
+    assert!(client
+        .events
+        .iter()
+        .any(|event| event.contains(r#""output":"changed\n""#)));
+    assert_eq!(client.finish(), Some(0));
+}
+
+#[test]
+fn reports_bad_requests() {
+    let mut client = Client::new();
+    let response = client.request("launch", r#"{"program":"/no/such/file.lox"}"#);
+    assert!(response.contains(r#""success":false"#));
+    assert!(response.contains(r#""message":"Could not read '/no/such/file.lox': "#));
+    assert!(client
+        .request("stackTrace", r#"{"threadId":1}"#)
+        .contains(r#""message":"The program is not stopped.""#));
+    assert!(client
+        .request("pause", r#"{"threadId":1}"#)
+        .contains(r#""message":"Unknown request 'pause'.""#));
+    assert_eq!(client.finish(), Some(0));
+}
@@ -0,0 +1,114 @@
+mod common;
+
+const SCRIPT: &str = "var total = 0;
+fun add(a, b) {
+  var sum = a + b;
+  return sum;
+}
+fun twice(x) {
+  var result = add(x, x);
+  return result;
+}
+total = twice(3);
+print total;
+";
+
+fn debug(input: &str) -> String {
+    let output = common::run_with_input(SCRIPT, &["--debug"], input);
+    assert_eq!(common::stderr(&output), "");
+    common::stdout(&output)
+}
+
+#[test]
+fn stops_at_breakpoints_and_shows_the_call_stack() {
+    assert_eq!(
+        debug("break 3\nbreakpoints\ncontinue\nbacktrace\nframe 1\nprint x * 10\ncontinue\n"),
+        "\
+Stopped at line 1 in <script>: var total = 0;
+Breakpoint set at line 3.
+Breakpoints at lines 3.
+Stopped at line 3 in add: var sum = a + b;
+*#0 at add (line 3)
+ #1 at twice (line 7)
+ #2 at <script> (line 10)
+#1 at twice (line 7)
+30
+6
+"
+    );
+}
+
+#[test]
+fn steps_in_over_and_out_of_calls() {
+    assert_eq!(
+        debug("next\nnext\nstep\nstep\nstep\nfinish\nnext\nnext\n"),
+        "\
+Stopped at line 1 in <script>: var total = 0;
+Stopped at line 2 in <script>: fun add(a, b) {
+Stopped at line 6 in <script>: fun twice(x) {
+Stopped at line 10 in <script>: total = twice(3);
+Stopped at line 7 in twice: var result = add(x, x);
+Stopped at line 3 in add: var sum = a + b;
+Stopped at line 8 in twice: return result;
+Stopped at line 11 in <script>: print total;
+6
+"
+    );
+}
+
+#[test]
+fn inspects_and_modifies_variables() {
+    assert_eq!(
+        debug("break 4\nc\nlocals\nset sum = 100\nset total = -1\nprint total\nc\n"),
+        "\
+Stopped at line 1 in <script>: var total = 0;
+Breakpoint set at line 4.
+Stopped at line 4 in add: return sum;
+locals:
+  a = 3
+  b = 3
+  sum = 6
+globals:
+  E = 2.718281828459045
+  INF = inf
+  NAN = NaN
+  PI = 3.141592653589793
+  add = <fn add>
+  args = []
+  total = 0
+  twice = <fn twice>
+100
+-1
+-1
+100
+"
+    );
+}
+
+#[test]
+fn reports_bad_commands_and_quits() {
+    let output = common::run_with_input(
+        SCRIPT,
+        &["--debug"],
+        "bogus\nbreak\nprint (\nset 1 + 2\nquit\n",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        common::stdout(&output),
+        "\
+Stopped at line 1 in <script>: var total = 0;
+Unknown command 'bogus'. Type help for a list.
+Usage: break <line>
+Error: Expect expression.
+Usage: set <name> = <expr>
+"
+    );
+}
+
+#[test]
+fn runs_on_when_the_commands_run_out() {
+    assert_eq!(
+        debug(""),
+        "Stopped at line 1 in <script>: var total = 0;\n6\n"
+    );
+}
@@ -0,0 +1,105 @@
+mod common;
+
+fn run_ok(source: &str) -> String {
+    let output = common::run(source);
+    assert_eq!(common::stderr(&output), "");
+    common::stdout(&output)
+}
+
+#[test]
+fn thrown_value_is_caught() {
+    let stdout = run_ok(
+        r#"
+try {
+  throw "boom";
+  print "not reached";
+} catch (e) {
+  print "caught " + e;
+}
+"#,
+    );
+    assert_eq!(stdout, "caught boom\n");
+}
+
+#[test]
+fn runtime_errors_are_caught_as_error_values() {
+    let stdout = run_ok(
+        r#"
+try {
+  print -"x";
+} catch (e) {
+  print e.message;
+  print e.line;
+}
+fun recurse() { return 1 + recurse(); }
+try { recurse(); } catch (e) { print e.message; }
+try { print nope; } catch (e) { print e.message; }
+"#,
+    );
+    assert_eq!(
+        stdout,
+        "Operand must be a number.\n3\nStack overflow.\nUndefined variable 'nope'.\n"
+    );
+}
+
+#[test]
+fn finally_runs_on_normal_exit_return_and_throw() {
+    let stdout = run_ok(
+        r#"
+try { print "body"; } finally { print "finally 1"; }
+
+fun f() {
+  try { return "returned"; } finally { print "finally 2"; }
+}
+print f();
+
+try {
+  try { throw "inner"; } finally { print "finally 3"; }
+} catch (e) {
+  print "outer caught " + e;
+}
+"#,
+    );
+    assert_eq!(
+        stdout,
+        "body\nfinally 1\nfinally 2\nreturned\nfinally 3\nouter caught inner\n"
+    );
+}
+
+#[test]
+fn return_call_inside_try_is_still_guarded() {
+    let stdout = run_ok(
+        r#"
+fun fail() { throw "failed"; }
+fun guarded() {
+  try {
+    return fail();
+  } catch (e) {
+    return "caught " + e;
+  }
+}
+print guarded();
+"#,
+    );
+    assert_eq!(stdout, "caught failed\n");
+}
+
+#[test]
+fn uncaught_throw_is_a_runtime_error() {
+    let output = common::run("throw \"oops\";\n");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(
+        common::stderr(&output),
+        "oops\n[line 1]\n  at <script> (line 1)\n"
+    );
+}
+
+#[test]
+fn try_needs_catch_or_finally() {
+    let output = common::run("try { print 1; }\n");
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        common::stderr(&output),
+        "[line 2] Error  at end: Expect 'catch' or 'finally' after try block.\n"
+    );
+}
@@ -0,0 +1,229 @@
+mod common;
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn fmt_stdin(source: &str, args: &[&str]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .arg("fmt")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn fmt_args(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .arg("fmt")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+const MESSY: &str = r#"// Header
+
+import   "lib.lox" as lib ;
+var   x=1; // trailing
+
+
+fun add(a,b){
+  // inside
+  return a+b;}
+fun early(n){if(n)return;return nil;}
+for(var i=0;i<3;i=i+1)print i;
+for(;;){print "x";}
+if(x>1){print "big";}else if(x){print "small";}else print "zero";
+if (x) print 1; else print 2;
+try{throw "e";}catch(e){print e.message;}finally{print "done";}
+print someFunctionWithLongName(argumentNumberOne, argumentNumberTwo, [1, 2, 3], "str");
+l[0]=-l[1]*(2+3);
+print 1 + // inside an expression
+  2;
+{
+}
+// end of file
+"#;
+
+const FORMATTED: &str = r#"// Header
+
+import "lib.lox" as lib;
+var x = 1; // trailing
+
+fun add(a, b) {
+  // inside
+  return a + b;
+}
+fun early(n) {
+  if (n) return;
+  return nil;
+}
+for (var i = 0; i < 3; i = i + 1) print i;
+for (;;) {
+  print "x";
+}
+if (x > 1) {
+  print "big";
+} else if (x) {
+  print "small";
+} else print "zero";
+if (x) print 1;
+else print 2;
+try {
+  throw "e";
+} catch (e) {
+  print e.message;
+} finally {
+  print "done";
+}
+print someFunctionWithLongName(
+  argumentNumberOne,
+  argumentNumberTwo,
+  [1, 2, 3],
+  "str"
+);
+l[0] = -l[1] * (2 + 3);
+print 1 + 2; // inside an expression
+{}
+// end of file
+"#;
+
+#[test]
+fn formats_canonically_and_keeps_comments() {
+    let output = fmt_stdin(MESSY, &[]);
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), FORMATTED);
+
+    // Formatting is idempotent.
+    let output = fmt_stdin(FORMATTED, &[]);
+    assert_eq!(common::stdout(&output), FORMATTED);
+}
+
+const COMMENTED: &str = r#"if (x) { print x; } // after if
+else { print y; // in else
+}
+if (x) // why
+  print x;
+if (x) // why
+{ print x; }
+try { f(); }
+// before catch
+catch (e) { print e; }
+print foo(1, // one
+  // before two
+  2 // two
+);
+var l = [1, [2, // inner
+  3], 4];
+"#;
+
+const COMMENTED_FORMATTED: &str = r#"if (x) {
+  print x;
+} // after if
+else {
+  print y; // in else
+}
+if (x) // why
+  print x;
+if (x) { // why
+  print x;
+}
+try {
+  f();
+}
+// before catch
+catch (e) {
+  print e;
+}
+print foo(
+  1, // one
+  // before two
+  2 // two
+);
+var l = [
+  1,
+  [
+    2, // inner
+    3
+  ],
+  4
+];
+"#;
+
+#[test]
+fn keeps_comments_before_else_and_inside_brackets_in_place() {
+    let output = fmt_stdin(COMMENTED, &[]);
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), COMMENTED_FORMATTED);
+
+    let output = fmt_stdin(COMMENTED_FORMATTED, &[]);
+    assert_eq!(common::stdout(&output), COMMENTED_FORMATTED);
+}
+
+#[test]
+fn check_lists_unformatted_files_without_changing_them() {
+    let dir = common::write_tree(&[
+        ("messy.lox", MESSY),
+        ("tidy.lox", FORMATTED),
+        ("nested/also_messy.lox", "print 1+2;\n"),
+        ("notes.txt", "not lox"),
+    ]);
+    let output = fmt_args(&["--check", dir.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        common::stdout(&output),
+        format!(
+            "{}\n{}\n",
+            dir.join("messy.lox").display(),
+            dir.join("nested/also_messy.lox").display()
+        )
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("messy.lox")).unwrap(),
+        MESSY
+    );
+
+    let output = fmt_stdin(FORMATTED, &["--check"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn rewrites_files_in_place() {
+    let dir = common::write_tree(&[("messy.lox", MESSY), ("tidy.lox", FORMATTED)]);
+    let output = fmt_args(&[
+        dir.join("messy.lox").to_str().unwrap(),
+        dir.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(common::stdout(&output), "");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("messy.lox")).unwrap(),
+        FORMATTED
+    );
+
+    let output = fmt_args(&["--check", dir.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn syntax_errors_are_reported_and_left_alone() {
+    let dir = common::write_tree(&[("broken.lox", "print (;\n")]);
+    let output = fmt_args(&[dir.join("broken.lox").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        common::stderr(&output),
+        "[line 1] Error  at ';': Expect expression.\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("broken.lox")).unwrap(),
+        "print (;\n"
+    );
+}
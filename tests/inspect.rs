@@ -0,0 +1,104 @@
+mod common;
+
+#[test]
+fn tokens_include_literals_and_columns() {
+    let output = common::run_with_args("var s = \"é\";\n  print s + 1.5;\n", &["--tokens"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        common::stdout(&output),
+        r#"[
+  {"type":"VAR","lexeme":"var","line":1,"column":1},
+  {"type":"IDENTIFIER","lexeme":"s","line":1,"column":5},
+  {"type":"EQUAL","lexeme":"=","line":1,"column":7},
+  {"type":"STRING","lexeme":"\"é\"","literal":"é","line":1,"column":9},
+  {"type":"SEMICOLON","lexeme":";","line":1,"column":12},
+  {"type":"PRINT","lexeme":"print","line":2,"column":3},
+  {"type":"IDENTIFIER","lexeme":"s","line":2,"column":9},
+  {"type":"PLUS","lexeme":"+","line":2,"column":11},
+  {"type":"NUMBER","lexeme":"1.5","literal":1.5,"line":2,"column":13},
+  {"type":"SEMICOLON","lexeme":";","line":2,"column":16},
+  {"type":"EOF","lexeme":"","line":3,"column":1}
+]
+"#
+    );
+}
+
+#[test]
+fn multi_line_strings_start_where_they_open() {
+    let output = common::run_with_args("print \"a\nb\"; x;\n", &["--tokens"]);
+    let stdout = common::stdout(&output);
+    assert!(stdout
+        .contains(r#"{"type":"STRING","lexeme":"\"a\nb\"","literal":"a\nb","line":1,"column":7}"#));
+    assert!(stdout.contains(r#"{"type":"IDENTIFIER","lexeme":"x","line":2,"column":5}"#));
+}
+
+#[test]
+fn ast_json_serializes_every_node() {
+    let output = common::run_with_args(
+        "fun f(a) { return a; }\nvar x;\ntry { f(1); } catch (e) {}\n",
+        &["--ast-json"],
+    );
+    assert_eq!(common::stderr(&output), "");
+    let stdout = common::stdout(&output);
+    for expected in [
+        "\"type\": \"Function\"",
+        "\"params\": [",
+        "\"type\": \"Return\"",
+        "\"type\": \"Var\"",
+        "\"initializer\": null",
+        "\"type\": \"Try\"",
+        "\"catchClause\": {",
+        "\"finallyBody\": null",
+        "\"type\": \"Call\"",
+        "\"value\": 1",
+    ] {
+        assert!(stdout.contains(expected), "missing {}", expected);
+    }
+}
+
+#[test]
+fn check_reports_syntax_errors_without_running() {
+    let output = common::run_with_args("print \"ran\";\n", &["--check"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(common::stdout(&output), "");
+
+    let output = common::run_with_args("print \"ran\";\nvar = 1;\nprint (;\n", &["--check"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(common::stdout(&output), "");
+    assert_eq!(
+        common::stderr(&output),
+        "[line 2] Error  at '=': Expect variable name.\n[line 3] Error  at ';': Expect expression.\n"
+    );
+}
+
+#[test]
+fn a_reader_that_stops_early_ends_the_output_quietly() {
+    let script = common::write_script(&"print 1;\n".repeat(100_000));
+    for mode in ["--tokens", "--ast-json", "--dump-ast"] {
+        let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_lox1"))
+            .arg(mode)
+            .arg(&script)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut start = [0; 16];
+        std::io::Read::read_exact(child.stdout.as_mut().unwrap(), &mut start).unwrap();
+        drop(child.stdout.take());
+        let output = child.wait_with_output().unwrap();
+        assert_eq!(common::stderr(&output), "", "{}", mode);
+        assert_eq!(output.status.code(), Some(0), "{}", mode);
+    }
+}
+
+#[test]
+fn a_missing_script_is_an_io_error() {
+    for args in [&[][..], &["--tokens"]] {
+        let output = common::run_file(std::path::Path::new("/no/such/script.lox"), args);
+        assert_eq!(output.status.code(), Some(74));
+        assert_eq!(
+            common::stderr(&output),
+            "Could not read '/no/such/script.lox': No such file or directory (os error 2).\n"
+        );
+    }
+}
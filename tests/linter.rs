@@ -0,0 +1,130 @@
+mod common;
+
+use std::process::{Command, Output};
+
+fn lint(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .arg("lint")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn lint_source(source: &str) -> Output {
+    lint(&[common::write_script(source).to_str().unwrap()])
+}
+
+#[test]
+fn reports_each_rule_in_source_order() {
+    let output = lint_source(
+        r#"var total = 0;
+fun add(a, b) {
+  return a;
+  print "never";
+}
+fun scale(total) {
+  var unused = 1;
+  var _ignored = 2;
+  {
+    var total = 3;
+    print total;
+  }
+  return total;
+}
+if (total = 1) print "assign";
+while ((total = 0)) print "meant";
+"text"();
+add(1);
+print scale(add(1, 2));
+"#,
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        common::stderr(&output),
+        "\
+[line 2] Warning  at 'b': Parameter 'b' is never used. [unused-parameter]
+[line 3] Warning  at 'return': Unreachable code after 'return'. [unreachable-code]
+[line 6] Warning  at 'total': 'total' shadows the declaration on line 1. [shadowing]
+[line 7] Warning  at 'unused': Local variable 'unused' is never used. [unused-variable]
+[line 10] Warning  at 'total': 'total' shadows the declaration on line 6. [shadowing]
+[line 15] Warning  at 'total': Assignment used as a condition; did you mean '=='? [assignment-in-condition]
+[line 17] Warning  at ')': Can only call functions. [not-callable]
+[line 18] Warning  at 'add': Expected 2 arguments but got 1. [arity-mismatch]
+"
+    );
+}
+
+#[test]
+fn allow_comments_suppress_rules_on_their_line_and_the_next() {
+    let output = lint_source(
+        r#"fun f(a) {
+  // lint: allow(unused-parameter)
+  return 1;
+}
+fun g(a) { // lint: allow(unused-parameter, shadowing)
+  var g = 1; // lint: allow(unused-variable)
+}
+// lint: allow(arity-mismatch)
+f();
+f();
+"#,
+    );
+    assert_eq!(
+        common::stderr(&output),
+        "\
+[line 1] Warning  at 'a': Parameter 'a' is never used. [unused-parameter]
+[line 10] Warning  at 'f': Expected 1 arguments but got 0. [arity-mismatch]
+"
+    );
+}
+
+#[test]
+fn redeclared_and_local_functions_are_not_checked_for_arity() {
+    let output = lint_source(
+        r#"fun f(a) { return a; }
+var f = nil;
+fun g(a) { return a; }
+fun h() {
+  fun g() { return 1; }
+  return g();
+}
+print f() + h();
+"#,
+    );
+    assert_eq!(
+        common::stderr(&output),
+        "[line 5] Warning  at 'g': 'g' shadows the declaration on line 3. [shadowing]\n"
+    );
+}
+
+#[test]
+fn clean_files_and_syntax_errors() {
+    let dir = common::write_tree(&[
+        ("clean.lox", "fun f(x) { return x; }\nprint f(1);\n"),
+        ("broken.lox", "print (;\n"),
+        ("stray/other.lox", "var a = 1;\n{ var a = a; }\nprint (;\n"),
+    ]);
+    let output = lint(&[dir.join("clean.lox").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(common::stderr(&output), "");
+
+    // Each file is named before its diagnostics; look only at the two
+    // written here, whatever else the directory holds.
+    let output = lint(&[dir.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(65));
+    let stderr = common::stderr(&output);
+    let section = |name: &str| {
+        let header = format!("{}:\n", dir.join(name).display());
+        let start = stderr.find(&header).unwrap() + header.len();
+        let rest = &stderr[start..];
+        let end = rest.find(".lox:\n").map_or(rest.len(), |end| {
+            rest[..end].rfind('\n').map_or(0, |newline| newline + 1)
+        });
+        rest[..end].to_string()
+    };
+    assert_eq!(
+        section("broken.lox"),
+        "[line 1] Error  at ';': Expect expression.\n"
+    );
+    assert_eq!(section("clean.lox"), "");
+}
@@ -0,0 +1,276 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Plays a scripted client: sends each message framed with its
+/// `Content-Length`, closes the input and returns the server's messages and
+/// exit code.
+fn session(messages: &[String]) -> (Vec<String>, Option<i32>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for message in messages {
+        write!(
+            stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            message.len(),
+            message
+        )
+        .unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+
+    let mut stdout = String::from_utf8(output.stdout).unwrap();
+    let mut replies = Vec::new();
+    while let Some(rest) = stdout.strip_prefix("Content-Length: ") {
+        let (length, rest) = rest.split_once("\r\n\r\n").unwrap();
+        let length: usize = length.parse().unwrap();
+        replies.push(rest[..length].to_string());
+        stdout = rest[length..].to_string();
+    }
+    assert_eq!(stdout, "");
+    (replies, output.status.code())
+}
+
+fn quote(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn request(id: u32, method: &str, params: &str) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","id":{},"method":"{}","params":{}}}"#,
+        id, method, params
+    )
+}
+
+fn notification(method: &str, params: &str) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","method":"{}","params":{}}}"#,
+        method, params
+    )
+}
+
+fn open(text: &str) -> String {
+    notification(
+        "textDocument/didOpen",
+        &format!(
+            r#"{{"textDocument":{{"uri":"file:///a.lox","languageId":"lox","version":1,"text":{}}}}}"#,
+            quote(text)
+        ),
+    )
+}
+
+fn at(id: u32, method: &str, line: u32, character: u32) -> String {
+    request(
+        id,
+        method,
+        &format!(
+            r#"{{"textDocument":{{"uri":"file:///a.lox"}},"position":{{"line":{},"character":{}}},"context":{{"includeDeclaration":true}}}}"#,
+            line, character
+        ),
+    )
+}
+
+fn range(line: u32, start: u32, end: u32) -> String {
+    format!(
+        r#"{{"start":{{"line":{},"character":{}}},"end":{{"line":{},"character":{}}}}}"#,
+        line, start, line, end
+    )
+}
+
+fn location(line: u32, start: u32, end: u32) -> String {
+    format!(
+        r#"{{"uri":"file:///a.lox","range":{}}}"#,
+        range(line, start, end)
+    )
+}
+
+fn shutdown(id: u32) -> [String; 2] {
+    [
+        request(id, "shutdown", "null"),
+        notification("exit", "null"),
+    ]
+}
+
+const SOURCE: &str = "var total = 0;
+fun add(a, b) {
+  var sum = a + b;
+  return sum;
+}
+total = add(total, 2);
+";
+
+#[test]
+fn navigates_definitions_references_and_hovers() {
+    let mut messages = vec![
+        request(1, "initialize", "{}"),
+        notification("initialized", "{}"),
+        open(SOURCE),
+        at(2, "textDocument/definition", 5, 9),
+        at(3, "textDocument/references", 0, 6),
+        at(4, "textDocument/hover", 5, 8),
+        at(5, "textDocument/hover", 2, 12),
+        at(6, "textDocument/definition", 3, 1),
+    ];
+    messages.extend(shutdown(7));
+    let (replies, code) = session(&messages);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        replies,
+        [
+            r#"{"jsonrpc":"2.0","id":1,"result":{"capabilities":{"textDocumentSync":1,"definitionProvider":true,"referencesProvider":true,"hoverProvider":true,"documentSymbolProvider":true,"completionProvider":{}},"serverInfo":{"name":"rlox"}}}"#.to_string(),
+            r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.lox","diagnostics":[]}}"#.to_string(),
+            format!(r#"{{"jsonrpc":"2.0","id":2,"result":{}}}"#, location(1, 4, 7)),
+            format!(
+                r#"{{"jsonrpc":"2.0","id":3,"result":[{},{},{}]}}"#,
+                location(0, 4, 9),
+                location(5, 0, 5),
+                location(5, 12, 17)
+            ),
+            format!(
+                r#"{{"jsonrpc":"2.0","id":4,"result":{{"contents":{{"kind":"markdown","value":"```lox\nfun add(a, b)\n```"}},"range":{}}}}}"#,
+                range(5, 8, 11)
+            ),
+            format!(
+                r#"{{"jsonrpc":"2.0","id":5,"result":{{"contents":{{"kind":"markdown","value":"```lox\n(parameter) a\n```"}},"range":{}}}}}"#,
+                range(2, 12, 13)
+            ),
+            r#"{"jsonrpc":"2.0","id":6,"result":null}"#.to_string(),
+            r#"{"jsonrpc":"2.0","id":7,"result":null}"#.to_string(),
+        ]
+    );
+}
+
+#[test]
+fn publishes_syntax_errors_and_lint_warnings_on_change() {
+    let change = |text: &str| {
+        notification(
+            "textDocument/didChange",
+            &format!(
+                r#"{{"textDocument":{{"uri":"file:///a.lox","version":2}},"contentChanges":[{{"text":{}}}]}}"#,
+                quote(text)
+            ),
+        )
+    };
+    let mut messages = vec![
+        open("print (;\n"),
+        change("fun f(unused) {\n  return 1;\n}\n"),
+        notification(
+            "textDocument/didClose",
+            r#"{"textDocument":{"uri":"file:///a.lox"}}"#,
+        ),
+    ];
+    messages.extend(shutdown(1));
+    let (replies, code) = session(&messages);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        replies[..3],
+        [
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{{"uri":"file:///a.lox","diagnostics":[{{"range":{},"severity":1,"source":"rlox","message":"Expect expression."}}]}}}}"#,
+                range(0, 7, 8)
+            ),
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{{"uri":"file:///a.lox","diagnostics":[{{"range":{},"severity":2,"code":"unused-parameter","source":"rlox","message":"Parameter 'unused' is never used."}}]}}}}"#,
+                range(0, 6, 12)
+            ),
+            r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.lox","diagnostics":[]}}"#.to_string(),
+        ]
+    );
+}
+
+#[test]
+fn lists_symbols_and_completes_names_in_scope() {
+    let mut messages = vec![
+        open(SOURCE),
+        request(
+            1,
+            "textDocument/documentSymbol",
+            r#"{"textDocument":{"uri":"file:///a.lox"}}"#,
+        ),
+        at(2, "textDocument/completion", 3, 2),
+        at(3, "textDocument/completion", 5, 0),
+    ];
+    messages.extend(shutdown(4));
+    let (replies, _) = session(&messages);
+    assert_eq!(
+        replies[1],
+        format!(
+            r#"{{"jsonrpc":"2.0","id":1,"result":[{{"name":"total","detail":"var total","kind":13,"range":{0},"selectionRange":{0}}},{{"name":"add","detail":"fun add(a, b)","kind":12,"range":{{"start":{{"line":1,"character":0}},"end":{{"line":4,"character":1}}}},"selectionRange":{1},"children":[{{"name":"sum","detail":"var sum","kind":13,"range":{2},"selectionRange":{2}}}]}}]}}"#,
+            range(0, 4, 9),
+            range(1, 4, 7),
+            range(2, 6, 9)
+        )
+    );
+
+    let labels = |reply: &str| -> Vec<String> {
+        reply
+            .split(r#""label":""#)
+            .skip(1)
+            .map(|rest| rest.split('"').next().unwrap().to_string())
+            .collect()
+    };
+    let inside = labels(&replies[2]);
+    assert_eq!(inside[..5], ["sum", "b", "a", "add", "total"]);
+    assert!(inside.contains(&"len".to_string()));
+    assert!(inside.contains(&"while".to_string()));
+    assert!(replies[2].contains(r#"{"label":"PI","kind":21,"detail":"(native constant) PI"}"#));
+
+    let outside = labels(&replies[3]);
+    assert_eq!(outside[..2], ["add", "total"]);
+    assert!(!outside.contains(&"sum".to_string()));
+}
+
+#[test]
+fn rejects_unknown_methods_and_exits_with_1_without_shutdown() {
+    let (replies, code) = session(&[
+        request(1, "textDocument/rename", "{}"),
+        at(2, "textDocument/hover", 0, 0),
+        notification("exit", "null"),
+    ]);
+    assert_eq!(code, Some(1));
+    assert_eq!(
+        replies,
+        [
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"Unknown method 'textDocument/rename'."}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32602,"message":"Document 'file:///a.lox' is not open."}}"#,
+        ]
+    );
+}
+
+#[test]
+fn survives_a_half_typed_function() {
+    let mut messages = vec![open("fun ")];
+    messages.extend(shutdown(1));
+    let (replies, code) = session(&messages);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        replies[0],
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{{"uri":"file:///a.lox","diagnostics":[{{"range":{},"severity":1,"source":"rlox","message":"Expect function name."}}]}}}}"#,
+            range(0, 4, 4)
+        )
+    );
+}
+
+#[test]
+fn survives_a_deeply_nested_message() {
+    let mut messages = vec!["[".repeat(100000)];
+    messages.extend(shutdown(1));
+    let (replies, code) = session(&messages);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        replies[0],
+        r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"Invalid JSON at line 1, column 513: Nesting too deep."}}"#
+    );
+}
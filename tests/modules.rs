@@ -0,0 +1,131 @@
+mod common;
+
+#[test]
+fn import_binds_module_under_alias() {
+    let dir = common::write_tree(&[
+        (
+            "main.lox",
+            r#"
+import "lib/shapes.lox" as shapes;
+print shapes.square(3);
+print shapes.sides;
+"#,
+        ),
+        (
+            "lib/shapes.lox",
+            r#"
+import "arith.lox" as arith;
+var sides = 4;
+fun square(x) { return arith.mul(x, x); }
+"#,
+        ),
+        ("lib/arith.lox", "fun mul(a, b) { return a * b; }\n"),
+    ]);
+    let output = common::run_file(&dir.join("main.lox"), &[]);
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "9\n4\n");
+}
+
+#[test]
+fn imports_inside_module_functions_resolve_against_the_module() {
+    let dir = common::write_tree(&[
+        (
+            "main.lox",
+            r#"
+import "lib/loader.lox" as loader;
+print loader.load();
+"#,
+        ),
+        (
+            "lib/loader.lox",
+            r#"
+fun load() {
+  import "helper.lox" as helper;
+  return helper.name;
+}
+"#,
+        ),
+        ("lib/helper.lox", "var name = \"lib helper\";\n"),
+        ("helper.lox", "var name = \"main helper\";\n"),
+    ]);
+    let output = common::run_file(&dir.join("main.lox"), &[]);
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "lib helper\n");
+}
+
+#[test]
+fn from_import_binds_selected_names() {
+    let dir = common::write_tree(&[
+        (
+            "main.lox",
+            r#"
+from "util.lox" import twice, name;
+print twice(21);
+print name;
+"#,
+        ),
+        (
+            "util.lox",
+            "var name = \"util\";\nfun twice(x) { return x * 2; }\n",
+        ),
+    ]);
+    let output = common::run_file(&dir.join("main.lox"), &[]);
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "42\nutil\n");
+}
+
+#[test]
+fn modules_run_once_and_keep_their_own_globals() {
+    let dir = common::write_tree(&[
+        (
+            "main.lox",
+            r#"
+var secret = "main";
+import "counter.lox" as a;
+import "counter.lox" as b;
+a.next();
+print b.next();
+print a.peek();
+"#,
+        ),
+        (
+            "counter.lox",
+            r#"
+print "loading";
+var count = 0;
+fun next() { count = count + 1; return count; }
+fun peek() {
+  try { return secret; } catch (e) { return e.message; }
+}
+"#,
+        ),
+    ]);
+    let output = common::run_file(&dir.join("main.lox"), &[]);
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(
+        common::stdout(&output),
+        "loading\n2\nUndefined variable 'secret'.\n"
+    );
+}
+
+#[test]
+fn import_errors_are_runtime_errors() {
+    let dir = common::write_tree(&[
+        (
+            "main.lox",
+            r#"
+try { import "missing.lox" as m; } catch (e) { print e.message; }
+try { from "a.lox" import nothing; } catch (e) { print e.message; }
+import "a.lox" as a;
+"#,
+        ),
+        ("a.lox", "import \"b.lox\" as b;\n"),
+        ("b.lox", "import \"a.lox\" as a;\n"),
+    ]);
+    let output = common::run_file(&dir.join("main.lox"), &[]);
+    let stdout = common::stdout(&output);
+    assert!(stdout.starts_with("Cannot import 'missing.lox': "));
+    assert!(stdout.contains("Import cycle: "));
+    assert_eq!(output.status.code(), Some(70));
+    assert!(common::stderr(&output).starts_with("Import cycle: "));
+}
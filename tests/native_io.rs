@@ -0,0 +1,72 @@
+mod common;
+
+#[test]
+fn file_natives_read_and_write() {
+    let dir = common::write_tree(&[("existing.txt", "")]);
+    let source = format!(
+        r#"
+var dir = "{}";
+print exists(dir + "/notes.txt");
+writeFile(dir + "/notes.txt", "one");
+appendFile(dir + "/notes.txt", " two");
+print readFile(dir + "/notes.txt");
+print exists(dir + "/notes.txt");
+print listDir(dir);
+"#,
+        dir.display()
+    );
+    let output = common::run(&source);
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(
+        common::stdout(&output),
+        "false\none two\ntrue\n[\"existing.txt\", \"notes.txt\"]\n"
+    );
+}
+
+#[test]
+fn os_failures_are_catchable() {
+    let output = common::run(
+        r#"
+try { readFile("/nonexistent/file"); } catch (e) { print e.message; }
+try { listDir("/nonexistent"); } catch (e) { print e.message; }
+"#,
+    );
+    assert_eq!(
+        common::stdout(&output),
+        "Could not read '/nonexistent/file': No such file or directory (os error 2).\n\
+         Could not list '/nonexistent': No such file or directory (os error 2).\n"
+    );
+}
+
+#[test]
+fn standard_streams() {
+    let output = common::run_with_input(
+        r#"
+print readLine();
+eprint("warning");
+print readAll();
+print readLine();
+"#,
+        &[],
+        "first\nsecond\nthird",
+    );
+    assert_eq!(common::stdout(&output), "first\nsecond\nthird\nnil\n");
+    assert_eq!(common::stderr(&output), "warning\n");
+}
+
+#[test]
+fn sandbox_disables_io() {
+    let output = common::run_with_args(
+        r#"
+try { readFile("x"); } catch (e) { print e.message; }
+try { eprint("x"); } catch (e) { print e.message; }
+print sqrt(4);
+"#,
+        &["--sandbox"],
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(
+        common::stdout(&output),
+        "'readFile' is not available in a sandbox.\n'eprint' is not available in a sandbox.\n2\n"
+    );
+}
@@ -0,0 +1,161 @@
+mod common;
+
+#[test]
+fn parse_and_stringify_round_trip() {
+    let dir = common::write_tree(&[
+        (
+            "data.json",
+            r#"{"name": "rlox", "tags": ["a", "é😀"], "n": -1.5e2, "ok": true, "none": null}"#,
+        ),
+        (
+            "main.lox",
+            r#"
+var data = jsonParse(readFile("data.json"));
+print data;
+print data.name;
+print data["tags"][1];
+print data["missing"];
+data["n"] = 7;
+print jsonStringify(data);
+print jsonStringify([1, [], jsonParse("{}")], 2);
+"#,
+        ),
+    ]);
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .arg("main.lox")
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(
+        common::stdout(&output),
+        "{\"name\": \"rlox\", \"tags\": [\"a\", \"é😀\"], \"n\": -150, \"ok\": true, \"none\": nil}\n\
+         rlox\n\
+         é😀\n\
+         nil\n\
+         {\"name\":\"rlox\",\"tags\":[\"a\",\"é😀\"],\"n\":7,\"ok\":true,\"none\":null}\n\
+         [\n  1,\n  [],\n  {}\n]\n"
+    );
+}
+
+#[test]
+fn malformed_json_reports_position() {
+    let output = common::run("jsonParse(\"[1,\n  2 x]\");\n");
+    assert_eq!(output.status.code(), Some(70));
+    assert!(common::stderr(&output).starts_with(
+        "Invalid JSON at line 2, column 5: Expected ',' or ']' after array element.\n[line 2]\n"
+    ));
+
+    let output = common::run("jsonParse(\"[1\");\n");
+    assert!(common::stderr(&output)
+        .starts_with("Invalid JSON at line 1, column 3: Expected ',' or ']' after array element."));
+}
+
+#[test]
+fn unserializable_values_are_runtime_errors() {
+    let output = common::run(
+        r#"
+fun f() {}
+try { jsonStringify([f]); } catch (e) { print e.message; }
+try { jsonStringify(0 / 0); } catch (e) { print e.message; }
+var list = [1, 2];
+list[1] = list;
+print list;
+try { jsonStringify(list); } catch (e) { print e.message; }
+var shared = [];
+print jsonStringify([shared, shared]);
+"#,
+    );
+    assert_eq!(
+        common::stdout(&output),
+        "Cannot serialize <fn f> to JSON.\n\
+         Cannot serialize NaN to JSON.\n\
+         [1, [...]]\n\
+         Cannot serialize a cyclic structure to JSON.\n\
+         [[],[]]\n"
+    );
+}
+
+#[test]
+fn cyclic_values_compare_without_overflowing() {
+    let output = common::run(
+        r#"
+var a = [1];
+a[0] = a;
+print a == a;
+var b = [1];
+b[0] = a;
+a[0] = b;
+print a == b;
+var c = [1];
+c[0] = c;
+print a == c;
+var m = jsonParse("{}");
+m["self"] = m;
+var n = jsonParse("{}");
+n["self"] = n;
+print m == n;
+print [[1, 2], 3] == [[1, 2], 3];
+print [[1, 2], 3] == [[1, 3], 3];
+"#,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        common::stdout(&output),
+        "true\ntrue\ntrue\ntrue\ntrue\nfalse\n"
+    );
+}
+
+#[test]
+fn maps_compare_regardless_of_key_order() {
+    let output = common::run(
+        r#"
+var xy = jsonParse("{}");
+xy["x"] = 1;
+xy["y"] = [2];
+var yx = jsonParse("{}");
+yx["y"] = [2];
+yx["x"] = 1;
+print xy == yx;
+yx["y"] = [3];
+print xy == yx;
+var xz = jsonParse("{}");
+xz["x"] = 1;
+xz["z"] = [2];
+print xy == xz;
+xz["y"] = [2];
+print xy == xz;
+"#,
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "true\nfalse\nfalse\nfalse\n");
+}
+
+#[test]
+fn nesting_is_limited_instead_of_overflowing() {
+    let output = common::run(
+        r#"
+try { jsonParse(repeat("[", 100000)); } catch (e) { print e.message; }
+try { jsonParse("
+  " + repeat("[", 600)); } catch (e) { print e.message; }
+print len(jsonStringify(jsonParse(repeat("[", 512) + repeat("]", 512))));
+var list = [];
+var depth = 1;
+while (depth < 512) {
+  list = [list];
+  depth = depth + 1;
+}
+print len(jsonStringify(list));
+try { jsonStringify([list]); } catch (e) { print e.message; }
+"#,
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(
+        common::stdout(&output),
+        "Invalid JSON at line 1, column 513: Nesting too deep.\n\
+         Invalid JSON at line 2, column 515: Nesting too deep.\n\
+         1024\n\
+         1024\n\
+         Cannot serialize values nested more than 512 deep to JSON.\n"
+    );
+}
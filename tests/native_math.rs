@@ -0,0 +1,66 @@
+mod common;
+
+fn run_ok(source: &str) -> String {
+    let output = common::run(source);
+    assert_eq!(common::stderr(&output), "");
+    common::stdout(&output)
+}
+
+#[test]
+fn math_functions_and_constants() {
+    let stdout = run_ok(
+        r#"
+print sqrt(16);
+print pow(2, 10);
+print abs(-3.5);
+print floor(2.7) + ceil(2.2) + round(2.5);
+print min(3, 1, 2);
+print max(3, 1, 2);
+print log(E);
+print atan2(1, 1) * 4 == PI;
+print isNaN(NAN);
+print isNaN(max(1, NAN));
+print isFinite(INF);
+print isFinite(1);
+"#,
+    );
+    assert_eq!(
+        stdout,
+        "4\n1024\n3.5\n8\n1\n3\n1\ntrue\ntrue\ntrue\nfalse\ntrue\n"
+    );
+}
+
+#[test]
+fn misuse_raises_runtime_errors() {
+    let stdout = run_ok(
+        r#"
+try { sqrt(-1); } catch (e) { print e.message; }
+try { asin(2); } catch (e) { print e.message; }
+try { sqrt("x"); } catch (e) { print e.message; }
+try { max(1, nil); } catch (e) { print e.message; }
+try { pow(2); } catch (e) { print e.message; }
+try { min(); } catch (e) { print e.message; }
+"#,
+    );
+    assert_eq!(
+        stdout,
+        "Math domain error in 'sqrt'.\n\
+         Math domain error in 'asin'.\n\
+         Argument 1 to 'sqrt' must be a number.\n\
+         Argument 2 to 'max' must be a number.\n\
+         Expected 2 arguments but got 1.\n\
+         Expected at least 1 arguments but got 0.\n"
+    );
+}
+
+#[test]
+fn native_frames_appear_in_tracebacks() {
+    let output = common::run("fun f(x) {\n  return log(x);\n}\nf(-1);\n");
+    assert_eq!(
+        common::stderr(&output),
+        "Math domain error in 'log'.\n[line 2]\n  \
+         at log (native)\n  \
+         at f (line 2)\n  \
+         at <script> (line 4)\n"
+    );
+}
@@ -0,0 +1,88 @@
+mod common;
+
+const SCRIPT: &str = r#"
+var l = [1, 2, 3, 4, 5, 6, 7, 8];
+shuffle(l);
+print l;
+print choice(l);
+print randomInt(-3, 3);
+print random();
+"#;
+
+#[test]
+fn seed_flag_makes_runs_reproducible() {
+    let first = common::run_with_args(SCRIPT, &["--seed", "1234"]);
+    let second = common::run_with_args(SCRIPT, &["--seed", "1234"]);
+    assert_eq!(common::stderr(&first), "");
+    assert_eq!(common::stdout(&first), common::stdout(&second));
+
+    let other = common::run_with_args(SCRIPT, &["--seed", "4321"]);
+    assert_ne!(common::stdout(&first), common::stdout(&other));
+}
+
+#[test]
+fn seed_native_restarts_sequence() {
+    let output = common::run(
+        r#"
+seed(7);
+var a = [random(), randomInt(1, 100)];
+seed(7);
+print a[0] == random() and a[1] == randomInt(1, 100);
+"#,
+    );
+    assert_eq!(common::stdout(&output), "true\n");
+}
+
+#[test]
+fn values_stay_in_range() {
+    let output = common::run(
+        r#"
+var ok = true;
+for (var i = 0; i < 500; i = i + 1) {
+  var r = random();
+  var n = randomInt(-2, 2);
+  if (r < 0 or r >= 1 or n < -2 or n > 2 or floor(n) != n) ok = false;
+}
+print ok;
+print randomInt(5, 5);
+var l = [1, 2, 3];
+shuffle(l);
+print len(l) == 3 and indexOf(l, 1) >= 0 and indexOf(l, 2) >= 0 and indexOf(l, 3) >= 0;
+"#,
+    );
+    assert_eq!(common::stdout(&output), "true\n5\ntrue\n");
+}
+
+#[test]
+fn bad_arguments_are_runtime_errors() {
+    let output = common::run(
+        r#"
+try { choice([]); } catch (e) { print e.message; }
+try { randomInt(3, 1); } catch (e) { print e.message; }
+try { randomInt(0.5, 1); } catch (e) { print e.message; }
+try { randomInt(0, 100000000000000000000); } catch (e) { print e.message; }
+print randomInt(0, 9007199254740992) >= 0;
+try { shuffle("abc"); } catch (e) { print e.message; }
+try { seed(-1); } catch (e) { print e.message; }
+try { seed(1.5); } catch (e) { print e.message; }
+try { seed(0 / 0); } catch (e) { print e.message; }
+try { seed(1 / 0); } catch (e) { print e.message; }
+try { seed(100000000000000000000); } catch (e) { print e.message; }
+seed(0);
+"#,
+    );
+    assert_eq!(
+        common::stdout(&output),
+        "Cannot choose from an empty list.\n\
+         Arguments to 'randomInt' must be integers with lo <= hi.\n\
+         Arguments to 'randomInt' must be integers with lo <= hi.\n\
+         Arguments to 'randomInt' must be at most 2^53 apart.\n\
+         true\n\
+         Argument 1 to 'shuffle' must be a list.\n\
+         Argument 1 to 'seed' must be a non-negative integer below 2^64.\n\
+         Argument 1 to 'seed' must be a non-negative integer below 2^64.\n\
+         Argument 1 to 'seed' must be a non-negative integer below 2^64.\n\
+         Argument 1 to 'seed' must be a non-negative integer below 2^64.\n\
+         Argument 1 to 'seed' must be a non-negative integer below 2^64.\n"
+    );
+}
@@ -0,0 +1,103 @@
+mod common;
+
+fn run_ok(source: &str) -> String {
+    let output = common::run(source);
+    assert_eq!(common::stderr(&output), "");
+    common::stdout(&output)
+}
+
+#[test]
+fn string_functions() {
+    let stdout = run_ok(
+        r#"
+print toString(12) + "!";
+print parseNumber(" 3.5 ") + 1;
+print parseNumber("abc");
+print len("abc");
+print substring("hello world", 6);
+print substring("hello world", 0, 5);
+print indexOf("hello", "ll");
+print indexOf("hello", "z");
+print split("a,b,,c", ",");
+print join(["a", 1, nil], "-");
+print trim("  x  ") + "|";
+print upper("abc") + lower("DEF");
+print replace("a-b-c", "-", "+");
+print startsWith("hello", "he");
+print endsWith("hello", "x");
+print repeat("ab", 3);
+print format("{} + {} = {}", 1, 2, 3);
+print format("{{}} {}", [1, "two"]);
+"#,
+    );
+    assert_eq!(
+        stdout,
+        "12!\n4.5\nnil\n3\nworld\nhello\n2\n-1\n[\"a\", \"b\", \"\", \"c\"]\n\
+         a-1-nil\nx|\nABCdef\na+b+c\ntrue\nfalse\nababab\n1 + 2 = 3\n{} [1, \"two\"]\n"
+    );
+}
+
+#[test]
+fn strings_are_indexed_by_character() {
+    let stdout = run_ok(
+        r#"
+var s = "héllo wörld";
+print len(s);
+print s[1];
+print s[7];
+print indexOf(s, "wö");
+print substring(s, 6, 8);
+print split("añb", "");
+print ["x", "y"][1];
+"#,
+    );
+    assert_eq!(stdout, "11\né\nö\n6\nwö\n[\"a\", \"ñ\", \"b\"]\ny\n");
+}
+
+#[test]
+fn equality_works_for_any_values() {
+    let stdout = run_ok(
+        r#"
+print "a" == "a";
+print "a" != "b";
+print 1 == "1";
+print nil == nil;
+print [1, "a"] == [1, "a"];
+print clock == clock;
+"#,
+    );
+    assert_eq!(stdout, "true\ntrue\nfalse\ntrue\ntrue\ntrue\n");
+}
+
+#[test]
+fn misuse_raises_runtime_errors() {
+    let stdout = run_ok(
+        r#"
+try { "abc"[3]; } catch (e) { print e.message; }
+try { "abc"[1.5]; } catch (e) { print e.message; }
+try { 3[0]; } catch (e) { print e.message; }
+try { format("{} {}", 1); } catch (e) { print e.message; }
+try { format("{"); } catch (e) { print e.message; }
+try { substring("abc", 2, 1); } catch (e) { print e.message; }
+try { upper(1); } catch (e) { print e.message; }
+try { repeat("a", -1); } catch (e) { print e.message; }
+try { repeat("ab", 10000000000000000000); } catch (e) { print e.message; }
+try { repeat("ab", 10000000000); } catch (e) { print e.message; }
+print len(repeat("", 10000000000000000000));
+"#,
+    );
+    assert_eq!(
+        stdout,
+        "Index out of range.\n\
+         Index must be a non-negative integer.\n\
+         Only strings, lists and maps can be indexed.\n\
+         Format string has 2 placeholders but got 1 arguments.\n\
+         Unmatched brace in format string; use '{{' or '}}' for a literal brace.\n\
+         Substring range 2..1 out of bounds for length 3.\n\
+         Argument 1 to 'upper' must be a string.\n\
+         Argument 2 to 'repeat' must be a non-negative integer.\n\
+         Result of 'repeat' is too large.\n\
+         Result of 'repeat' is too large.\n\
+         0\n"
+    );
+}
@@ -0,0 +1,62 @@
+mod common;
+
+#[test]
+fn extra_arguments_are_passed_to_script() {
+    let output = common::run_with_args("print args;\nprint len(args);\n", &[]);
+    assert_eq!(common::stdout(&output), "[]\n0\n");
+
+    let path = common::write_script("print args;\n");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .arg(&path)
+        .args(["one", "--two", "3"])
+        .output()
+        .unwrap();
+    assert_eq!(common::stdout(&output), "[\"one\", \"--two\", \"3\"]\n");
+}
+
+#[test]
+fn getenv_reads_environment() {
+    let path = common::write_script(
+        "print getenv(\"RLOX_TEST_VAR\");\nprint getenv(\"RLOX_UNSET_VAR\");\n",
+    );
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .arg(&path)
+        .env("RLOX_TEST_VAR", "hello")
+        .env_remove("RLOX_UNSET_VAR")
+        .output()
+        .unwrap();
+    assert_eq!(common::stdout(&output), "hello\nnil\n");
+}
+
+#[test]
+fn exit_unwinds_and_sets_status() {
+    let output = common::run(
+        r#"
+fun f() {
+  try {
+    exit(3);
+  } catch (e) {
+    print "caught";
+  } finally {
+    print "finally";
+  }
+}
+f();
+print "unreachable";
+"#,
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(common::stdout(&output), "finally\n");
+    assert_eq!(common::stderr(&output), "");
+
+    let output = common::run("print 1;\nexit();\nprint 2;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(common::stdout(&output), "1\n");
+}
+
+#[test]
+fn compile_and_runtime_errors_keep_their_exit_codes() {
+    assert_eq!(common::run("print ;").status.code(), Some(65));
+    assert_eq!(common::run("print -nil;").status.code(), Some(70));
+    assert_eq!(common::run("exit(256);").status.code(), Some(70));
+}
@@ -0,0 +1,118 @@
+mod common;
+
+#[test]
+fn fixed_clock_makes_time_deterministic() {
+    let output = common::run_with_args(
+        r#"
+print clock();
+print now();
+sleep(1500);
+print now();
+print isoDate();
+"#,
+        &["--fixed-time", "1700000000"],
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(
+        common::stdout(&output),
+        "1700000000\n0\n1.5\n2023-11-14T22:13:21.500Z\n"
+    );
+}
+
+#[test]
+fn dates_break_down_in_utc() {
+    let output = common::run(
+        r#"
+var d = date(1709210096);
+print d;
+print d.year + d.month + d.day;
+print isoDate(0);
+print isoDate(951782400.25);
+print isoDate(-1);
+print isoDate(253402300799);
+"#,
+    );
+    assert_eq!(
+        common::stdout(&output),
+        "{\"year\": 2024, \"month\": 2, \"day\": 29, \"hour\": 12, \"minute\": 34, \"second\": 56, \"millisecond\": 0}\n\
+         2055\n\
+         1970-01-01T00:00:00Z\n\
+         2000-02-29T00:00:00.250Z\n\
+         1969-12-31T23:59:59Z\n\
+         9999-12-31T23:59:59Z\n"
+    );
+}
+
+#[test]
+fn sleep_advances_monotonic_time() {
+    let output = common::run(
+        r#"
+var start = now();
+sleep(20);
+print now() - start >= 0.02;
+try { sleep(-1); } catch (e) { print e.message; }
+try { sleep(0 / 0); } catch (e) { print e.message; }
+try { sleep(100000000000000000000000000000000000000000000000000000); } catch (e) { print e.message; }
+"#,
+    );
+    assert_eq!(
+        common::stdout(&output),
+        "true\n\
+         Argument 1 to 'sleep' must be a non-negative number.\n\
+         Argument 1 to 'sleep' must be a non-negative number.\n\
+         Argument 1 to 'sleep' is too large.\n"
+    );
+}
+
+#[test]
+fn fixed_time_beyond_the_clock_is_a_usage_error() {
+    for time in ["1e300", "-1", "inf"] {
+        let output = common::run_with_args("print 1;\n", &["--fixed-time", time]);
+        assert_eq!(output.status.code(), Some(64));
+        assert_eq!(common::stderr(&output), "");
+    }
+}
+
+#[test]
+fn sleeping_past_the_end_of_a_fixed_clock_is_an_error() {
+    let source = r#"
+try { sleep(10000000000000000000000); } catch (e) { print e.message; }
+try { sleep(10000000000000000000000); } catch (e) { print e.message; }
+print clock() > 0;
+"#;
+    let too_large = "Argument 1 to 'sleep' is too large.\n";
+    for (time, expected) in [
+        ("18000000000000000000", format!("{0}{0}true\n", too_large)),
+        ("0", format!("{}true\n", too_large)),
+    ] {
+        let output = common::run_with_args(source, &["--fixed-time", time]);
+        assert_eq!(common::stderr(&output), "");
+        assert_eq!(common::stdout(&output), expected);
+    }
+}
+
+#[test]
+fn dates_too_far_from_the_epoch_are_errors() {
+    let output = common::run_with_args(
+        r#"
+print isoDate(8640000000000);
+print isoDate(-8640000000000);
+try { isoDate(8640000000001); } catch (e) { print e.message; }
+try { date(-100000000000000000000); } catch (e) { print e.message; }
+sleep(9000000000000000);
+try { isoDate(); } catch (e) { print e.message; }
+"#,
+        &["--fixed-time", "0"],
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(
+        common::stdout(&output),
+        "275760-09-13T00:00:00Z
+\
+         -271821-04-20T00:00:00Z
+\
+         Argument 1 to 'isoDate' must be within 100000000 days of the epoch.\n\
+         Argument 1 to 'date' must be within 100000000 days of the epoch.\n\
+         The clock is too far from the epoch for 'isoDate'.\n"
+    );
+}
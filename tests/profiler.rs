@@ -0,0 +1,107 @@
+mod common;
+
+// With the clock fixed, only `sleep` takes time, so the times are exact.
+const SCRIPT: &str = "fun work(ms) {
+  sleep(ms);
+  return ms;
+}
+fun fib(n) {
+  if (n < 2) {
+    sleep(1);
+    return n;
+  }
+  return fib(n - 1) + fib(n - 2);
+}
+fun countdown(n) {
+  if (n == 0) return 0;
+  work(2);
+  return countdown(n - 1);
+}
+work(10);
+fib(3);
+countdown(2);
+";
+
+#[test]
+fn reports_time_by_function_and_line() {
+    let dir = common::fresh_dir();
+    let folded = dir.join("stacks.folded");
+    let output = common::run_with_args(
+        SCRIPT,
+        &[
+            "--fixed-time",
+            "0",
+            "--profile-folded",
+            folded.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        common::stderr(&output),
+        format!(
+            "\
+Profile: 17.000 ms in total
+
+Functions by exclusive time:
+   calls  inclusive ms  exclusive ms  function
+       3        14.000        14.000  work (script.lox:1)
+       5         3.000         3.000  fib (script.lox:5)
+       1        17.000         0.000  <script>
+       3         4.000         0.000  countdown (script.lox:12)
+
+Lines by exclusive time:
+    runs  inclusive ms  exclusive ms  line
+       3        14.000        14.000  script.lox:2  sleep(ms);
+       3         3.000         3.000  script.lox:7  sleep(1);
+       1        10.000         0.000  script.lox:17  work(10);
+       2         4.000         0.000  script.lox:14  work(2);
+       1         4.000         0.000  script.lox:19  countdown(2);
+       5         3.000         0.000  script.lox:6  if (n < 2) {{
+       2         3.000         0.000  script.lox:10  return fib(n - 1) + fib(n - 2);
+       1         3.000         0.000  script.lox:18  fib(3);
+       1         0.000         0.000  script.lox:1  fun work(ms) {{
+       3         0.000         0.000  script.lox:3  return ms;
+       1         0.000         0.000  script.lox:5  fun fib(n) {{
+       3         0.000         0.000  script.lox:8  return n;
+       1         0.000         0.000  script.lox:12  fun countdown(n) {{
+       4         0.000         0.000  script.lox:13  if (n == 0) return 0;
+       2         0.000         0.000  script.lox:15  return countdown(n - 1);
+
+Folded stacks written to {}.
+",
+            folded.display()
+        )
+    );
+    assert_eq!(
+        std::fs::read_to_string(folded).unwrap(),
+        "\
+<script>;countdown (script.lox:12);work (script.lox:1) 4000
+<script>;fib (script.lox:5);fib (script.lox:5) 1000
+<script>;fib (script.lox:5);fib (script.lox:5);fib (script.lox:5) 2000
+<script>;work (script.lox:1) 10000
+"
+    );
+}
+
+#[test]
+fn reports_scripts_that_exit_early() {
+    let dir = common::fresh_dir();
+    let folded = dir.join("stacks.folded");
+    let output = common::run_with_args(
+        "fun stop() {\n  sleep(3);\n  exit(2);\n}\nstop();\nprint \"unreachable\";\n",
+        &[
+            "--fixed-time",
+            "0",
+            "--profile-folded",
+            folded.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(common::stdout(&output), "");
+    assert!(common::stderr(&output)
+        .contains("       1         3.000         3.000  stop (script.lox:1)\n"));
+    assert_eq!(
+        std::fs::read_to_string(folded).unwrap(),
+        "<script>;stop (script.lox:1) 3000\n"
+    );
+}
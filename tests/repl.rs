@@ -0,0 +1,95 @@
+mod common;
+
+#[test]
+fn incomplete_entries_continue_on_next_line() {
+    let dir = common::fresh_dir();
+    let history = dir.join("history");
+    let output = common::run_repl(
+        "fun twice(a) {\n  return a * 2;\n}\nprint twice(21);\nprint \"two\nlines\";\nprint (1 +\n  2);\n// (\nprint \"done\";\n",
+        &history,
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "42\ntwo\nlines\n3\ndone\n");
+}
+
+#[test]
+fn end_of_input_exits_cleanly_and_saves_history() {
+    let dir = common::fresh_dir();
+    let history = dir.join("history");
+    let output = common::run_repl("var x = 1;\nprint x;\n", &history);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(common::stdout(&output), "1\n");
+
+    let saved = std::fs::read_to_string(&history).unwrap();
+    assert!(saved.contains("var x = 1;"));
+    assert!(saved.contains("print x;"));
+
+    // An unfinished entry at end of input is dropped.
+    let output = common::run_repl("print 2;\nfun f() {\n", &history);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(common::stdout(&output), "2\n");
+}
+
+#[test]
+fn errors_do_not_end_the_session() {
+    let dir = common::fresh_dir();
+    let history = dir.join("history");
+    let output = common::run_repl("print 1 +;\nprint undefined;\nprint 3;\n", &history);
+    assert_eq!(common::stdout(&output), "3\n");
+    assert!(common::stderr(&output).contains("Expect expression."));
+    assert!(common::stderr(&output).contains("Undefined variable 'undefined'."));
+}
+
+#[test]
+fn bare_expressions_are_echoed() {
+    let dir = common::fresh_dir();
+    let history = dir.join("history");
+    let output = common::run_repl(
+        "1 + 2\n\"hi\"\nvar a = [1, \"x\"];\na\nprint 5; 6\nfun f() {}\nf();\n",
+        &history,
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "3\n\"hi\"\n[1, \"x\"]\n5\n6\n");
+}
+
+#[test]
+fn statements_ending_in_a_bare_expression_still_run() {
+    let dir = common::fresh_dir();
+    let history = dir.join("history");
+    let output = common::run_repl(
+        "var x = 0;\nif (true) x = 1\nx\nwhile (x < 3) x = x + 1\nx\n",
+        &history,
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "1\n3\n");
+}
+
+#[test]
+fn meta_commands() {
+    let dir = common::fresh_dir();
+    std::fs::write(dir.join("lib.lox"), "var loaded = \"yes\";\n").unwrap();
+    let input = format!(
+        ":env\nvar b = 2;\nfun f(n) {{ return n; }}\n:env\n:tokens print \"a\";\n:load {}\nloaded\n:time f(3)\n:reset\n:env\n:bogus\n",
+        dir.join("lib.lox").display()
+    );
+    let output = common::run_repl(&input, &dir.join("history"));
+    let builtins =
+        "E = 2.718281828459045\nINF = inf\nNAN = NaN\nPI = 3.141592653589793\nargs = []\n";
+    let stdout = common::stdout(&output);
+    let (before_time, after_time) = stdout.split_once("took ").unwrap();
+    assert_eq!(
+        before_time,
+        format!(
+            "{builtins}\
+             {builtins}b = 2\nf = <fn f>\n\
+             1 PRINT print\n1 STRING \"a\" a\n1 SEMICOLON ;\n1 EOF \n\
+             \"yes\"\n\
+             3\n"
+        )
+    );
+    assert!(after_time.ends_with(&format!(" ms\n{builtins}")));
+    assert_eq!(
+        common::stderr(&output),
+        "Unknown command ':bogus'. Type :help for a list.\n"
+    );
+}
@@ -0,0 +1,57 @@
+mod common;
+
+#[test]
+fn uncaught_error_prints_traceback() {
+    let output = common::run(
+        r#"
+fun inner(x) {
+  return x + "a";
+}
+
+fun outer(x) {
+  var y = inner(x);
+  return y;
+}
+
+outer(1);
+"#,
+    );
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(
+        common::stderr(&output),
+        "Operands must be two numbers or two strings.\n[line 3]\n  \
+         at inner (line 3)\n  \
+         at outer (line 7)\n  \
+         at <script> (line 11)\n"
+    );
+}
+
+#[test]
+fn error_at_top_level_has_only_script_frame() {
+    let output = common::run("print 1;\nprint -\"a\";\n");
+    assert_eq!(
+        common::stderr(&output),
+        "Operand must be a number.\n[line 2]\n  at <script> (line 2)\n"
+    );
+}
+
+#[test]
+fn tail_call_replaces_caller_frame() {
+    let output = common::run(
+        r#"
+fun first(n) {
+  return second(n);
+}
+fun second(n) {
+  return n + "x";
+}
+first(1);
+"#,
+    );
+    assert_eq!(
+        common::stderr(&output),
+        "Operands must be two numbers or two strings.\n[line 6]\n  \
+         at second (line 6)\n  \
+         at <script> (line 8)\n"
+    );
+}
@@ -0,0 +1,65 @@
+mod common;
+
+#[test]
+fn self_recursion_runs_in_constant_stack() {
+    let output = common::run(
+        r#"
+fun count(n, acc) {
+  if (n <= 0) return acc;
+  return count(n - 1, acc + 1);
+}
+print count(1000000, 0);
+"#,
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "1000000\n");
+}
+
+#[test]
+fn mutual_recursion_runs_in_constant_stack() {
+    let output = common::run(
+        r#"
+fun isEven(n) {
+  if (n <= 0) return true;
+  return isOdd(n - 1);
+}
+fun isOdd(n) {
+  if (n <= 0) return false;
+  return isEven(n - 1);
+}
+print isEven(100001);
+"#,
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "false\n");
+}
+
+#[test]
+fn non_tail_calls_still_return_values() {
+    let output = common::run(
+        r#"
+fun fib(n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+fun twice(x) { return x * 2; }
+fun apply(f, x) { return f(x); }
+print fib(10);
+print apply(twice, 21);
+"#,
+    );
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(common::stdout(&output), "55\n42\n");
+}
+
+#[test]
+fn return_outside_a_function_is_a_syntax_error() {
+    let output = common::run("fun f() { return 1; }\nreturn f();\n{\n  return 2;\n}\n");
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(common::stdout(&output), "");
+    assert_eq!(
+        common::stderr(&output),
+        "[line 2] Error  at 'return': Can't return from top-level code.\n\
+         [line 4] Error  at 'return': Can't return from top-level code.\n"
+    );
+}
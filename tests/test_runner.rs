@@ -0,0 +1,163 @@
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn run_tests(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lox1"))
+        .arg("test")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+const MATH: &str = r#"fun add(a, b) {
+  return a + b;
+}
+var count = 0;
+
+test "adds" {
+  assertEqual(add(1, 2), 3);
+  count = count + 1;
+}
+
+test "starts fresh" {
+  assert(count == 0, "count leaked");
+}
+
+test "adds wrongly" {
+  var sum = add(2, 2);
+  assertEqual(sum, 5);
+  print "unreachable";
+}
+"#;
+
+#[test]
+fn runs_each_test_and_summarizes_failures() {
+    let dir = common::write_tree(&[
+        ("math.lox", MATH),
+        ("more/exits.lox", "test \"exits\" {\n  exit(3);\n}\n"),
+        ("notes.txt", "test \"ignored\" {}\n"),
+    ]);
+    let output = run_tests(&dir, &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        common::stdout(&output),
+        "\
+ok      math.lox: adds
+ok      math.lox: starts fresh
+FAILED  math.lox: adds wrongly
+FAILED  more/exits.lox: exits
+
+Failures:
+  math.lox:17 in adds wrongly: Expected 4 to equal 5.
+  more/exits.lox in exits: Exited with code 3.
+
+4 tests: 2 passed, 2 failed.
+"
+    );
+
+    let output = run_tests(&dir, &["math.lox"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(common::stdout(&output).ends_with("\n3 tests: 2 passed, 1 failed.\n"));
+}
+
+#[test]
+fn reports_syntax_errors_and_passes_without_failures() {
+    let dir = common::write_tree(&[
+        ("ok.lox", "test \"truth\" {\n  assert(true);\n}\n"),
+        ("broken.lox", "test \"broken\" {\n  print ;\n}\n"),
+        ("returns.lox", "test \"returns\" {\n  return 1;\n}\n"),
+    ]);
+    let output = run_tests(&dir, &["ok.lox"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        common::stdout(&output),
+        "ok      ok.lox: truth\n\n1 test: 1 passed, 0 failed.\n"
+    );
+
+    let output = run_tests(&dir, &[]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        common::stderr(&output),
+        "broken.lox:\n\
+         [line 2] Error at ';': Expect expression.\n\
+         returns.lox:\n\
+         [line 2] Error at 'return': Can't return from top-level code.\n"
+    );
+}
+
+#[test]
+fn skips_tests_in_normal_runs() {
+    let output = common::run(
+        r#"var test = "still a name";
+test "never runs" {
+  print "inside";
+}
+print test;
+assert(1 < 2);
+assert(nil, "nil is falsey");
+"#,
+    );
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(common::stdout(&output), "still a name\n");
+    assert_eq!(
+        common::stderr(&output),
+        "Assertion failed: nil is falsey\n[line 7]\n  at assert (native)\n  at <script> (line 7)\n"
+    );
+}
+
+#[test]
+fn tests_must_be_declared_at_the_top_level() {
+    let output = common::run(
+        r#"fun helper() {
+  test "in a function" {}
+}
+if (true) test "in an if" {}
+test "outer" {
+  test "in a test" {}
+}
+"#,
+    );
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        common::stderr(&output),
+        "[line 2] Error  at 'test': Tests must be declared at the top level.\n\
+         [line 4] Error  at 'test': Tests must be declared at the top level.\n\
+         [line 6] Error  at 'test': Tests must be declared at the top level.\n"
+    );
+}
+
+#[test]
+fn top_level_runs_again_for_each_test() {
+    let dir = common::write_tree(&[(
+        "setup.lox",
+        r#"var count = 0;
+print "setting up";
+fun bump() {
+  count = count + 1;
+}
+test "first" {
+  bump();
+  assertEqual(count, 1);
+}
+test "second" {
+  assertEqual(count, 0);
+}
+"#,
+    )]);
+    let output = run_tests(&dir, &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        common::stdout(&output),
+        "\
+setting up
+ok      setup.lox: first
+setting up
+ok      setup.lox: second
+
+2 tests: 2 passed, 0 failed.
+"
+    );
+}
@@ -0,0 +1,86 @@
+mod common;
+
+const SCRIPT: &str = r#"fun add(a, b) {
+  return a + b;
+}
+fun twice(x) {
+  return add(x, x);
+}
+fun fail(message) {
+  throw message;
+}
+print twice(len("abc"));
+try {
+  fail("boom");
+} catch (e) {
+  print e;
+}
+"#;
+
+const TRACE: &str = r#"[line 1] fun add(a, b) {
+[line 4] fun twice(x) {
+[line 7] fun fail(message) {
+[line 10] print twice(len("abc"));
+-> len("abc")
+<- len returned 3
+-> twice(3)
+  [line 5] return add(x, x);
+-> add(3, 3) (tail call)
+  [line 2] return a + b;
+<- add returned 6
+[line 11] try {
+[line 12] fail("boom");
+-> fail("boom")
+  [line 8] throw message;
+<- fail failed: boom
+[line 14] print e;
+"#;
+
+#[test]
+fn traces_statements_and_calls_to_stderr() {
+    let output = common::run_with_args(SCRIPT, &["--trace"]);
+    assert_eq!(common::stdout(&output), "6\nboom\n");
+    assert_eq!(common::stderr(&output), TRACE);
+}
+
+#[test]
+fn traces_to_a_file() {
+    let dir = common::fresh_dir();
+    let log = dir.join("trace.log");
+    let output = common::run_with_args(SCRIPT, &["--trace-file", log.to_str().unwrap()]);
+    assert_eq!(common::stdout(&output), "6\nboom\n");
+    assert_eq!(common::stderr(&output), "");
+    assert_eq!(std::fs::read_to_string(log).unwrap(), TRACE);
+}
+
+#[test]
+fn indents_nested_calls_and_module_statements() {
+    let dir = common::write_tree(&[
+        (
+            "main.lox",
+            "import \"util.lox\" as util;\nprint util.square(util.square(2));\n",
+        ),
+        (
+            "util.lox",
+            "fun square(n) {\n  var result = n * n;\n  return result;\n}\n",
+        ),
+    ]);
+    let output = common::run_file(&dir.join("main.lox"), &["--trace"]);
+    assert_eq!(common::stdout(&output), "16\n");
+    assert_eq!(
+        common::stderr(&output),
+        "\
+[line 1] import \"util.lox\" as util;
+[line 1] fun square(n) {
+[line 2] print util.square(util.square(2));
+-> square(2)
+  [line 2] var result = n * n;
+  [line 3] return result;
+<- square returned 4
+-> square(4)
+  [line 2] var result = n * n;
+  [line 3] return result;
+<- square returned 16
+"
+    );
+}